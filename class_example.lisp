@@ -0,0 +1,22 @@
+(import "class.lisp" (class! call new))
+
+(class! Animal (
+    init (self noise) (assoc self :noise noise)
+    speak (self) (print (:noise self))
+))
+
+(class! Dog Animal (
+    init (self name) (assoc (call self super "WOOF") :name name)
+    bark (self) (print (:name self) "says WROOF")
+    say (self msg) (print (:name self) "says" msg)
+))
+
+(def! tucky (new Dog "Tucky"))
+
+(call tucky bark)
+(call tucky say "helo")
+(call tucky speak)
+
+(def! froge (new Animal "Ribbit"))
+
+(call froge speak)
@@ -1,48 +1,239 @@
 #![allow(clippy::module_name_repetitions)]
 use std::{cell::RefCell, collections::HashMap, rc::Rc};
 
+use smallvec::SmallVec;
+
 use crate::{
-    eval::{self, builtins},
+    eval::{self, builtins, scope},
+    gc,
+    module::Modules,
     parser::parse,
-    types::Value,
+    types::{symbol::SymbolMap, Symbol, Value},
 };
 
+/// State shared by every environment in one interpreter
+#[derive(Default)]
+pub struct Runtime {
+    /// Documentation for builtins, special forms, and documented definitions
+    pub docs: RefCell<HashMap<String, String>>,
+    /// Documentation waiting to be attached to the next definition
+    pub next_doc: RefCell<String>,
+    /// The modules this interpreter has imported
+    pub(crate) modules: Modules,
+}
+
+/// The names bound directly in one environment. Local scopes are far more common, so they are kept inline
+/// rather than boxed.
+#[allow(clippy::large_enum_variant)]
+enum Bindings {
+    /// A scope made while evaluating, like the call of a lambda or a `let*`, which binds a few names
+    Local(SmallVec<[(Symbol, Value); 4]>),
+    /// The top level of a program or module, where everything is defined
+    Global(SymbolMap<Value>),
+}
+
 pub struct EnvData {
     parent: Option<Env>,
-    data: HashMap<String, Value>,
+    bindings: Bindings,
+    /// Whether the names a local scope was made for are still being bound
+    binding: bool,
+    /// Whether `def!`, `import` or `require` has defined a name in this local scope
+    defined: bool,
+    /// Whether the form evaluated in this local scope could define a name in it
+    open: bool,
+    runtime: Rc<Runtime>,
 }
 
 impl EnvData {
+    /// The value of a name bound directly in this environment, not in its parents
+    pub(crate) fn lookup(&self, k: Symbol) -> Option<&Value> {
+        match &self.bindings {
+            Bindings::Local(slots) => slots.iter().find(|(name, _)| *name == k).map(|(_, v)| v),
+            Bindings::Global(data) => data.get(&k),
+        }
+    }
+
+    #[must_use]
+    pub fn get(&self, k: Symbol) -> Option<Value> {
+        if let Some(v) = self.lookup(k) {
+            return Some(v.clone());
+        }
+        let mut env = self.parent.clone()?;
+        loop {
+            let parent = {
+                let data = env.borrow();
+                if let Some(v) = data.lookup(k) {
+                    return Some(v.clone());
+                }
+                data.parent.clone()?
+            };
+            env = parent;
+        }
+    }
+
+    /// Bind a name, replacing its value if it is already bound here
+    pub fn set(&mut self, k: Symbol, v: Value) {
+        scope::bound(k, &v);
+        match &mut self.bindings {
+            Bindings::Local(slots) => match slots.iter_mut().find(|(name, _)| *name == k) {
+                Some((_, slot)) => *slot = v,
+                None => slots.push((k, v)),
+            },
+            Bindings::Global(data) => {
+                data.insert(k, v);
+            }
+        }
+    }
+
+    /// Define a name, as `def!` does. Unlike the names a local scope is made for, lambdas can't know about
+    /// it ahead of time.
+    pub fn define(&mut self, k: Symbol, v: Value) {
+        self.defined = true;
+        self.set(k, v);
+    }
+
+    /// Replace the value of each binding that `f` gives a new value for
+    pub(crate) fn replace_values(&mut self, mut f: impl FnMut(&Value) -> Option<Value>) {
+        match &mut self.bindings {
+            Bindings::Local(slots) => {
+                for (_, value) in slots {
+                    if let Some(new) = f(value) {
+                        *value = new;
+                    }
+                }
+            }
+            Bindings::Global(data) => {
+                let replaced: Vec<_> = data.iter().filter_map(|(k, v)| Some((*k, f(v)?))).collect();
+                for (k, v) in replaced {
+                    data.insert(k, v);
+                }
+            }
+        }
+    }
+
+    /// Mark the names a local scope was made for as bound, before `body` is evaluated in it
+    pub(crate) fn seal(&mut self, body: &Value) {
+        self.binding = false;
+        self.open = scope::can_define(body);
+    }
+
+    /// Whether this is a local scope whose names are all bound and can't change, so a lambda can copy
+    /// them rather than keep the scope
+    pub(crate) const fn is_fixed(&self) -> bool {
+        matches!(self.bindings, Bindings::Local(_)) && !self.binding && !self.defined && !self.open
+    }
+
+    /// Whether a name has been defined in this scope after it was made
+    pub(crate) const fn is_defined(&self) -> bool {
+        self.defined
+    }
+
+    /// The name and value in a slot of a local scope
+    pub(crate) fn slot(&self, index: usize) -> Option<(Symbol, &Value)> {
+        match &self.bindings {
+            Bindings::Local(slots) => slots.get(index).map(|(name, v)| (*name, v)),
+            Bindings::Global(_) => None,
+        }
+    }
+
+    pub(crate) const fn parent(&self) -> Option<&Env> {
+        self.parent.as_ref()
+    }
+
+    /// Every name visible from this environment, including those defined in its parents
+    #[must_use]
+    pub fn names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.bindings().map(|(k, _)| k.to_string()).collect();
+        if let Some(parent) = &self.parent {
+            names.extend(parent.borrow().names());
+        }
+        names
+    }
+
+    /// The values defined directly in this environment, not including its parents
     #[must_use]
-    pub fn get(&self, k: &str) -> Option<Value> {
-        if let Some(v) = self.data.get(k) {
-            Some(v.clone())
-        } else {
-            self.parent.as_ref()?.borrow_mut().get(k)
+    pub fn bindings(&self) -> Box<dyn Iterator<Item = (Symbol, &Value)> + '_> {
+        match &self.bindings {
+            Bindings::Local(slots) => Box::new(slots.iter().map(|(k, v)| (*k, v))),
+            Bindings::Global(data) => Box::new(data.iter().map(|(k, v)| (*k, v))),
         }
     }
 
-    pub fn set(&mut self, k: &str, v: Value) {
-        self.data.insert(k.to_string(), v);
+    /// Unbind every name and let go of the parent, to break a cycle the environment is part of. Returns
+    /// what it held, so it can be dropped once every cycle is broken.
+    pub(crate) fn clear(&mut self) -> (Option<Env>, Vec<Value>) {
+        let values = match std::mem::replace(&mut self.bindings, Bindings::Local(SmallVec::new())) {
+            Bindings::Local(slots) => slots.into_iter().map(|(_, v)| v).collect(),
+            Bindings::Global(data) => data.into_values().collect(),
+        };
+        (self.parent.take(), values)
+    }
+
+    /// The state of the interpreter this environment belongs to
+    #[must_use]
+    pub fn runtime(&self) -> Rc<Runtime> {
+        self.runtime.clone()
+    }
+}
+
+impl Drop for EnvData {
+    fn drop(&mut self) {
+        gc::freed(&gc::Kind::Env);
     }
 }
 
 pub type Env = Rc<RefCell<EnvData>>;
 
-pub fn new_env(parent: Env) -> Env {
+fn child(parent: Env, bindings: Bindings) -> Env {
+    let runtime = parent.borrow().runtime();
+    gc::allocated(&gc::Kind::Env);
     Rc::new(RefCell::new(EnvData {
         parent: Some(parent),
-        data: HashMap::new(),
+        bindings,
+        binding: true,
+        defined: false,
+        open: false,
+        runtime,
     }))
 }
 
+/// Make a local scope inside another environment. Call [`EnvData::seal`] once the names it is for are bound.
+pub fn new_env(parent: Env) -> Env {
+    child(parent, Bindings::Local(SmallVec::new()))
+}
+
+/// Make a local scope that already has all of its names bound
+pub(crate) fn new_sealed(parent: Env, slots: SmallVec<[(Symbol, Value); 4]>) -> Env {
+    let env = child(parent, Bindings::Local(slots));
+    env.borrow_mut().binding = false;
+    env
+}
+
+/// Make a top level environment for definitions, like that of a module, inside another environment
+pub fn new_global(parent: Env) -> Env {
+    child(parent, Bindings::Global(SymbolMap::default()))
+}
+
+/// Get the outermost environment, which holds the builtins and stdlib
+#[must_use]
+pub fn root(env: &Env) -> Env {
+    let mut env = env.clone();
+    loop {
+        let parent = env.borrow().parent.clone();
+        match parent {
+            Some(parent) => env = parent,
+            None => return env,
+        }
+    }
+}
+
 macro_rules! builtins {
     ($($name:literal $($val:expr)? $(=>$doc:literal)?);*$(;)?) => {{
-        let mut data = HashMap::new();
+        let mut data = SymbolMap::default();
         let mut docs: HashMap<String,String> = HashMap::new();
         $(
             $(
-                data.insert($name.to_string(), $crate::types::Value::function(Rc::from($val)));
+                data.insert(Symbol::intern($name), $crate::types::Value::function(Rc::from($val)));
             )?
             $(
                 docs.insert($name.to_string(), $doc.to_string());
@@ -60,33 +251,104 @@ pub fn default_env(args: Rc<[Value]>) -> Env {
         "+" builtins::add => "Calculate the sum of the arguments";
         "-" builtins::sub => "Calculate the difference between arguments. One argument calculates the negative.";
         "*" builtins::mul => "Calculate the product of the arguments.";
-        "/" builtins::div => "Divide the two arguments, rounding down.";
-        "%" builtins::div => "Calculate the remainder when dividing the two arguments.";
+        "/" builtins::div => "Divide the first argument by the rest. Dividing ints produces an exact ratio unless the result is whole. One argument calculates the reciprocal.";
+        "quot" builtins::quot => "Divide the two arguments, rounding toward zero.";
+        "%" builtins::rem => "Calculate the remainder when dividing the two arguments.";
         "=" builtins::eq => "Check if two values are equal";
+        "==" builtins::num_eq => "Check if two numerical values are equal, regardless of whether they are ints, ratios, or floats";
+        "assert=" builtins::assert_eq => "Raise an AssertFailed error unless the expected value, given first, equals the actual value";
+        "assert-raises" builtins::assert_raises => "Call a function with no arguments, raising an ExpectedError error unless it raises an error with one of the names in the list. An empty list accepts any error. Usually used through assert-err.";
         "<" builtins::lt => "Check if one numerical value is less than antother";
         "<=" builtins::le => "Check if one numerical value is less than or equal to another";
         ">" builtins::gt => "Check if a numerical value is greater than another";
         ">=" builtins::ge => "Check if a numerical value is greater than or equal to another";
         "print" builtins::print => "Print the arguments to stdout";
         "input" builtins::input => "Read a line of input from stdin";
-        "findall" builtins::findall => "Search a string for all non-overlapping matches to a regular expression";
+        "slurp" builtins::fs::slurp => "Read the whole contents of a file as a string";
+        "spit" builtins::fs::spit => "Write a value to a file as a string, replacing the file's contents. Creates the file if it doesn't exist.";
+        "append-file" builtins::fs::append_file => "Write a value to the end of a file as a string. Creates the file if it doesn't exist.";
+        "read-lines" builtins::fs::read_lines => "Read a file as a list of lines, without their line endings";
+        "file-exists?" builtins::fs::file_exists => "Check if a file or directory exists at a path";
+        "dir?" builtins::fs::is_dir => "Check if a path is a directory";
+        "list-dir" builtins::fs::list_dir => "List the names of the entries in a directory, sorted";
+        "mkdir" builtins::fs::mkdir => "Create a directory, along with any missing parent directories";
+        "delete-file" builtins::fs::delete_file => "Delete a file or an empty directory";
+        "path-join" builtins::fs::path_join => "Join path components with the path separator. A component that is an absolute path replaces everything before it.";
+        "path-dir" builtins::fs::path_part("path-dir", builtins::fs::parent) => "Get the directory part of a path, or nil if it has none";
+        "path-name" builtins::fs::path_part("path-name", std::path::Path::file_name) => "Get the final component of a path, or nil if it has none";
+        "path-stem" builtins::fs::path_part("path-stem", std::path::Path::file_stem) => "Get the final component of a path without its extension, or nil if it has none";
+        "path-ext" builtins::fs::path_part("path-ext", std::path::Path::extension) => "Get the extension of a path without the dot, or nil if it has none";
+        "path-absolute" builtins::fs::path_absolute => "Get the absolute form of a path, resolving symlinks. The path must exist.";
+        "findall" builtins::findall => "Search a string for all non-overlapping matches to a regular expression, returning the groups that matched in each non-empty match";
+        "regex" builtins::re::regex => "Compile a regular expression. Flags can be given as a string of i (case-insensitive), m (multi-line), s (. matches newlines), and x (ignore whitespace and comments). The re- functions also accept patterns as strings.";
+        "re-match" builtins::re::re_match => "Match a regular expression against a whole string. Returns a match table, or nil if the string doesn't match. A match table holds the matched text, its start and end indices, the list of groups, and a table of named groups, under the keywords :text, :start, :end, :groups, and :named.";
+        "re-find" builtins::re::re_find => "Find the first match of a regular expression in a string, optionally starting at an index. Returns a match table, or nil if there is none.";
+        "re-find-all" builtins::re::re_find_all => "Find every non-overlapping match of a regular expression in a string, as a list of match tables";
+        "re-replace" builtins::re::re_replace => "Replace every match of a regular expression in a string. The replacement is either a string, where $1 or ${name} refer to groups, or a function that is called with each match table.";
+        "re-split" builtins::re::re_split => "Split a string on every match of a regular expression";
         "eval" builtins::eval => "Evaluate a given expression as code";
-        "apply" builtins::apply => "Apply the given function using the given arguments.";
+        "apply" builtins::apply_fn() => "Apply the given function using the given arguments. In tail position, this is a tail call.";
+        "max-depth" builtins::max_depth => "Get how many evaluations can be nested inside each other before a StackOverflow error is raised, or give a number to change it. Calls in tail position, including through apply, don't nest.";
+        "gc" builtins::collect => "Free the environments, atoms and lazy sequences that can only be reached through cycles, such as a lambda stored in the scope it captures, returning how many were freed. Runs on its own as lambdas and atoms are made.";
+        "gc-stats" builtins::gc_stats => "Get a table counting the live :envs, :atoms and :lazy-seqs, the objects :tracked for cycles, and how many :collections have freed how many objects :collected";
         "str" builtins::str => "Concatenate the arguments into a string";
         "symbol" builtins::symbol => "Convert a string to a symbol";
-        "int" builtins::int => "Convert a string to an int";
+        "keyword" builtins::keyword => "Convert a string or symbol to a keyword. Keywords are written :name and evaluate to themselves. Called with a table, a keyword gets its value from the table, or nil or the given default if it isn't there.";
+        "int" builtins::int => "Convert a string to an int, or truncate a number to an int";
+        "float" builtins::float => "Convert a number or a string to a float";
+        "round" builtins::round => "Round a number to the nearest int, rounding halfway cases away from zero";
+        "floor" builtins::floor => "Round a number down to an int";
+        "ceil" builtins::ceil => "Round a number up to an int";
+        "numerator" builtins::numerator => "Get the numerator of a ratio or int";
+        "denominator" builtins::denominator => "Get the denominator of a ratio or int";
         "chr" builtins::chr => "Convert an integer to its corresponding character in UTF-8";
+        "ord" builtins::string::ord => "Convert a single character string to its code point, the inverse of chr";
+        "substr" builtins::string::substr => "Get the part of a string from a start index up to, but not including, an end index. Omit the end to go to the end of the string. Indices count characters.";
+        "split" builtins::string::split => "Split a string on a separator into a list of strings. Without a separator, split on whitespace and drop empty parts.";
+        "join" builtins::string::join => "Join a list of values into a string, with an optional separator between them";
+        "trim" builtins::string::trim => "Remove whitespace from the start and end of a string. Given a sequence and a count, remove that many values from its start, as drop does.";
+        "upper" builtins::string::map_str("upper", str::to_uppercase) => "Convert a string to uppercase";
+        "lower" builtins::string::map_str("lower", str::to_lowercase) => "Convert a string to lowercase";
+        "starts-with?" builtins::string::starts_with => "Check if a string starts with a prefix";
+        "ends-with?" builtins::string::ends_with => "Check if a string ends with a suffix";
+        "index-of" builtins::string::index_of => "Find the character index where a substring first appears in a string, or nil if it doesn't";
+        "replace" builtins::string::replace => "Replace every occurrence of a substring in a string";
+        "chars" builtins::string::chars => "Split a string into a list of single character strings";
+        "format" builtins::string::format => "Fill in each {} in a string with the next argument, as str would write it. Write {{ and }} for literal braces.";
+        "map" builtins::seq::map => "Call a function on each value of a list or character of a string, returning a list of the results. Mapping over a lazy sequence gives a lazy sequence.";
+        "filter" builtins::seq::filter => "Keep the values of a list or characters of a string for which a function returns a truthy value. Filtering a lazy sequence gives a lazy sequence.";
+        "reduce" builtins::seq::reduce => "Combine the values of a sequence with a function of the result so far and the next value, starting from an initial value if one is given and the first value otherwise";
+        "zip" builtins::seq::zip => "Pair up the values of sequences into a list of lists, stopping at the end of the shortest";
+        "take" builtins::seq::take => "Get the first n values of a list, lazy sequence, or characters of a string";
+        "drop" builtins::seq::drop => "Remove the first n values of a list, lazy sequence, or characters of a string";
+        "sort" builtins::seq::sort => "Sort a list or string. Numbers sort by value, and strings, symbols and keywords alphabetically. Give a function to sort by the result of calling it on each value.";
+        "reverse" builtins::seq::reverse => "Reverse a list or string";
+        "concat" builtins::seq::concat => "Join sequences together. Strings join into a string, and anything else into a list.";
+        "flatten" builtins::seq::flatten => "Flatten nested lists into a single list";
+        "group-by" builtins::seq::group_by => "Group the values of a sequence into a table of lists, keyed by the result of calling a function on each value";
+        "range" builtins::seq::range => "Count from a start, or from 0, up to, but not including, an end, by a step of 1 unless another is given. Without any arguments, count up from 0 forever, as a lazy sequence.";
+        "repeat" builtins::seq::repeat => "Create a list with a value repeated a number of times. Without a number, repeat the value forever, as a lazy sequence.";
+        "iterate" builtins::seq::iterate => "Create a lazy sequence of a value, then the result of calling a function on it, then the result of calling the function on that, and so on forever";
+        "cycle" builtins::seq::cycle => "Create a lazy sequence that repeats the values of a sequence forever";
+        "realized?" builtins::seq::realized => "Check if the first value of a lazy sequence has been computed";
         "nth" builtins::nth => "Get the nth value of a sequence";
-        "count" builtins::count => "Find the size of a sequence";
+        "count" builtins::count => "Find the size of a sequence. The size of a string is its number of characters.";
         "assoc" builtins::assoc => "Return a table with the additional keys and values combined with the original";
         "dissoc" builtins::dissoc => "Return a table without the specified keys";
         "first" builtins::first => "Get the first value of a sequence.";
         "last" builtins::last => "Get the last value of a sequence.";
-        "rest" builtins::rest => "Get a copy of a list without its first value";
+        "rest" builtins::rest => "Get a list without its first value. The new list shares structure with the original rather than copying it.";
+        "conj" builtins::conj => "Return a list with the values added to the end, or a set with the values added";
         "get" builtins::get => "Get the value associated with a given key in a table";
         "keys" builtins::keys => "Get a table's keys as a sequence";
         "values" builtins::values => "Get a table's values as a sequence";
-        "contains?" builtins::contains => "Check if a table contains a key";
+        "contains?" builtins::contains => "Check if a table contains a key, or a set contains a value";
+        "set" builtins::set::set => "Create a set of the values in a list, or the keys of a table";
+        "union" builtins::set::union => "Combine sets into a set of the values in any of them";
+        "intersection" builtins::set::intersection => "Get the set of values that are in every given set";
+        "difference" builtins::set::difference => "Get the set of values in the first set that aren't in any of the others";
+        "subset?" builtins::set::subset => "Check if every value in the first set is in the second";
+        "disj" builtins::set::disj => "Return a set without the given values";
         "type" builtins::typ => "Get the type of a value, as a symbol";
         "err?" builtins::type_is("err") => "Check if the value is an error";
         "function?" builtins::type_is("function") => "Check if the value is a function";
@@ -94,29 +356,61 @@ pub fn default_env(args: Rc<[Value]>) -> Env {
         "macro?" builtins::type_is("macro") => "Check if the value is a macro";
         "list?" builtins::type_is("list") => "Check if the value is a list";
         "table?" builtins::type_is("table") => "Check if the value is a table";
+        "set?" builtins::type_is("set") => "Check if the value is a set";
+        "lazy-seq?" builtins::type_is("lazy-seq") => "Check if the value is a lazy sequence";
         "nil?" builtins::type_is("nil") => "Check if the value is nil";
         "bool?" builtins::type_is("bool") => "Check if the value is a boolean";
         "symbol?" builtins::type_is("symbol") => "Check if the value is a symbol";
+        "keyword?" builtins::type_is("keyword") => "Check if the value is a keyword";
         "int?" builtins::type_is("int") => "Check if the value is an integer";
+        "float?" builtins::type_is("float") => "Check if the value is a float";
+        "ratio?" builtins::type_is("ratio") => "Check if the value is a ratio";
+        "regex?" builtins::type_is("regex") => "Check if the value is a compiled regular expression";
         "macro" builtins::as_macro => "Convert a function to a macro. The function should take syntax as an input and produce it as output.";
+        "gensym" builtins::syntax::gensym => "Make a symbol that hasn't been used yet, for a macro to bind without capturing names in the code it is given. Give a string or symbol to start its name with. In a quasiquoted template, `name#` does the same.";
+        "syntax-rules" builtins::syntax::syntax_rules => "Make a macro from a list of literal symbols and (pattern template) rules, usually through defsyntax!. The first rule whose pattern matches the macro's arguments fills in its template. Symbols in a pattern bind what they match, except for _ and the literals, and a pattern followed by ... matches any number of forms.";
+        "macroexpand-1" builtins::syntax::macroexpand_1 => "Expand a quoted form once if it is headed by a macro, returning it unchanged if it isn't";
+        "macroexpand" builtins::syntax::macroexpand => "Expand a quoted form until it is no longer headed by a macro. The forms within it are left as they are.";
+        "macroexpand-all" builtins::syntax::macroexpand_all => "Expand a quoted form and every form within it that is evaluated as code, leaving quoted forms alone";
         "atom" builtins::atom => "Create a new atom with the given value inside it";
         "set!" builtins::set_atom => "Set the value inside an atom, returning the original value";
         "inspect!" builtins::inspect_atom => "Modify the value in the atom with a function";
-        "\\" => "Create a lambda function that accepts the given parameters and returns the result of evaluating the body expression";
+        "\\" => "Create a lambda function that accepts the given parameters and returns the result of evaluating the body expression. The values of the local variables the body uses are copied when the lambda is made; globals are looked up when it is called.";
         "if" => "If the first value is truthy, evaluate and return the second value. Otherwise, evaluate and return the third value.";
         "quote" => "Return the arguments without evaluating them";
-        "quasiquote" => "Return the arguments, only evaluating the parts within a (unquote ...) expression";
+        "quasiquote" => "Return the arguments, only evaluating the parts within a (unquote ...) expression, written ~x. (splice-unquote ...), written ~@x, evaluates to a list whose values are spliced into the list around it. A nested quasiquote is left for later, except for what is unquoted once more for each level.";
         "err" => "Throw an error with the provided information. The first argument is not evaluated and should be an identifier.";
         "let*" => "The first argument is an alternating list of patterns and values. Each value is evaluated in order and bound to its corresponding pattern. Returns the result of evaluating the second argument with the context of the bindings created from the first argument.";
+        "lazy-seq" => "Create a lazy sequence from an expression that evaluates to a sequence. The expression isn't evaluated until a value of the sequence is needed, and only once.";
+        "doseq" => "Evaluate the body once for each value of a sequence, bound to the pattern, as in (doseq (x seq) body...). Lazy sequences are realized one value at a time. Returns nil.";
+        "loop" => "Bind patterns to values as let* does, then evaluate the body. A recur in tail position in the body evaluates the body again with the patterns bound to new values.";
+        "recur" => "Evaluate the arguments and jump back to the innermost loop or lambda with them as its new values, without growing the stack. Must be in tail position.";
         "def!" => "Define a variable, providing its name and a value";
         "try*" => "Attempt to evaluate the first argument. If an exception is thrown, goes to each catch block to recover";
         "doc" => "Attach documentation to the next value that is defined";
+        "import" => "Load a file as a module, relative to the current file. Each module is only evaluated once. With just a path, its definitions are accessible as `name/definition`, where `name` is the file name without its extension. Provide a list of names after the path to define them directly instead.";
+        "require" => "Load a file as a module, relative to the current file, and define everything it defines directly in the current environment";
         "help" => "Retrieve the documentation for a function";
         "*ARGS*" => "Arguments provided in the command line"
     );
-    data.insert("*ARGS*".to_string(), Value::List(args));
-    let env = Rc::new(RefCell::new(EnvData { parent: None, data }));
-    eval::eval(parse(include_str!("../stdlib.lisp")).unwrap(), env.clone()).unwrap();
-    *eval::DOCS.write().unwrap() = docs;
+    data.insert(Symbol::intern("*ARGS*"), Value::List(args.into()));
+    let runtime = Rc::new(Runtime {
+        docs: RefCell::new(docs),
+        ..Runtime::default()
+    });
+    gc::allocated(&gc::Kind::Env);
+    let env = Rc::new(RefCell::new(EnvData {
+        parent: None,
+        bindings: Bindings::Global(data),
+        binding: false,
+        defined: false,
+        open: false,
+        runtime,
+    }));
+    eval::eval(
+        parse(include_str!("../stdlib.lisp"), "stdlib.lisp").unwrap(),
+        env.clone(),
+    )
+    .unwrap();
     env
 }
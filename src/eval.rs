@@ -1,51 +1,208 @@
-use std::{
-    collections::HashMap,
-    rc::Rc,
-    sync::{LazyLock, RwLock},
-};
+use core::{cell::Cell, str::FromStr};
+
+use im_rc::{HashMap, HashSet};
 
 use crate::{
     env::{new_env, Env},
-    types::Value,
+    gc, module,
+    span::{self, Span},
+    trace::{self, Frame, FrameGuard},
+    types::{
+        lazy::{self, LazySeq, Thunk},
+        List, Symbol, Value,
+    },
 };
 
 pub mod builtins;
+mod compile;
+pub mod expand;
+pub(crate) mod scope;
+mod vm;
+
+/// How many evaluations can be nested inside each other before a `StackOverflow` error is raised
+pub const DEFAULT_MAX_DEPTH: usize = 10_000;
 
-pub static DOCS: LazyLock<RwLock<HashMap<String, String>>> =
-    LazyLock::new(|| RwLock::new(HashMap::new()));
-pub static NEXT_DOC: RwLock<String> = RwLock::new(String::new());
+/// Stack that must be left before an evaluation moves onto a new stack segment
+const RED_ZONE: usize = 256 * 1024;
+/// The size of each new stack segment
+const STACK_SEGMENT: usize = 4 * 1024 * 1024;
+
+/// The ways code can be evaluated. Both give the same results; the choice exists so they can be compared.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Engine {
+    /// Walk the syntax tree of each form as it is evaluated
+    #[default]
+    Tree,
+    /// Compile each form to bytecode once, and run the bytecode on a stack machine
+    Vm,
+}
+
+impl FromStr for Engine {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "tree" => Ok(Self::Tree),
+            "vm" => Ok(Self::Vm),
+            _ => Err(format!("unknown engine `{s}`, expected `tree` or `vm`")),
+        }
+    }
+}
+
+thread_local! {
+    /// How many evaluations are in progress
+    static DEPTH: Cell<usize> = const { Cell::new(0) };
+    static MAX_DEPTH: Cell<usize> = const { Cell::new(DEFAULT_MAX_DEPTH) };
+    static ENGINE: Cell<Engine> = const { Cell::new(Engine::Tree) };
+}
+
+/// The engine that evaluates code
+#[must_use]
+pub fn engine() -> Engine {
+    ENGINE.get()
+}
+
+/// Change the engine that evaluates code
+pub fn set_engine(engine: Engine) {
+    ENGINE.set(engine);
+}
+
+/// The most evaluations that can be nested inside each other
+#[must_use]
+pub fn max_depth() -> usize {
+    MAX_DEPTH.get()
+}
+
+/// Change how many evaluations can be nested inside each other
+pub fn set_max_depth(depth: usize) {
+    MAX_DEPTH.set(depth);
+}
+
+/// Counts an evaluation as in progress until it is dropped
+struct DepthGuard(());
+
+impl DepthGuard {
+    fn enter() -> Result<Self, Value> {
+        let depth = DEPTH.get();
+        if depth >= MAX_DEPTH.get() {
+            let depth = i128::try_from(depth).unwrap_or(i128::MAX);
+            return Err(Value::error("StackOverflow", vec![Value::Int(depth)]));
+        }
+        DEPTH.set(depth + 1);
+        Ok(Self(()))
+    }
+}
+
+impl Drop for DepthGuard {
+    fn drop(&mut self) {
+        DEPTH.set(DEPTH.get() - 1);
+    }
+}
+
+/// Where `recur` jumps back to: the innermost `loop` or lambda whose body is in tail position
+struct Recur {
+    /// The pattern the new values are bound to
+    pattern: Value,
+    body: Value,
+    /// The environment the bindings are made in a child of
+    env: Env,
+    /// How many values a `loop` takes, or `None` for a lambda, whose pattern decides
+    arity: Option<usize>,
+}
 
 /// syntax => value
+///
+/// Errors are located at the innermost parsed form that was being evaluated when they were raised.
+/// Evaluations nested more than [`max_depth`] deep raise `(StackOverflow depth)`.
+#[allow(clippy::missing_errors_doc)]
+pub fn eval(syn: Value, env: Env) -> Result<Value, Value> {
+    match engine() {
+        Engine::Tree => walk(syn, env, None),
+        Engine::Vm => vm::eval(&syn, env),
+    }
+}
+
+/// Evaluate the body of a lambda that has had its arguments bound in `env`, so that `recur` in the body
+/// calls the lambda again
+pub(crate) fn eval_lambda(
+    params: Value,
+    body: Value,
+    captures: Env,
+    env: Env,
+) -> Result<Value, Value> {
+    if engine() == Engine::Vm {
+        return vm::eval_lambda(params, &body, captures, env);
+    }
+    let recur = Recur {
+        pattern: params,
+        body: body.clone(),
+        env: captures,
+        arity: None,
+    };
+    walk(body, env, Some(recur))
+}
+
+/// Evaluate with the tree-walking engine
+fn walk(syn: Value, env: Env, recur: Option<Recur>) -> Result<Value, Value> {
+    let _depth = DepthGuard::enter()?;
+    let mut location = None;
+    // deep recursion moves onto the heap rather than overflowing the thread's stack
+    stacker::maybe_grow(RED_ZONE, STACK_SEGMENT, || {
+        eval_located(syn, env, recur, &mut location)
+    })
+    .inspect_err(|err| {
+        if let Some(location) = location {
+            span::locate_error(err, location);
+        }
+    })
+}
+
+/// Evaluate a form, capturing the trace of any error while the frame of the lambda being evaluated is
+/// still on the call stack
+fn eval_located(
+    syn: Value,
+    env: Env,
+    recur: Option<Recur>,
+    location: &mut Option<Span>,
+) -> Result<Value, Value> {
+    // the frame for the lambda this invocation is evaluating; tail calls replace it
+    let mut frame: Option<FrameGuard> = None;
+    eval_in_frame(syn, env, recur, location, &mut frame).inspect_err(trace::capture)
+}
+
 /// # Panics
 /// Whenever :3
-#[allow(clippy::too_many_lines, clippy::missing_errors_doc)]
-pub fn eval(mut syn: Value, mut env: Env) -> Result<Value, Value> {
+#[allow(clippy::too_many_lines)]
+fn eval_in_frame(
+    mut syn: Value,
+    mut env: Env,
+    mut recur: Option<Recur>,
+    location: &mut Option<Span>,
+    frame: &mut Option<FrameGuard>,
+) -> Result<Value, Value> {
     let mut cons: Vec<Value> = Vec::new();
     let out = 'main: loop {
         match syn {
-            Value::List(ref arr) => {
+            Value::List(ref list) => {
+                if let Some(span) = span::of(list) {
+                    *location = Some(span);
+                }
+                let arr = list.values();
                 if arr.is_empty() {
-                    break 'main Value::List(Rc::new([]));
+                    break 'main Value::List(List::new());
                 }
-                if arr[0].is_symbol("\\") {
+                if arr[0].is_symbol(Symbol::LAMBDA) {
                     let [param, body] = &arr[1..] else {
                         return Err(Value::error("InvalidLambdaError", arr.to_vec()));
                     };
-                    let body = body.clone();
-                    let sub_env = new_env(env);
-                    break 'main Value::Lambda {
-                        args: Box::new(param.clone()),
-                        body: Box::new(body),
-                        captures: sub_env,
-                        is_macro: false,
-                    };
-                } else if arr[0].is_symbol("if") {
+                    break 'main lambda(param, body, &env);
+                } else if arr[0].is_symbol(Symbol::IF) {
                     match &arr[1..] {
                         [condition, t] => {
                             if eval(condition.clone(), env.clone())?.is_truthy() {
                                 syn = t.clone();
                             } else {
-                                break 'main Value::symbol("nil");
+                                break 'main Value::nil();
                             }
                         }
                         [condition, t, f] => {
@@ -57,11 +214,11 @@ pub fn eval(mut syn: Value, mut env: Env) -> Result<Value, Value> {
                         }
                         _ => return Err(Value::error("InvalidArgs@if", vec![syn.clone()])),
                     }
-                } else if arr[0].is_symbol("quote") {
+                } else if arr[0].is_symbol(Symbol::QUOTE) {
                     break 'main arr[1].clone();
-                } else if arr[0].is_symbol("quasiquote") {
-                    break 'main arr[1].quasiquote(env)?;
-                } else if arr[0].is_symbol("err") {
+                } else if arr[0].is_symbol(Symbol::QUASIQUOTE) {
+                    break 'main arr[1].quasiquote(&env)?;
+                } else if arr[0].is_symbol(Symbol::ERR) {
                     // if it is an error, evaluate everything but the first and return itself
                     return Err(Value::List(
                         // start with the first value unchanged
@@ -78,10 +235,39 @@ pub fn eval(mut syn: Value, mut env: Env) -> Result<Value, Value> {
                             )
                             .collect(),
                     ));
-                } else if arr[0].is_symbol("cons") {
+                } else if arr[0].is_symbol(Symbol::CONS) {
                     cons.push(eval(arr[1].clone(), env.clone())?);
                     syn = arr[2].clone();
-                } else if arr[0].is_symbol("let*") {
+                } else if arr[0].is_symbol(Symbol::LAZY_SEQ) {
+                    let [_, body] = &arr[..] else {
+                        return Err(Value::error("InvalidArgs@lazy-seq", arr.to_vec()));
+                    };
+                    break 'main LazySeq::from_thunk(Thunk::Body {
+                        body: body.clone(),
+                        env,
+                    });
+                } else if arr[0].is_symbol(Symbol::DOSEQ) {
+                    let Some((Value::List(binding), body)) = arr[1..].split_first() else {
+                        return Err(Value::error("InvalidArgs@doseq", arr.to_vec()));
+                    };
+                    let binding = binding.values();
+                    let [pattern, seq] = &binding[..] else {
+                        return Err(Value::error("InvalidArgs@doseq", arr.to_vec()));
+                    };
+                    let mut seq = eval(seq.clone(), env.clone())?;
+                    while let Some((value, rest)) = lazy::step(&seq)? {
+                        let sub_env = new_env(env.clone());
+                        if destructure(pattern, value, &sub_env).is_none() {
+                            return Err(Value::error("PatternMismatch", arr.to_vec()));
+                        }
+                        sub_env.borrow_mut().seal(&syn);
+                        for form in body {
+                            eval(form.clone(), sub_env.clone())?;
+                        }
+                        seq = rest;
+                    }
+                    break 'main Value::nil();
+                } else if arr[0].is_symbol(Symbol::LET) {
                     if arr.len() != 3 {
                         return Err(Value::error("InvalidArgs@let*", arr.to_vec()));
                     }
@@ -89,14 +275,62 @@ pub fn eval(mut syn: Value, mut env: Env) -> Result<Value, Value> {
                     let Value::List(assigns) = &arr[1] else {
                         return Err(Value::error("InvalidArgs@let*", arr.to_vec()));
                     };
+                    let assigns = assigns.values();
                     for i in 0..(assigns.len() / 2) {
                         let result = eval(assigns[2 * i + 1].clone(), env.clone())?;
                         if destructure(&assigns[2 * i], result, &env).is_none() {
                             return Err(Value::error("PatternMismatch", arr.to_vec()));
                         }
                     }
+                    env.borrow_mut().seal(&arr[2]);
                     syn = arr[2].clone();
-                } else if arr[0].is_symbol("def!") {
+                } else if arr[0].is_symbol(Symbol::LOOP) {
+                    let [_, Value::List(assigns), body] = &arr[..] else {
+                        return Err(Value::error("InvalidArgs@loop", arr.to_vec()));
+                    };
+                    if assigns.len() % 2 != 0 {
+                        return Err(Value::error("InvalidArgs@loop", arr.to_vec()));
+                    }
+                    let assigns = assigns.values();
+                    let outer = env.clone();
+                    env = new_env(env);
+                    for pair in assigns.chunks(2) {
+                        let result = eval(pair[1].clone(), env.clone())?;
+                        if destructure(&pair[0], result, &env).is_none() {
+                            return Err(Value::error("PatternMismatch", arr.to_vec()));
+                        }
+                    }
+                    env.borrow_mut().seal(body);
+                    recur = Some(Recur {
+                        pattern: Value::List(assigns.iter().step_by(2).cloned().collect()),
+                        body: body.clone(),
+                        env: outer,
+                        arity: Some(assigns.len() / 2),
+                    });
+                    syn = body.clone();
+                } else if arr[0].is_symbol(Symbol::RECUR) {
+                    let Some(target) = &recur else {
+                        return Err(Value::error("RecurNotInTailPosition", arr.to_vec()));
+                    };
+                    let vals: Vec<_> = arr
+                        .iter()
+                        .skip(1)
+                        .cloned()
+                        .map(|v| eval(v, env.clone()))
+                        .collect::<Result<_, _>>()?;
+                    if target.arity.is_some_and(|arity| arity != vals.len()) {
+                        return Err(Value::error("InvalidArgs@recur", vals));
+                    }
+                    env = new_env(target.env.clone());
+                    if destructure(&target.pattern, Value::List(vals.into()), &env).is_none() {
+                        return Err(Value::error(
+                            "PatternMismatch",
+                            vec![target.pattern.clone()],
+                        ));
+                    }
+                    env.borrow_mut().seal(&target.body);
+                    syn = target.body.clone();
+                } else if arr[0].is_symbol(Symbol::DEF) {
                     if arr.len() != 3 {
                         return Err(Value::error("InvalidArgs@def!", arr.to_vec()));
                     }
@@ -104,84 +338,70 @@ pub fn eval(mut syn: Value, mut env: Env) -> Result<Value, Value> {
                         return Err(Value::error("InvalidArgs@def!", arr.to_vec()));
                     };
                     let result = eval(arr[2].clone(), env.clone())?;
-                    let docstring = core::mem::take(&mut *NEXT_DOC.write().unwrap());
-                    if !docstring.is_empty() {
-                        DOCS.write().unwrap().insert(
-                            i.clone(),
-                            docstring.split('\r').collect::<Vec<_>>().join("\n"),
-                        );
-                    }
-                    env.borrow_mut().set(i, result);
+                    define(*i, result, &env);
                     break 'main Value::nil();
-                } else if arr[0].is_symbol("do") {
+                } else if arr[0].is_symbol(Symbol::DO) {
                     for i in arr.iter().take(arr.len() - 1).skip(1) {
                         eval(i.clone(), env.clone())?;
                     }
                     syn = arr.last().unwrap().clone();
-                } else if arr[0].is_symbol("try*") {
-                    let result = eval(arr[1].clone(), env.clone());
-                    match result {
+                } else if arr[0].is_symbol(Symbol::TRY) {
+                    match eval(arr[1].clone(), env.clone()) {
                         Ok(r) => break 'main r,
-                        Err(e) => {
-                            for catch_block in &arr[2..] {
-                                let Value::List(l) = catch_block else {
-                                    return Err(Value::error(
-                                        "InvalidCatchBlock",
-                                        vec![catch_block.clone()],
-                                    ));
-                                };
-                                let [Value::Symbol(catch), Value::Symbol(capture_symbol), inner @ .., body] =
-                                    &l[..]
+                        Err(e) => (env, syn) = catch(e, &arr[2..], env)?,
+                    }
+                } else if arr[0].is_symbol(Symbol::DOC) {
+                    if arr.len() != 2 {
+                        return Err(Value::error("InvalidArgs@doc", arr.to_vec()));
+                    }
+                    let Value::String(docstring) = arr[1].clone() else {
+                        return Err(Value::error("InvalidArgs@doc", arr.to_vec()));
+                    };
+                    let runtime = env.borrow().runtime();
+                    let mut next_doc = runtime.next_doc.borrow_mut();
+                    // each line of a `##` comment is its own doc form
+                    if !next_doc.is_empty() {
+                        next_doc.push('\n');
+                    }
+                    next_doc.push_str(&docstring);
+                    break 'main Value::nil();
+                } else if arr[0].is_symbol(Symbol::IMPORT) || arr[0].is_symbol(Symbol::REQUIRE) {
+                    let (Some(Value::String(path)), names @ ([] | [Value::List(_)])) =
+                        (arr.get(1), arr[..].get(2..).unwrap_or_default())
+                    else {
+                        return Err(Value::error("InvalidArgs@import", arr.to_vec()));
+                    };
+                    let Value::Table(module) = module::import(path, location.as_ref(), &env)?
+                    else {
+                        unreachable!()
+                    };
+                    match names {
+                        [Value::List(names)] => {
+                            for name in names {
+                                let (Value::Symbol(sym), Some(value)) = (name, module.get(name))
                                 else {
                                     return Err(Value::error(
-                                        "InvalidCatchBlock",
-                                        vec![catch_block.clone()],
+                                        "UndefinedImport",
+                                        vec![Value::String(path.clone()), name.clone()],
                                     ));
                                 };
-                                if catch != "catch*" {
-                                    return Err(Value::error(
-                                        "InvalidCatchBlock",
-                                        vec![catch_block.clone()],
-                                    ));
-                                }
-                                match inner {
-                                    [Value::Symbol(cap_sym)] => {
-                                        if e.as_list().is_some_and(|e| {
-                                            e.first().is_some_and(|x| x.is_symbol(capture_symbol))
-                                        }) {
-                                            env = new_env(env);
-                                            env.borrow_mut().set(cap_sym, e);
-                                            syn = body.clone();
-                                            continue 'main;
-                                        }
-                                    }
-                                    [] => {
-                                        env = new_env(env);
-                                        env.borrow_mut().set(capture_symbol, e);
-                                        syn = body.clone();
-                                        continue 'main;
-                                    }
-                                    _ => {
-                                        return Err(Value::error(
-                                            "InvalidCatchBlock",
-                                            vec![catch_block.clone()],
-                                        ))
-                                    }
+                                env.borrow_mut().define(*sym, value.clone());
+                            }
+                        }
+                        _ if arr[0].is_symbol(Symbol::REQUIRE) => {
+                            for (k, v) in module.iter() {
+                                if let Value::Symbol(sym) = k {
+                                    env.borrow_mut().define(*sym, v.clone());
                                 }
                             }
-                            return Err(e);
                         }
+                        _ => env.borrow_mut().define(
+                            Symbol::intern(&module::namespace(path)),
+                            Value::Table(module),
+                        ),
                     }
-                } else if arr[0].is_symbol("doc") {
-                    if arr.len() != 2 {
-                        return Err(Value::error("InvalidArgs@doc", arr.to_vec()));
-                    }
-                    let Value::String(docstring) = arr[1].clone() else {
-                        return Err(Value::error("InvalidArgs@doc", arr.to_vec()));
-                    };
-                    NEXT_DOC.write().unwrap().push_str(&docstring);
                     break 'main Value::nil();
-                } else if arr[0].is_symbol("help") {
+                } else if arr[0].is_symbol(Symbol::HELP) {
                     if arr.len() != 2 {
                         return Err(Value::error("InvalidArgs@help", arr.to_vec()));
                     }
@@ -189,113 +409,231 @@ pub fn eval(mut syn: Value, mut env: Env) -> Result<Value, Value> {
                         return Err(Value::error("InvalidArgs@help", arr.to_vec()));
                     };
                     println!("-- (help {symbol}) --");
-                    if let Some(docstr) = DOCS.read().unwrap().get(symbol) {
+                    if let Some(docstr) = env.borrow().runtime().docs.borrow().get(&*symbol.name())
+                    {
                         println!("{docstr}");
                         // break 'main Value::String(docstr.to_string());
                     }
-                    println!("-- ----{}--- --", "-".repeat(symbol.len()));
+                    println!("-- ----{}--- --", "-".repeat(symbol.name().len()));
                     break 'main Value::nil();
                 } else {
-                    match eval(arr[0].clone(), env.clone())? {
-                        Value::Function {
-                            fn_ref: func,
-                            is_macro: false,
-                        } => {
-                            break 'main func(
-                                arr.iter()
-                                    .skip(1)
-                                    .cloned()
-                                    .map(|v| eval(v, env.clone()))
-                                    .collect::<Result<_, _>>()?,
-                                env,
-                            )?;
+                    let (mut func, mut vals) = match eval(arr[0].clone(), env.clone())? {
+                        mac @ (Value::Function { is_macro: true, .. }
+                        | Value::Lambda { is_macro: true, .. }) => {
+                            syn = expand::apply(mac, &arr[1..], &env)?;
+                            continue 'main;
                         }
+                        func => (
+                            func,
+                            arr.iter()
+                                .skip(1)
+                                .cloned()
+                                .map(|v| eval(v, env.clone()))
+                                .collect::<Result<Vec<_>, _>>()?,
+                        ),
+                    };
+                    // call the function `apply` is given here, so that it is a tail call too
+                    while builtins::is_apply(&func) {
+                        let [f, Value::List(l)] = &vals[..] else {
+                            break;
+                        };
+                        (func, vals) = (f.clone(), l.to_vec());
+                    }
+                    match func {
                         Value::Function {
                             fn_ref: func,
-                            is_macro: true,
+                            is_macro: false,
                         } => {
-                            syn = func(arr[1..].into(), env.clone())?;
+                            break 'main func(vals, env)?;
                         }
                         Value::Lambda {
                             args,
                             body,
                             captures,
                             is_macro: false,
+                            name,
                         } => {
-                            let vals: Vec<_> = arr
-                                .iter()
-                                .skip(1)
-                                .cloned()
-                                .map(|v| eval(v, env.clone()))
-                                .collect::<Result<_, _>>()?;
-                            env = new_env(captures);
-                            syn = *body;
-                            let vals = Value::List(Rc::from(vals));
-                            if destructure(&args, vals, &env).is_none() {
-                                return Err(Value::error("PatternMismatch", vec![*args]));
+                            let call = Frame {
+                                name,
+                                form: syn.clone(),
+                                location: location.clone(),
+                            };
+                            match frame {
+                                Some(frame) => frame.replace(call),
+                                None => *frame = Some(FrameGuard::push(call)),
                             }
-                        }
-                        Value::Lambda {
-                            args,
-                            body,
-                            captures,
-                            is_macro: true,
-                        } => {
-                            let sub_env = new_env(captures);
-                            let vals = Value::List(Rc::from(
-                                arr.iter().skip(1).cloned().collect::<Vec<_>>(),
-                            ));
-                            if destructure(&args, vals, &sub_env).is_none() {
+                            env = new_env(captures.clone());
+                            if destructure(&args, Value::List(vals.into()), &env).is_none() {
                                 return Err(Value::error("PatternMismatch", vec![*args]));
                             }
-                            syn = eval(*body, sub_env)?;
+                            env.borrow_mut().seal(&body);
+                            syn = (*body).clone();
+                            recur = Some(Recur {
+                                pattern: *args,
+                                body: *body,
+                                env: captures,
+                                arity: None,
+                            });
+                        }
+                        key @ Value::Keyword(_) => {
+                            break 'main builtins::keyword_get(&key, vals)?;
                         }
-                        Value::Atom(at) if arr.len() == 1 => {
+                        Value::Atom(at) if vals.is_empty() => {
                             break 'main at.read().unwrap().clone();
                         }
                         other => return Err(Value::error("NotAFunction", vec![other])),
                     }
                 }
             }
-            Value::Symbol(ref id) if id == "true" || id == "false" || id == "nil" => {
-                break 'main syn.clone()
-            }
-            Value::Symbol(ref id) => match env.borrow().get(id) {
+            Value::Symbol(Symbol::TRUE | Symbol::FALSE | Symbol::NIL) => break 'main syn.clone(),
+            Value::Symbol(id) => match env.borrow().get(id) {
                 Some(x) => break 'main x,
-                None => return Err(Value::error("UnresolvedIdentifier", vec![syn.clone()])),
+                None => match qualified(id, &env) {
+                    Some(x) => break 'main x,
+                    None => return Err(Value::error("UnresolvedIdentifier", vec![syn.clone()])),
+                },
             },
             Value::Table(table) => {
                 let mut t = HashMap::new();
-                for (k, v) in &*table {
+                for (k, v) in &table {
                     let v = eval(v.clone(), env.clone())?;
                     t.insert(k.clone(), v);
                 }
-                break 'main Value::Table(Rc::new(t));
+                break 'main Value::Table(t);
+            }
+            Value::Set(set) => {
+                let mut s = HashSet::new();
+                for v in &set {
+                    s.insert(eval(v.clone(), env.clone())?);
+                }
+                break 'main Value::Set(s);
             }
             other => break 'main other,
         }
     };
+    finish_cons(cons, out)
+}
+
+/// Define a name in an environment, as `def!` does. A lambda is named after the first name it is defined
+/// with, and takes the documentation from any `doc` forms before it.
+fn define(name: Symbol, value: Value, env: &Env) {
+    let value = match value {
+        Value::Lambda {
+            args,
+            body,
+            captures,
+            is_macro,
+            name: None,
+        } => Value::Lambda {
+            args,
+            body,
+            captures,
+            is_macro,
+            name: Some(name),
+        },
+        other => other,
+    };
+    let runtime = env.borrow().runtime();
+    let docstring = runtime.next_doc.take();
+    if !docstring.is_empty() {
+        runtime.docs.borrow_mut().insert(
+            name.to_string(),
+            docstring.split('\r').collect::<Vec<_>>().join("\n"),
+        );
+    }
+    env.borrow_mut().define(name, value);
+}
+
+/// Make a lambda in an environment, capturing the variables its body uses
+pub(super) fn lambda(params: &Value, body: &Value, env: &Env) -> Value {
+    let analysis = scope::analyze(params, body);
+    let captures = scope::capture(&analysis, env);
+    gc::track_env(&captures);
+    Value::Lambda {
+        args: Box::new(params.clone()),
+        body: Box::new(body.clone()),
+        captures,
+        is_macro: false,
+        name: None,
+    }
+}
+
+/// Find the catch block of a `try*` that handles an error, returning the environment and body to evaluate
+/// the block in. The error is raised again if no block handles it.
+fn catch(e: Value, blocks: &[Value], env: Env) -> Result<(Env, Value), Value> {
+    for catch_block in blocks {
+        let Value::List(l) = catch_block else {
+            return Err(Value::error("InvalidCatchBlock", vec![catch_block.clone()]));
+        };
+        let l = l.values();
+        let [Value::Symbol(catch), Value::Symbol(capture_symbol), inner @ .., body] = &l[..] else {
+            return Err(Value::error("InvalidCatchBlock", vec![catch_block.clone()]));
+        };
+        if *catch != Symbol::CATCH {
+            return Err(Value::error("InvalidCatchBlock", vec![catch_block.clone()]));
+        }
+        let name = match inner {
+            [Value::Symbol(cap_sym)] => {
+                if !e
+                    .as_list()
+                    .is_some_and(|e| e.first().is_some_and(|x| x.is_symbol(*capture_symbol)))
+                {
+                    continue;
+                }
+                cap_sym
+            }
+            [] => capture_symbol,
+            _ => return Err(Value::error("InvalidCatchBlock", vec![catch_block.clone()])),
+        };
+        let env = new_env(env);
+        env.borrow_mut().set(Symbol::TRACE, trace::to_value(&e));
+        env.borrow_mut().set(*name, e);
+        env.borrow_mut().seal(body);
+        return Ok((env, body.clone()));
+    }
+    Err(e)
+}
+
+/// Add the values of `cons` forms evaluated in tail position to the front of the sequence they ended with
+fn finish_cons(cons: Vec<Value>, out: Value) -> Result<Value, Value> {
     if cons.is_empty() {
         Ok(out)
     } else if let Value::List(l) = out {
-        cons.extend(l.iter().cloned());
-        Ok(Value::List(Rc::from(cons)))
+        Ok(Value::List(List::from(cons).concat(&l)))
+    } else if let Value::Lazy(_) = out {
+        Ok(cons
+            .into_iter()
+            .rev()
+            .fold(out, |rest, first| LazySeq::cons(first, rest)))
     } else {
         Err(Value::error("NotAList", vec![out]))
     }
 }
 
+/// Look up a qualified identifier like `module/name` in an imported module
+fn qualified(id: Symbol, env: &Env) -> Option<Value> {
+    let id = id.name();
+    let (namespace, name) = id.split_once('/')?;
+    if namespace.is_empty() || name.is_empty() {
+        return None;
+    }
+    let Value::Table(module) = env.borrow().get(Symbol::intern(namespace))? else {
+        return None;
+    };
+    module.get(&Value::symbol(name)).cloned()
+}
+
 fn destructure(pat: &Value, value: Value, env: &Env) -> Option<()> {
     // println!("{pat:?} {value:?}");
     if let Value::Symbol(s) = &pat {
-        env.borrow_mut().set(s, value);
+        env.borrow_mut().set(*s, value);
         Some(())
     } else if let (Some(p), Some(v)) = (pat.as_list(), value.as_list()) {
         if p.len() > v.len() {
             return None;
         }
-        for i in 0..p.len() {
-            destructure(&p[i], v[i].clone(), env)?;
+        for (p, v) in p.iter().zip(v) {
+            destructure(p, v.clone(), env)?;
         }
         Some(())
     } else {
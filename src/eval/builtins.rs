@@ -1,74 +1,99 @@
 #![allow(clippy::needless_pass_by_value, clippy::missing_errors_doc)]
+use core::cmp::Ordering;
+use core::fmt::Write;
 use std::io::stdin;
+use std::num::IntErrorKind;
+use std::rc::Rc;
 use std::sync::RwLock;
-use std::{collections::HashMap, rc::Rc};
-
-use regex::Regex;
 
 use crate::env::{new_env, Env};
+use crate::gc;
+use crate::trace::{Frame, FrameGuard};
+
+use crate::types::{
+    convert::IntoValue,
+    lazy::{self, LazySeq},
+    number::Number,
+    DynFn, List, Symbol, Value,
+};
 
-use crate::types::{DynFn, Value};
+pub mod fs;
+pub mod re;
+pub mod seq;
+pub mod set;
+pub mod string;
+pub mod syntax;
+
+fn number(arg: Value) -> Result<Number, Value> {
+    Number::from_value(&arg).ok_or_else(|| Value::error("NotANumber", vec![arg]))
+}
 
 pub fn add(args: Vec<Value>, _env: Env) -> Result<Value, Value> {
-    let mut sum = 0;
+    let mut sum = Number::Int(0);
     for arg in args {
-        let Value::Int(i) = arg else {
-            return Err(Value::error("NotANumber", vec![arg]));
-        };
-        sum += i;
+        sum = sum + number(arg)?;
     }
-    Ok(Value::Int(sum))
+    Ok(sum.into_value())
 }
 
 pub fn sub(args: Vec<Value>, _env: Env) -> Result<Value, Value> {
-    let mut difference = 0;
+    let mut args = args.into_iter();
+    let Some(first) = args.next() else {
+        return Ok(Value::Int(0));
+    };
+    let first = number(first)?;
+    if args.len() == 0 {
+        return Ok((-first).into_value());
+    }
+    let mut difference = first;
     for arg in args {
-        let Value::Int(i) = arg else {
-            return Err(Value::error("NotANumber", vec![arg]));
-        };
-        // I promise this makes a little bit of sense
-        difference = -i - difference;
+        difference = difference - number(arg)?;
     }
-    Ok(Value::Int(difference))
+    Ok(difference.into_value())
 }
 
 pub fn mul(args: Vec<Value>, _env: Env) -> Result<Value, Value> {
-    let mut product = 1;
+    let mut product = Number::Int(1);
     for arg in args {
-        let Value::Int(i) = arg else {
-            return Err(Value::error("NotANumber", vec![arg]));
-        };
-        product *= i;
+        product = product * number(arg)?;
     }
-    Ok(Value::Int(product))
+    Ok(product.into_value())
 }
 
 pub fn div(args: Vec<Value>, _env: Env) -> Result<Value, Value> {
-    let [Value::Int(a), Value::Int(b)] = &args[..] else {
-        return Err(Value::error(
-            "InvalidArgs@div",
-            vec![Value::List(args.into())],
-        ));
+    let mut args = args.into_iter();
+    let Some(first) = args.next() else {
+        return Err(Value::error("InvalidArgs@div", vec![]));
     };
-    if *b == 0 {
-        Err(Value::error("DivideByZero", vec![]))
-    } else {
-        Ok(Value::Int(*a / *b))
+    let first = number(first)?;
+    if args.len() == 0 {
+        return Ok(Number::Int(1).try_div(first)?.into_value());
+    }
+    let mut quotient = first;
+    for arg in args {
+        quotient = quotient.try_div(number(arg)?)?;
     }
+    Ok(quotient.into_value())
+}
+
+pub fn quot(args: Vec<Value>, _env: Env) -> Result<Value, Value> {
+    let [a, b] = &args[..] else {
+        return Err(Value::error("InvalidArgs@quot", args));
+    };
+    let (Some(a), Some(b)) = (Number::from_value(a), Number::from_value(b)) else {
+        return Err(Value::error("InvalidArgs@quot", args));
+    };
+    Ok(a.quot(b)?.into_value())
 }
 
 pub fn rem(args: Vec<Value>, _env: Env) -> Result<Value, Value> {
-    let [Value::Int(a), Value::Int(b)] = &args[..] else {
-        return Err(Value::error(
-            "InvalidArgs@div",
-            vec![Value::List(args.into())],
-        ));
+    let [a, b] = &args[..] else {
+        return Err(Value::error("InvalidArgs@rem", args));
     };
-    if *b == 0 {
-        Err(Value::error("DivideByZero", vec![]))
-    } else {
-        Ok(Value::Int(*a % *b))
-    }
+    let (Some(a), Some(b)) = (Number::from_value(a), Number::from_value(b)) else {
+        return Err(Value::error("InvalidArgs@rem", args));
+    };
+    Ok(a.try_rem(b)?.into_value())
 }
 
 #[allow(clippy::needless_pass_by_value)]
@@ -80,48 +105,74 @@ pub fn eq(args: Vec<Value>, _env: Env) -> Result<Value, Value> {
     }
 }
 
-pub fn lt(args: Vec<Value>, _env: Env) -> Result<Value, Value> {
-    let [Value::Int(a), Value::Int(b)] = &args[..] else {
-        return Err(Value::error("InvalidArgs@lt", args));
+fn compare(args: Vec<Value>, name: &str, pred: impl Fn(Ordering) -> bool) -> Result<Value, Value> {
+    let [a, b] = &args[..] else {
+        return Err(Value::error(&format!("InvalidArgs@{name}"), args));
+    };
+    let (Some(a), Some(b)) = (Number::from_value(a), Number::from_value(b)) else {
+        return Err(Value::error(&format!("InvalidArgs@{name}"), args));
     };
-    if *a < *b {
+    if a.partial_cmp(b).is_some_and(pred) {
         Ok(Value::symbol("true"))
     } else {
         Ok(Value::symbol("false"))
     }
 }
 
-pub fn le(args: Vec<Value>, _env: Env) -> Result<Value, Value> {
-    let [Value::Int(a), Value::Int(b)] = &args[..] else {
-        return Err(Value::error("InvalidArgs@le", args));
+/// Raise `(AssertFailed expected actual)` unless the two values are equal
+pub fn assert_eq(args: Vec<Value>, _env: Env) -> Result<Value, Value> {
+    let [expected, actual] = &args[..] else {
+        return Err(Value::error("InvalidArgs@assert=", args));
     };
-    if *a <= *b {
+    if expected == actual {
         Ok(Value::symbol("true"))
     } else {
-        Ok(Value::symbol("false"))
+        Err(Value::error("AssertFailed", args))
     }
 }
 
-pub fn gt(args: Vec<Value>, _env: Env) -> Result<Value, Value> {
-    let [Value::Int(a), Value::Int(b)] = &args[..] else {
-        return Err(Value::error("InvalidArgs@gt", args));
+/// Call a function with no arguments, raising `(ExpectedError names form result)` unless it raises an error.
+/// If any error names are given, the error must have one of them.
+pub fn assert_raises(args: Vec<Value>, env: Env) -> Result<Value, Value> {
+    let [func, form, Value::List(names)] = &args[..] else {
+        return Err(Value::error("InvalidArgs@assert-raises", args));
     };
-    if *a > *b {
-        Ok(Value::symbol("true"))
-    } else {
-        Ok(Value::symbol("false"))
+    let result = apply(vec![func.clone(), Value::List(List::new())], env);
+    match result {
+        Err(err)
+            if names.is_empty()
+                || err
+                    .as_list()
+                    .and_then(List::first)
+                    .is_some_and(|name| names.contains(name)) =>
+        {
+            Ok(err)
+        }
+        Ok(result) | Err(result) => Err(Value::error(
+            "ExpectedError",
+            vec![Value::List(names.clone()), form.clone(), result],
+        )),
     }
 }
 
+pub fn num_eq(args: Vec<Value>, _env: Env) -> Result<Value, Value> {
+    compare(args, "==", Ordering::is_eq)
+}
+
+pub fn lt(args: Vec<Value>, _env: Env) -> Result<Value, Value> {
+    compare(args, "lt", Ordering::is_lt)
+}
+
+pub fn le(args: Vec<Value>, _env: Env) -> Result<Value, Value> {
+    compare(args, "le", Ordering::is_le)
+}
+
+pub fn gt(args: Vec<Value>, _env: Env) -> Result<Value, Value> {
+    compare(args, "gt", Ordering::is_gt)
+}
+
 pub fn ge(args: Vec<Value>, _env: Env) -> Result<Value, Value> {
-    let [Value::Int(a), Value::Int(b)] = &args[..] else {
-        return Err(Value::error("InvalidArgs@ge", args));
-    };
-    if *a >= *b {
-        Ok(Value::symbol("true"))
-    } else {
-        Ok(Value::symbol("false"))
-    }
+    compare(args, "ge", Ordering::is_ge)
 }
 
 pub fn print(args: Vec<Value>, _env: Env) -> Result<Value, Value> {
@@ -150,26 +201,30 @@ pub fn typ(args: Vec<Value>, _env: Env) -> Result<Value, Value> {
         return Err(Value::error("InvalidArgs@type", args));
     }
     Ok(match &args[0] {
-        Value::Int(_) => Value::symbol("int"),
-        Value::Symbol(s) => match &**s {
-            "true" | "false" => Value::symbol("bool"),
-            "nil" => Value::symbol("nil"),
-            _ => Value::symbol("symbol"),
-        },
+        Value::Int(_) | Value::BigInt(_) => Value::symbol("int"),
+        Value::Float(_) => Value::symbol("float"),
+        Value::Ratio(_) => Value::symbol("ratio"),
+        Value::Symbol(Symbol::TRUE | Symbol::FALSE) => Value::symbol("bool"),
+        Value::Symbol(Symbol::NIL) => Value::nil(),
+        Value::Symbol(_) => Value::symbol("symbol"),
+        Value::Keyword(_) => Value::symbol("keyword"),
         Value::String(_) => Value::symbol("string"),
         Value::List(_) => Value::symbol("list"),
         Value::Table(_) => Value::symbol("table"),
+        Value::Set(_) => Value::symbol("set"),
         Value::Function { is_macro, .. } | Value::Lambda { is_macro, .. } => {
             Value::symbol(if *is_macro { "macro" } else { "function" })
         }
         Value::Atom(_) => Value::symbol("atom"),
+        Value::Regex(_) => Value::symbol("regex"),
+        Value::Lazy(_) => Value::symbol("lazy-seq"),
     })
 }
 
 #[must_use]
 pub fn type_is(type_is: &'static str) -> Rc<DynFn> {
     Rc::new(|args, env| {
-        if typ(args, env)?.is_symbol(type_is) {
+        if typ(args, env)?.is_symbol(Symbol::intern(type_is)) {
             Ok(Value::symbol("true"))
         } else {
             Ok(Value::symbol("false"))
@@ -177,10 +232,11 @@ pub fn type_is(type_is: &'static str) -> Rc<DynFn> {
     })
 }
 
+/// # Panics
 pub fn str(args: Vec<Value>, _env: Env) -> Result<Value, Value> {
     let mut str = String::new();
     for arg in args {
-        str.push_str(&format!("{arg}"));
+        write!(str, "{arg}").unwrap();
     }
     Ok(Value::String(str))
 }
@@ -192,7 +248,26 @@ pub fn symbol(args: Vec<Value>, _env: Env) -> Result<Value, Value> {
     let Value::String(s) = args[0].clone() else {
         return Err(Value::error("InvalidArgs@symbol", args));
     };
-    Ok(Value::Symbol(s))
+    Ok(Value::symbol(&s))
+}
+
+pub fn keyword(args: Vec<Value>, _env: Env) -> Result<Value, Value> {
+    match &args[..] {
+        [Value::String(name)] if !name.is_empty() => Ok(Value::keyword(name)),
+        [Value::Symbol(name)] if !name.name().is_empty() => Ok(Value::Keyword(*name)),
+        [Value::Keyword(_)] => Ok(args[0].clone()),
+        _ => Err(Value::error("InvalidArgs@keyword", args)),
+    }
+}
+
+/// Call a keyword as a function: `(:key table)` gets the key's value in the table, or nil or the default
+/// if it isn't there
+pub fn keyword_get(key: &Value, args: Vec<Value>) -> Result<Value, Value> {
+    match &args[..] {
+        [Value::Table(t)] => Ok(t.get(key).cloned().unwrap_or_else(Value::nil)),
+        [Value::Table(t), default] => Ok(t.get(key).unwrap_or(default).clone()),
+        _ => Err(Value::error(&format!("InvalidArgs@{key}"), args)),
+    }
 }
 
 pub fn chr(mut args: Vec<Value>, _env: Env) -> Result<Value, Value> {
@@ -202,30 +277,37 @@ pub fn chr(mut args: Vec<Value>, _env: Env) -> Result<Value, Value> {
     let Value::Int(i) = args.remove(0) else {
         return Err(Value::error("InvalidArgs@chr", args));
     };
-    #[allow(clippy::cast_sign_loss)]
-    char::try_from(i as u32).map_or_else(
-        |_| Err(Value::error("InvalidChar", vec![Value::Int(i)])),
+    u32::try_from(i).ok().and_then(char::from_u32).map_or_else(
+        || Err(Value::error("InvalidChar", vec![Value::Int(i)])),
         |c| Ok(Value::String(c.to_string())),
     )
 }
 
+/// # Panics
 pub fn int(mut args: Vec<Value>, _env: Env) -> Result<Value, Value> {
     if args.len() != 1 {
         return Err(Value::error("InvalidArgs@int", args));
     }
     match args.remove(0) {
-        Value::Int(i) => Ok(Value::Int(i)),
+        i @ (Value::Int(_) | Value::BigInt(_)) => Ok(i),
+        num @ (Value::Float(_) | Value::Ratio(_)) => Ok(number(num)?.trunc()?.into_value()),
         Value::String(s) => match s.parse() {
             Ok(i) => Ok(Value::Int(i)),
+            Err(err)
+                if matches!(
+                    err.kind(),
+                    IntErrorKind::PosOverflow | IntErrorKind::NegOverflow
+                ) =>
+            {
+                Ok(Value::BigInt(Rc::new(s.parse().unwrap())))
+            }
             Err(err) => Err(Value::error(
                 "ParseError",
                 vec![
                     Value::String(s),
                     Value::symbol(match err.kind() {
-                        std::num::IntErrorKind::Empty => "EmptyString",
-                        std::num::IntErrorKind::InvalidDigit => "InvalidDigit",
-                        std::num::IntErrorKind::PosOverflow => "PositiveOverflow",
-                        std::num::IntErrorKind::NegOverflow => "NegativeOverflow",
+                        IntErrorKind::Empty => "EmptyString",
+                        IntErrorKind::InvalidDigit => "InvalidDigit",
                         _ => "UnknownReason",
                     }),
                 ],
@@ -235,6 +317,58 @@ pub fn int(mut args: Vec<Value>, _env: Env) -> Result<Value, Value> {
     }
 }
 
+pub fn float(mut args: Vec<Value>, _env: Env) -> Result<Value, Value> {
+    if args.len() != 1 {
+        return Err(Value::error("InvalidArgs@float", args));
+    }
+    match args.remove(0) {
+        Value::String(s) => s.trim().parse().map_or_else(
+            |_| Err(Value::error("ParseError", vec![Value::String(s)])),
+            |f| Ok(Value::Float(f)),
+        ),
+        other => Ok(Value::Float(number(other)?.to_f64())),
+    }
+}
+
+fn rounding(
+    mut args: Vec<Value>,
+    name: &str,
+    round: impl Fn(Number) -> Result<Number, Value>,
+) -> Result<Value, Value> {
+    if args.len() != 1 {
+        return Err(Value::error(&format!("InvalidArgs@{name}"), args));
+    }
+    Ok(round(number(args.remove(0))?)?.into_value())
+}
+
+pub fn round(args: Vec<Value>, _env: Env) -> Result<Value, Value> {
+    rounding(args, "round", Number::round)
+}
+
+pub fn floor(args: Vec<Value>, _env: Env) -> Result<Value, Value> {
+    rounding(args, "floor", Number::floor)
+}
+
+pub fn ceil(args: Vec<Value>, _env: Env) -> Result<Value, Value> {
+    rounding(args, "ceil", Number::ceil)
+}
+
+pub fn numerator(args: Vec<Value>, _env: Env) -> Result<Value, Value> {
+    match &args[..] {
+        [Value::Int(_) | Value::BigInt(_)] => Ok(args[0].clone()),
+        [Value::Ratio(r)] => Ok(Number::BigInt(r.numer().clone()).into_value()),
+        _ => Err(Value::error("InvalidArgs@numerator", args)),
+    }
+}
+
+pub fn denominator(args: Vec<Value>, _env: Env) -> Result<Value, Value> {
+    match &args[..] {
+        [Value::Int(_) | Value::BigInt(_)] => Ok(Value::Int(1)),
+        [Value::Ratio(r)] => Ok(Number::BigInt(r.denom().clone()).into_value()),
+        _ => Err(Value::error("InvalidArgs@denominator", args)),
+    }
+}
+
 /// # Panics
 #[allow(clippy::cast_sign_loss)]
 pub fn nth(mut args: Vec<Value>, _env: Env) -> Result<Value, Value> {
@@ -255,6 +389,21 @@ pub fn nth(mut args: Vec<Value>, _env: Env) -> Result<Value, Value> {
             .nth(i as usize)
             .map(|c| Value::String(c.to_string()))
             .ok_or_else(|| Value::error("InvalidIndex", Vec::new())),
+        seq @ Value::Lazy(_) if i < 0 => {
+            Err(Value::error("InvalidIndex", vec![seq, Value::Int(i)]))
+        }
+        seq @ Value::Lazy(_) => {
+            let mut rest = seq.clone();
+            for _ in 0..i {
+                match lazy::step(&rest)? {
+                    Some((_, next)) => rest = next,
+                    None => break,
+                }
+            }
+            lazy::step(&rest)?
+                .map(|(value, _)| value)
+                .ok_or_else(|| Value::error("InvalidIndex", vec![seq, Value::Int(i)]))
+        }
         o => Err(Value::error("NotAList", vec![o])),
     }
 }
@@ -272,6 +421,7 @@ pub fn first(mut args: Vec<Value>, _env: Env) -> Result<Value, Value> {
             .next()
             .map(|c| Value::String(c.to_string()))
             .ok_or_else(Value::nil),
+        Value::Lazy(seq) => Ok(seq.step()?.map_or_else(Value::nil, |(first, _)| first)),
         other => Err(Value::error("InvalidArgs@first", vec![other])),
     }
 }
@@ -288,6 +438,15 @@ pub fn last(mut args: Vec<Value>, _env: Env) -> Result<Value, Value> {
             .chars()
             .last()
             .map_or_else(Value::nil, |c| Value::String(c.to_string()))),
+        seq @ Value::Lazy(_) if lazy::is_infinite(&seq) => Err(seq::infinite("last")),
+        seq @ Value::Lazy(_) => {
+            let (mut last, mut rest) = (Value::nil(), seq);
+            while let Some((value, next)) = lazy::step(&rest)? {
+                last = value;
+                rest = next;
+            }
+            Ok(last)
+        }
         other => Err(Value::error("InvalidArgs@last", vec![other])),
     }
 }
@@ -297,14 +456,14 @@ pub fn rest(mut args: Vec<Value>, _env: Env) -> Result<Value, Value> {
         return Err(Value::error("InvalidArgs@rest", args));
     }
     let arg = args.remove(0);
-    if arg.is_symbol("nil") {
+    if arg.is_symbol(Symbol::NIL) {
         Ok(arg)
     } else if let Value::List(l) = arg {
-        if l.is_empty() {
-            Ok(Value::List(Rc::new([])))
-        } else {
-            Ok(Value::List(l[1..].into()))
-        }
+        Ok(Value::List(l.rest()))
+    } else if let Value::Lazy(seq) = arg {
+        Ok(seq
+            .step()?
+            .map_or_else(|| Value::List(List::new()), |(_, rest)| rest))
     } else {
         Err(Value::error("NotAList", vec![arg]))
     }
@@ -323,6 +482,66 @@ pub fn eval(mut args: Vec<Value>, env: Env) -> Result<Value, Value> {
     }
 }
 
+pub fn max_depth(args: Vec<Value>, _env: Env) -> Result<Value, Value> {
+    match &args[..] {
+        [] => Ok(Value::Int(
+            i128::try_from(super::max_depth()).unwrap_or(i128::MAX),
+        )),
+        [Value::Int(depth)] => match usize::try_from(*depth) {
+            Ok(depth) if depth > 0 => {
+                super::set_max_depth(depth);
+                Ok(Value::nil())
+            }
+            _ => Err(Value::error("InvalidArgs@max-depth", args)),
+        },
+        _ => Err(Value::error("InvalidArgs@max-depth", args)),
+    }
+}
+
+pub fn collect(args: Vec<Value>, _env: Env) -> Result<Value, Value> {
+    if !args.is_empty() {
+        return Err(Value::error("InvalidArgs@gc", args));
+    }
+    Ok(gc::collect().into_value())
+}
+
+pub fn gc_stats(args: Vec<Value>, _env: Env) -> Result<Value, Value> {
+    if !args.is_empty() {
+        return Err(Value::error("InvalidArgs@gc-stats", args));
+    }
+    let stats = gc::stats();
+    Ok(Value::Table(
+        [
+            ("envs", stats.envs),
+            ("atoms", stats.atoms),
+            ("lazy-seqs", stats.lazy_seqs),
+            ("tracked", stats.tracked),
+            ("collections", stats.collections),
+            ("collected", stats.collected),
+        ]
+        .into_iter()
+        .map(|(name, n)| (Value::keyword(name), n.into_value()))
+        .collect(),
+    ))
+}
+
+thread_local! {
+    /// The `apply` builtin, kept so the evaluator can recognize it and make its call a tail call
+    static APPLY: Rc<DynFn> = Rc::new(apply);
+}
+
+/// The `apply` builtin, as it is defined in the default environment
+#[must_use]
+pub fn apply_fn() -> Rc<DynFn> {
+    APPLY.with(Rc::clone)
+}
+
+/// Check if a value is the `apply` builtin
+#[must_use]
+pub fn is_apply(func: &Value) -> bool {
+    matches!(func, Value::Function { fn_ref, is_macro: false } if APPLY.with(|apply| Rc::ptr_eq(fn_ref, apply)))
+}
+
 pub fn apply(mut args: Vec<Value>, env: Env) -> Result<Value, Value> {
     if args.len() != 2 {
         return Err(Value::error("InvalidArgs@apply", args));
@@ -345,13 +564,32 @@ pub fn apply(mut args: Vec<Value>, env: Env) -> Result<Value, Value> {
             body,
             captures,
             is_macro: false,
+            name,
         } => {
-            let env = new_env(captures);
+            let env = new_env(captures.clone());
+            let form = Value::List(List::from([
+                Value::symbol("apply"),
+                name.map_or_else(Value::nil, Value::Symbol),
+                l.clone(),
+            ]));
+            let _frame = FrameGuard::push(Frame {
+                name,
+                form,
+                location: None,
+            });
             if super::destructure(&params, l, &env).is_none() {
                 return Err(Value::error("PatternMismatch", vec![*params]));
             }
-            super::eval(*body, env)
+            env.borrow_mut().seal(&body);
+            super::eval_lambda(*params, *body, captures, env)
         }
+        key @ Value::Keyword(_) => keyword_get(
+            &key,
+            match l {
+                Value::List(l) => l.to_vec(),
+                o => return Err(Value::error("NotAList", vec![o])),
+            },
+        ),
         other => Err(Value::error("NotAFunction", vec![other])),
     }
 }
@@ -373,18 +611,20 @@ pub fn as_macro(mut args: Vec<Value>, _env: Env) -> Result<Value, Value> {
             body,
             captures,
             is_macro: false,
+            name,
         } => Ok(Value::Lambda {
             args,
             body,
             captures,
             is_macro: true,
+            name,
         }),
         other => Err(Value::error("NotAFunction", vec![other])),
     }
 }
 
 pub fn assoc(args: Vec<Value>, _env: Env) -> Result<Value, Value> {
-    if args.len() % 2 == 0 {
+    if args.len().is_multiple_of(2) {
         return Err(Value::error("InvalidArgs@assoc", args));
     }
     let mut args = args.into_iter();
@@ -393,11 +633,11 @@ pub fn assoc(args: Vec<Value>, _env: Env) -> Result<Value, Value> {
         Some(other) => return Err(Value::error("NotATable", vec![other])),
         None => unreachable!(),
     };
-    let mut table: HashMap<_, _> = (*table).clone();
+    let mut table = table;
     while let (Some(k), Some(v)) = (args.next(), args.next()) {
         table.insert(k, v);
     }
-    Ok(Value::Table(Rc::new(table)))
+    Ok(Value::Table(table))
 }
 
 pub fn dissoc(args: Vec<Value>, _env: Env) -> Result<Value, Value> {
@@ -410,11 +650,11 @@ pub fn dissoc(args: Vec<Value>, _env: Env) -> Result<Value, Value> {
         Some(other) => return Err(Value::error("NotATable", vec![other])),
         None => unreachable!(),
     };
-    let mut table: HashMap<_, _> = (*table).clone();
+    let mut table = table;
     for k in args {
         table.remove(&k);
     }
-    Ok(Value::Table(Rc::new(table)))
+    Ok(Value::Table(table))
 }
 
 pub fn get(args: Vec<Value>, _env: Env) -> Result<Value, Value> {
@@ -428,45 +668,31 @@ pub fn keys(args: Vec<Value>, _env: Env) -> Result<Value, Value> {
     let [Value::Table(t)] = &args[..] else {
         return Err(Value::error("InvalidArgs@keys", args));
     };
-    Ok(Value::List(t.keys().cloned().collect::<Vec<_>>().into()))
+    Ok(Value::List(t.keys().cloned().collect()))
 }
 
 pub fn values(args: Vec<Value>, _env: Env) -> Result<Value, Value> {
     let [Value::Table(t)] = &args[..] else {
         return Err(Value::error("InvalidArgs@values", args));
     };
-    Ok(Value::List(t.values().cloned().collect::<Vec<_>>().into()))
+    Ok(Value::List(t.values().cloned().collect()))
 }
 
 pub fn contains(args: Vec<Value>, _env: Env) -> Result<Value, Value> {
-    let [Value::Table(t), k] = &args[..] else {
-        return Err(Value::error("InvalidArgs@contains", args));
+    let found = match &args[..] {
+        [Value::Table(t), k] => t.contains_key(k),
+        [Value::Set(s), v] => s.contains(v),
+        _ => return Err(Value::error("InvalidArgs@contains", args)),
     };
-    Ok(Value::symbol(if t.contains_key(k) {
-        "true"
-    } else {
-        "false"
-    }))
+    Ok(Value::symbol(if found { "true" } else { "false" }))
 }
 
 pub fn findall(args: Vec<Value>, _env: Env) -> Result<Value, Value> {
-    let [Value::String(re), Value::String(haystack)] = &args[..] else {
+    let [re, Value::String(haystack)] = &args[..] else {
         return Err(Value::error("InvalidArgs@findall", args));
     };
-    let re = match Regex::new(re) {
-        Ok(re) => re,
-        Err(regex::Error::CompiledTooBig(i)) => {
-            return Err(Value::error(
-                "RegexTooLong",
-                vec![i128::try_from(i).map_or_else(|_| Value::String(i.to_string()), Value::Int)],
-            ))
-        }
-        Err(regex::Error::Syntax(syn)) => {
-            return Err(Value::error("InvalidRegex", vec![Value::String(syn)]))
-        }
-        Err(_) => return Err(Value::error("RegexError", Vec::new())),
-    };
-    Ok(Value::List(Rc::from(
+    let re = re::to_regex(re, "findall")?;
+    Ok(Value::List(
         re.captures_iter(haystack)
             .map(|m| m.iter().flatten().collect::<Vec<_>>())
             .filter(|s| !s.is_empty())
@@ -477,17 +703,32 @@ pub fn findall(args: Vec<Value>, _env: Env) -> Result<Value, Value> {
                         .collect(),
                 )
             })
-            .collect::<Vec<Value>>(),
-    )))
+            .collect(),
+    ))
 }
 
 pub fn cons(args: Vec<Value>, _env: Env) -> Result<Value, Value> {
-    let [elem, Value::List(l)] = &args[..] else {
-        return Err(Value::error("InvalidArgs@cons", args));
-    };
-    let mut elements = vec![elem.clone()];
-    elements.extend(l.iter().cloned());
-    Ok(Value::List(Rc::from(elements)))
+    match &args[..] {
+        [elem, Value::List(l)] => Ok(Value::List(l.cons(elem.clone()))),
+        [elem, rest @ Value::Lazy(_)] => Ok(LazySeq::cons(elem.clone(), rest.clone())),
+        _ => Err(Value::error("InvalidArgs@cons", args)),
+    }
+}
+
+pub fn conj(args: Vec<Value>, _env: Env) -> Result<Value, Value> {
+    match args.split_first() {
+        Some((Value::List(l), values)) => Ok(Value::List(
+            values
+                .iter()
+                .fold(l.clone(), |l, value| l.conj(value.clone())),
+        )),
+        Some((Value::Set(s), values)) => {
+            let mut s = s.clone();
+            s.extend(values.iter().cloned());
+            Ok(Value::Set(s))
+        }
+        _ => Err(Value::error("InvalidArgs@conj", args)),
+    }
 }
 
 pub fn count(mut args: Vec<Value>, _env: Env) -> Result<Value, Value> {
@@ -496,8 +737,18 @@ pub fn count(mut args: Vec<Value>, _env: Env) -> Result<Value, Value> {
     }
     let i = match args.remove(0) {
         Value::List(l) => l.len(),
-        Value::String(s) => s.len(),
+        Value::String(s) => s.chars().count(),
         Value::Table(t) => t.len(),
+        Value::Set(s) => s.len(),
+        seq @ Value::Lazy(_) if lazy::is_infinite(&seq) => return Err(seq::infinite("count")),
+        seq @ Value::Lazy(_) => {
+            let (mut count, mut rest) = (0, seq);
+            while let Some((_, next)) = lazy::step(&rest)? {
+                count += 1;
+                rest = next;
+            }
+            count
+        }
         other => return Err(Value::error("NotASequence", vec![other])),
     };
     match i128::try_from(i) {
@@ -527,7 +778,9 @@ pub fn atom(args: Vec<Value>, _env: Env) -> Result<Value, Value> {
     if args.len() != 1 {
         return Err(Value::error("InvalidArgs@atom", args));
     }
-    Ok(Value::Atom(Rc::new(RwLock::new(args[0].clone()))))
+    let atom = Rc::new(RwLock::new(args[0].clone()));
+    gc::track_atom(&atom);
+    Ok(Value::Atom(atom))
 }
 
 /// # Panics
@@ -540,7 +793,7 @@ pub fn inspect_atom(args: Vec<Value>, env: Env) -> Result<Value, Value> {
     };
     let mut atl = at.write().unwrap();
     *atl = apply(
-        vec![args[1].clone(), Value::List(Rc::new([atl.clone()]))],
+        vec![args[1].clone(), Value::List(List::from([atl.clone()]))],
         env,
     )?;
     drop(atl);
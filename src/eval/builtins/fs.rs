@@ -0,0 +1,174 @@
+use core::fmt::Write as _;
+use std::{
+    ffi::OsStr,
+    fs::{self, OpenOptions},
+    io::{self, Write as _},
+    path::{Path, PathBuf},
+    rc::Rc,
+};
+
+use crate::{
+    env::Env,
+    types::{convert::IntoValue, DynFn, Value},
+};
+
+/// Describe a failed file system operation as `(IoError Kind "path")`
+pub(crate) fn io_error(path: &Path, err: &io::Error) -> Value {
+    Value::error(
+        "IoError",
+        vec![
+            Value::symbol(&format!("{:?}", err.kind())),
+            Value::String(path.to_string_lossy().to_string()),
+        ],
+    )
+}
+
+fn path_string(path: &Path) -> Value {
+    Value::String(path.to_string_lossy().to_string())
+}
+
+/// Write a value's contents to a file, either replacing or appending to what is there
+fn write(args: Vec<Value>, append: bool, name: &str) -> Result<Value, Value> {
+    let [Value::String(path), contents] = &args[..] else {
+        return Err(Value::error(&format!("InvalidArgs@{name}"), args));
+    };
+    let mut text = String::new();
+    write!(text, "{contents}").unwrap();
+    let path = Path::new(path);
+    OpenOptions::new()
+        .create(true)
+        .write(true)
+        .append(append)
+        .truncate(!append)
+        .open(path)
+        .and_then(|mut file| file.write_all(text.as_bytes()))
+        .map_err(|err| io_error(path, &err))?;
+    Ok(Value::nil())
+}
+
+pub fn slurp(args: Vec<Value>, _env: Env) -> Result<Value, Value> {
+    let [Value::String(path)] = &args[..] else {
+        return Err(Value::error("InvalidArgs@slurp", args));
+    };
+    let path = Path::new(path);
+    fs::read_to_string(path)
+        .map(Value::String)
+        .map_err(|err| io_error(path, &err))
+}
+
+/// # Panics
+pub fn spit(args: Vec<Value>, _env: Env) -> Result<Value, Value> {
+    write(args, false, "spit")
+}
+
+/// # Panics
+pub fn append_file(args: Vec<Value>, _env: Env) -> Result<Value, Value> {
+    write(args, true, "append-file")
+}
+
+pub fn read_lines(args: Vec<Value>, _env: Env) -> Result<Value, Value> {
+    let [Value::String(path)] = &args[..] else {
+        return Err(Value::error("InvalidArgs@read-lines", args));
+    };
+    let path = Path::new(path);
+    let text = fs::read_to_string(path).map_err(|err| io_error(path, &err))?;
+    Ok(Value::List(
+        text.lines()
+            .map(|line| Value::String(line.to_string()))
+            .collect(),
+    ))
+}
+
+pub fn file_exists(args: Vec<Value>, _env: Env) -> Result<Value, Value> {
+    let [Value::String(path)] = &args[..] else {
+        return Err(Value::error("InvalidArgs@file-exists?", args));
+    };
+    Ok(Path::new(path).exists().into_value())
+}
+
+pub fn is_dir(args: Vec<Value>, _env: Env) -> Result<Value, Value> {
+    let [Value::String(path)] = &args[..] else {
+        return Err(Value::error("InvalidArgs@dir?", args));
+    };
+    Ok(Path::new(path).is_dir().into_value())
+}
+
+pub fn list_dir(args: Vec<Value>, _env: Env) -> Result<Value, Value> {
+    let [Value::String(path)] = &args[..] else {
+        return Err(Value::error("InvalidArgs@list-dir", args));
+    };
+    let path = Path::new(path);
+    let mut names = fs::read_dir(path)
+        .and_then(|entries| {
+            entries
+                .map(|entry| Ok(entry?.file_name().to_string_lossy().to_string()))
+                .collect::<io::Result<Vec<_>>>()
+        })
+        .map_err(|err| io_error(path, &err))?;
+    // directory order is platform dependent
+    names.sort_unstable();
+    Ok(Value::List(names.into_iter().map(Value::String).collect()))
+}
+
+pub fn mkdir(args: Vec<Value>, _env: Env) -> Result<Value, Value> {
+    let [Value::String(path)] = &args[..] else {
+        return Err(Value::error("InvalidArgs@mkdir", args));
+    };
+    let path = Path::new(path);
+    fs::create_dir_all(path).map_err(|err| io_error(path, &err))?;
+    Ok(Value::nil())
+}
+
+pub fn delete_file(args: Vec<Value>, _env: Env) -> Result<Value, Value> {
+    let [Value::String(path)] = &args[..] else {
+        return Err(Value::error("InvalidArgs@delete-file", args));
+    };
+    let path = Path::new(path);
+    if path.is_dir() {
+        fs::remove_dir(path)
+    } else {
+        fs::remove_file(path)
+    }
+    .map_err(|err| io_error(path, &err))?;
+    Ok(Value::nil())
+}
+
+pub fn path_join(args: Vec<Value>, _env: Env) -> Result<Value, Value> {
+    let mut path = PathBuf::new();
+    for arg in &args {
+        let Value::String(part) = arg else {
+            return Err(Value::error("InvalidArgs@path-join", args));
+        };
+        path.push(part);
+    }
+    Ok(path_string(&path))
+}
+
+/// Build a builtin that gets part of a path, or nil if the path doesn't have that part
+#[must_use]
+pub fn path_part(name: &'static str, part: fn(&Path) -> Option<&OsStr>) -> Rc<DynFn> {
+    Rc::new(move |args, _env| {
+        let [Value::String(path)] = &args[..] else {
+            return Err(Value::error(&format!("InvalidArgs@{name}"), args));
+        };
+        Ok(part(Path::new(path)).map_or_else(Value::nil, |part| path_string(Path::new(part))))
+    })
+}
+
+/// The directory a path is in
+#[must_use]
+pub fn parent(path: &Path) -> Option<&OsStr> {
+    path.parent()
+        .filter(|p| !p.as_os_str().is_empty())
+        .map(Path::as_os_str)
+}
+
+pub fn path_absolute(args: Vec<Value>, _env: Env) -> Result<Value, Value> {
+    let [Value::String(path)] = &args[..] else {
+        return Err(Value::error("InvalidArgs@path-absolute", args));
+    };
+    let path = Path::new(path);
+    path.canonicalize()
+        .map(|path| path_string(&path))
+        .map_err(|err| io_error(path, &err))
+}
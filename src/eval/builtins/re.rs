@@ -0,0 +1,208 @@
+use std::{cell::RefCell, collections::HashMap, rc::Rc};
+
+use im_rc::HashMap as Table;
+
+use regex::{Captures, Regex};
+
+use crate::{
+    env::Env,
+    types::{List, Value},
+};
+
+/// How many compiled patterns to keep before the cache is cleared
+const CACHE_SIZE: usize = 256;
+
+thread_local! {
+    /// Compiled regexes by pattern, so patterns given as strings aren't recompiled on every call
+    static CACHE: RefCell<HashMap<String, Rc<Regex>>> = RefCell::new(HashMap::new());
+}
+
+/// Compile a pattern, or get it from the cache if it has been compiled before
+/// # Errors
+/// If the pattern is invalid or too large
+pub fn compile(pattern: &str) -> Result<Rc<Regex>, Value> {
+    if let Some(re) = CACHE.with_borrow(|cache| cache.get(pattern).cloned()) {
+        return Ok(re);
+    }
+    let re = Rc::new(Regex::new(pattern).map_err(|err| match err {
+        regex::Error::CompiledTooBig(i) => Value::error(
+            "RegexTooLong",
+            vec![i128::try_from(i).map_or_else(|_| Value::String(i.to_string()), Value::Int)],
+        ),
+        regex::Error::Syntax(syn) => Value::error("InvalidRegex", vec![Value::String(syn)]),
+        _ => Value::error("RegexError", Vec::new()),
+    })?);
+    CACHE.with_borrow_mut(|cache| {
+        if cache.len() >= CACHE_SIZE {
+            cache.clear();
+        }
+        cache.insert(pattern.to_string(), re.clone());
+    });
+    Ok(re)
+}
+
+/// Get a regex from a compiled regex or a pattern string
+/// # Errors
+/// If the value is a string that isn't a valid pattern, or isn't a regex or string
+pub fn to_regex(value: &Value, name: &str) -> Result<Rc<Regex>, Value> {
+    match value {
+        Value::Regex(re) => Ok(re.clone()),
+        Value::String(pattern) => compile(pattern),
+        other => Err(Value::error(
+            &format!("InvalidArgs@{name}"),
+            vec![other.clone()],
+        )),
+    }
+}
+
+/// Convert a byte offset in a string to a char index
+fn char_index(s: &str, byte: usize) -> Value {
+    Value::Int(i128::try_from(s[..byte].chars().count()).unwrap_or(i128::MAX))
+}
+
+fn text(m: Option<regex::Match<'_>>) -> Value {
+    m.map_or_else(Value::nil, |m| Value::String(m.as_str().to_string()))
+}
+
+/// Describe a match as a table of its text, its start and end char indices, its groups,
+/// and a table of its named groups
+fn match_table(re: &Regex, caps: &Captures<'_>, haystack: &str) -> Value {
+    let whole = caps.get(0).unwrap();
+    let named: Table<Value, Value> = re
+        .capture_names()
+        .flatten()
+        .map(|name| (Value::keyword(name), text(caps.name(name))))
+        .collect();
+    let table = Table::from(vec![
+        (Value::keyword("text"), text(Some(whole))),
+        (Value::keyword("start"), char_index(haystack, whole.start())),
+        (Value::keyword("end"), char_index(haystack, whole.end())),
+        (
+            Value::keyword("groups"),
+            Value::List(caps.iter().skip(1).map(text).collect()),
+        ),
+        (Value::keyword("named"), Value::Table(named)),
+    ]);
+    Value::Table(table)
+}
+
+pub fn regex(args: Vec<Value>, _env: Env) -> Result<Value, Value> {
+    let Some((Value::String(pattern), flags)) = args.split_first() else {
+        return Err(Value::error("InvalidArgs@regex", args));
+    };
+    let flags = match flags {
+        [] => "",
+        [Value::String(flags)] => flags,
+        _ => return Err(Value::error("InvalidArgs@regex", args)),
+    };
+    if let Some(flag) = flags.chars().find(|c| !matches!(c, 'i' | 'm' | 's' | 'x')) {
+        return Err(Value::error(
+            "InvalidRegexFlag",
+            vec![Value::String(flag.to_string())],
+        ));
+    }
+    // flags are written into the pattern so that regexes with different flags are never equal
+    let pattern = if flags.is_empty() {
+        pattern.clone()
+    } else {
+        format!("(?{flags}){pattern}")
+    };
+    compile(&pattern).map(Value::Regex)
+}
+
+pub fn re_match(args: Vec<Value>, _env: Env) -> Result<Value, Value> {
+    let [re, Value::String(haystack)] = &args[..] else {
+        return Err(Value::error("InvalidArgs@re-match", args));
+    };
+    // anchoring the pattern finds a match of the whole string even if a shorter match would be found first;
+    // the newline ends a trailing `#` comment under the `x` flag, and is ignored whitespace otherwise
+    let re = compile(&format!(
+        "\\A(?:(?:{})(?x:\n))\\z",
+        to_regex(re, "re-match")?.as_str()
+    ))?;
+    Ok(re
+        .captures(haystack)
+        .map_or_else(Value::nil, |caps| match_table(&re, &caps, haystack)))
+}
+
+pub fn re_find(args: Vec<Value>, _env: Env) -> Result<Value, Value> {
+    let (Some(re), Some(Value::String(haystack)), start) =
+        (args.first(), args.get(1), args.get(2..))
+    else {
+        return Err(Value::error("InvalidArgs@re-find", args));
+    };
+    let start = match start {
+        None | Some([]) => 0,
+        Some([Value::Int(i)]) => match usize::try_from(*i).ok().and_then(|i| {
+            haystack
+                .char_indices()
+                .map(|(b, _)| b)
+                .chain([haystack.len()])
+                .nth(i)
+        }) {
+            Some(byte) => byte,
+            None => return Err(Value::error("IndexOutOfRange", args)),
+        },
+        Some(_) => return Err(Value::error("InvalidArgs@re-find", args)),
+    };
+    let re = to_regex(re, "re-find")?;
+    Ok(re
+        .captures_at(haystack, start)
+        .map_or_else(Value::nil, |caps| match_table(&re, &caps, haystack)))
+}
+
+pub fn re_find_all(args: Vec<Value>, _env: Env) -> Result<Value, Value> {
+    let [re, Value::String(haystack)] = &args[..] else {
+        return Err(Value::error("InvalidArgs@re-find-all", args));
+    };
+    let re = to_regex(re, "re-find-all")?;
+    Ok(Value::List(
+        re.captures_iter(haystack)
+            .map(|caps| match_table(&re, &caps, haystack))
+            .collect(),
+    ))
+}
+
+/// # Panics
+pub fn re_replace(args: Vec<Value>, env: Env) -> Result<Value, Value> {
+    let [re, Value::String(haystack), replacement] = &args[..] else {
+        return Err(Value::error("InvalidArgs@re-replace", args));
+    };
+    let re = to_regex(re, "re-replace")?;
+    match replacement {
+        // `$1` and `${name}` refer to groups
+        Value::String(replacement) => Ok(Value::String(
+            re.replace_all(haystack, replacement.as_str()).into_owned(),
+        )),
+        func @ (Value::Function { .. } | Value::Lambda { .. }) => {
+            let mut out = String::new();
+            let mut last = 0;
+            for caps in re.captures_iter(haystack) {
+                let whole = caps.get(0).unwrap();
+                out.push_str(&haystack[last..whole.start()]);
+                let table = match_table(&re, &caps, haystack);
+                let result = super::apply(
+                    vec![func.clone(), Value::List(List::from([table]))],
+                    env.clone(),
+                )?;
+                out.push_str(&result.to_string());
+                last = whole.end();
+            }
+            out.push_str(&haystack[last..]);
+            Ok(Value::String(out))
+        }
+        _ => Err(Value::error("InvalidArgs@re-replace", args)),
+    }
+}
+
+pub fn re_split(args: Vec<Value>, _env: Env) -> Result<Value, Value> {
+    let [re, Value::String(haystack)] = &args[..] else {
+        return Err(Value::error("InvalidArgs@re-split", args));
+    };
+    let re = to_regex(re, "re-split")?;
+    Ok(Value::List(
+        re.split(haystack)
+            .map(|part| Value::String(part.to_string()))
+            .collect(),
+    ))
+}
@@ -0,0 +1,417 @@
+use core::cmp::Ordering;
+use std::collections::HashMap;
+
+use crate::{
+    env::Env,
+    types::{
+        lazy::{self, LazySeq, Thunk},
+        number::Number,
+        List, Value,
+    },
+};
+
+/// Get the values of a list or lazy sequence, or the characters of a string as single character strings.
+/// A lazy sequence is realized to its end, unless it is known not to have one.
+fn items(value: &Value, name: &str) -> Result<Vec<Value>, Value> {
+    match value {
+        Value::List(l) => Ok(l.iter().cloned().collect()),
+        Value::String(s) => Ok(s.chars().map(|c| Value::String(c.to_string())).collect()),
+        Value::Lazy(_) if lazy::is_infinite(value) => Err(infinite(name)),
+        Value::Lazy(_) => {
+            let (mut items, mut rest) = (Vec::new(), value.clone());
+            while let Some((first, next)) = lazy::step(&rest)? {
+                items.push(first);
+                rest = next;
+            }
+            Ok(items)
+        }
+        other => Err(Value::error(
+            &format!("InvalidArgs@{name}"),
+            vec![other.clone()],
+        )),
+    }
+}
+
+/// The error raised when a builtin that needs every value of a sequence is given one without end
+pub(super) fn infinite(name: &str) -> Value {
+    Value::error("InfiniteSequence", vec![Value::symbol(name)])
+}
+
+/// Build a sequence of the same kind as `like`: a string if it is a string, otherwise a list
+fn same_kind(like: &Value, items: Vec<Value>) -> Value {
+    if matches!(like, Value::String(_)) {
+        Value::String(items.iter().map(ToString::to_string).collect())
+    } else {
+        Value::List(items.into())
+    }
+}
+
+/// Get a count from an int, which must not be negative
+fn count(value: &Value) -> Option<usize> {
+    match value {
+        Value::Int(i) => usize::try_from(*i).ok(),
+        _ => None,
+    }
+}
+
+fn call(func: &Value, args: Vec<Value>, env: &Env) -> Result<Value, Value> {
+    super::apply(vec![func.clone(), Value::List(args.into())], env.clone())
+}
+
+/// Order two values, if they can be ordered: numbers by value, strings, symbols and keywords alphabetically,
+/// and lists by their values in order
+fn compare(a: &Value, b: &Value) -> Option<Ordering> {
+    match (a, b) {
+        (Value::String(a), Value::String(b)) => Some(a.cmp(b)),
+        (Value::Symbol(a), Value::Symbol(b)) | (Value::Keyword(a), Value::Keyword(b)) => {
+            Some(a.cmp(b))
+        }
+        (Value::List(a), Value::List(b)) => {
+            for (a, b) in a.iter().zip(b.iter()) {
+                match compare(a, b)? {
+                    Ordering::Equal => {}
+                    ordering => return Some(ordering),
+                }
+            }
+            Some(a.len().cmp(&b.len()))
+        }
+        _ => Number::from_value(a)?.partial_cmp(Number::from_value(b)?),
+    }
+}
+
+/// A stable merge sort of values by their keys, raising `(Incomparable a b)` if two keys can't be compared
+fn merge_sort(items: Vec<(Value, Value)>) -> Result<Vec<(Value, Value)>, Value> {
+    let mut runs: Vec<Vec<_>> = items.into_iter().map(|item| vec![item]).collect();
+    while runs.len() > 1 {
+        let mut merged = Vec::with_capacity(runs.len().div_ceil(2));
+        let mut pairs = runs.into_iter();
+        while let Some(a) = pairs.next() {
+            merged.push(match pairs.next() {
+                Some(b) => merge(a, b)?,
+                None => a,
+            });
+        }
+        runs = merged;
+    }
+    Ok(runs.pop().unwrap_or_default())
+}
+
+/// Merge two sorted runs, taking from the first run when keys are equal
+fn merge(a: Vec<(Value, Value)>, b: Vec<(Value, Value)>) -> Result<Vec<(Value, Value)>, Value> {
+    let mut out = Vec::with_capacity(a.len() + b.len());
+    let mut a = a.into_iter().peekable();
+    let mut b = b.into_iter().peekable();
+    while let (Some((x, _)), Some((y, _))) = (a.peek(), b.peek()) {
+        let Some(ordering) = compare(x, y) else {
+            return Err(Value::error("Incomparable", vec![x.clone(), y.clone()]));
+        };
+        out.extend(if ordering.is_gt() { b.next() } else { a.next() });
+    }
+    out.extend(a);
+    out.extend(b);
+    Ok(out)
+}
+
+/// Map over a list or string, or lazily over a lazy sequence
+pub fn map(args: Vec<Value>, env: Env) -> Result<Value, Value> {
+    let [seq, func] = &args[..] else {
+        return Err(Value::error("InvalidArgs@map", args));
+    };
+    if let Value::Lazy(_) = seq {
+        return Ok(LazySeq::from_thunk(Thunk::Map {
+            func: func.clone(),
+            source: seq.clone(),
+            env,
+        }));
+    }
+    Ok(Value::List(
+        items(seq, "map")?
+            .into_iter()
+            .map(|item| call(func, vec![item], &env))
+            .collect::<Result<_, _>>()?,
+    ))
+}
+
+pub fn filter(args: Vec<Value>, env: Env) -> Result<Value, Value> {
+    let [seq, pred] = &args[..] else {
+        return Err(Value::error("InvalidArgs@filter", args));
+    };
+    if let Value::Lazy(_) = seq {
+        return Ok(LazySeq::from_thunk(Thunk::Filter {
+            pred: pred.clone(),
+            source: seq.clone(),
+            env,
+        }));
+    }
+    let mut kept = Vec::new();
+    for item in items(seq, "filter")? {
+        if call(pred, vec![item.clone()], &env)?.is_truthy() {
+            kept.push(item);
+        }
+    }
+    Ok(same_kind(seq, kept))
+}
+
+pub fn reduce(args: Vec<Value>, env: Env) -> Result<Value, Value> {
+    let (seq, func, init) = match &args[..] {
+        [seq, func] => (seq, func, None),
+        [seq, func, init] => (seq, func, Some(init.clone())),
+        _ => return Err(Value::error("InvalidArgs@reduce", args)),
+    };
+    let mut items = items(seq, "reduce")?.into_iter();
+    let Some(mut acc) = init.or_else(|| items.next()) else {
+        return Err(Value::error("EmptySequence", vec![Value::symbol("reduce")]));
+    };
+    for item in items {
+        acc = call(func, vec![acc, item], &env)?;
+    }
+    Ok(acc)
+}
+
+pub fn zip(args: Vec<Value>, _env: Env) -> Result<Value, Value> {
+    if !args.is_empty() && args.iter().all(lazy::is_infinite) {
+        return Err(infinite("zip"));
+    }
+    // lazy sequences are only stepped as far as the shortest sequence goes
+    let mut seqs = args
+        .iter()
+        .map(|seq| match seq {
+            Value::Lazy(_) => Ok(seq.clone()),
+            other => Ok(Value::List(items(other, "zip")?.into())),
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let mut zipped = Vec::new();
+    while !seqs.is_empty() {
+        let mut values = Vec::with_capacity(seqs.len());
+        for seq in &mut seqs {
+            let Some((first, rest)) = lazy::step(seq)? else {
+                return Ok(Value::List(zipped.into()));
+            };
+            values.push(first);
+            *seq = rest;
+        }
+        zipped.push(Value::List(values.into()));
+    }
+    Ok(Value::List(zipped.into()))
+}
+
+pub fn take(args: Vec<Value>, _env: Env) -> Result<Value, Value> {
+    let ([seq, _], Some(n)) = (&args[..], args.get(1).and_then(count)) else {
+        return Err(Value::error("InvalidArgs@take", args));
+    };
+    match seq {
+        Value::List(l) => Ok(Value::List(l.take(n))),
+        Value::String(s) => Ok(Value::String(s.chars().take(n).collect())),
+        Value::Lazy(_) => {
+            let (mut taken, mut rest) = (Vec::new(), seq.clone());
+            while taken.len() < n {
+                let Some((first, next)) = lazy::step(&rest)? else {
+                    break;
+                };
+                taken.push(first);
+                rest = next;
+            }
+            Ok(Value::List(taken.into()))
+        }
+        _ => Err(Value::error("InvalidArgs@take", args)),
+    }
+}
+
+pub fn drop(args: Vec<Value>, _env: Env) -> Result<Value, Value> {
+    let ([seq, _], Some(n)) = (&args[..], args.get(1).and_then(count)) else {
+        return Err(Value::error("InvalidArgs@drop", args));
+    };
+    match seq {
+        Value::List(l) => Ok(Value::List(l.skip(n))),
+        Value::String(s) => Ok(Value::String(s.chars().skip(n).collect())),
+        Value::Lazy(_) => {
+            let mut rest = seq.clone();
+            for _ in 0..n {
+                match lazy::step(&rest)? {
+                    Some((_, next)) => rest = next,
+                    None => return Ok(Value::List(List::new())),
+                }
+            }
+            Ok(rest)
+        }
+        _ => Err(Value::error("InvalidArgs@drop", args)),
+    }
+}
+
+/// Sort a sequence, comparing values or the results of a key function. The sort is stable.
+pub fn sort(args: Vec<Value>, env: Env) -> Result<Value, Value> {
+    let (seq, key) = match &args[..] {
+        [seq] => (seq, None),
+        [seq, key] => (seq, Some(key)),
+        _ => return Err(Value::error("InvalidArgs@sort", args)),
+    };
+    let items = items(seq, "sort")?;
+    let keyed = match key {
+        Some(key) => items
+            .into_iter()
+            .map(|item| Ok((call(key, vec![item.clone()], &env)?, item)))
+            .collect::<Result<Vec<_>, Value>>()?,
+        None => items.into_iter().map(|item| (item.clone(), item)).collect(),
+    };
+    Ok(same_kind(
+        seq,
+        merge_sort(keyed)?
+            .into_iter()
+            .map(|(_, item)| item)
+            .collect(),
+    ))
+}
+
+pub fn reverse(args: Vec<Value>, _env: Env) -> Result<Value, Value> {
+    let [seq] = &args[..] else {
+        return Err(Value::error("InvalidArgs@reverse", args));
+    };
+    let mut items = items(seq, "reverse")?;
+    items.reverse();
+    Ok(same_kind(seq, items))
+}
+
+/// Join sequences together. The result is a string if every argument is a string, and a list otherwise.
+pub fn concat(args: Vec<Value>, _env: Env) -> Result<Value, Value> {
+    if !args.is_empty() && args.iter().all(|arg| matches!(arg, Value::String(_))) {
+        return Ok(Value::String(
+            args.iter().map(ToString::to_string).collect(),
+        ));
+    }
+    let mut out = List::new();
+    for arg in &args {
+        out = match arg {
+            Value::List(l) => out.concat(l),
+            other => out.concat(&items(other, "concat")?.into()),
+        };
+    }
+    Ok(Value::List(out))
+}
+
+/// Flatten nested lists into one list, however deeply they are nested
+pub fn flatten(args: Vec<Value>, _env: Env) -> Result<Value, Value> {
+    let [seq] = &args[..] else {
+        return Err(Value::error("InvalidArgs@flatten", args));
+    };
+    let mut out = Vec::new();
+    // the lists being walked, each with the index of its next value, so deep nesting doesn't recurse
+    let mut stack = vec![(List::from(items(seq, "flatten")?), 0)];
+    while let Some((list, i)) = stack.last_mut() {
+        let Some(item) = list.get(*i).cloned() else {
+            stack.pop();
+            continue;
+        };
+        *i += 1;
+        match item {
+            Value::List(inner) => stack.push((inner, 0)),
+            other => out.push(other),
+        }
+    }
+    Ok(Value::List(out.into()))
+}
+
+/// Group values into a table by the result of calling a function on each. Each group keeps the order of the
+/// values in the sequence.
+pub fn group_by(args: Vec<Value>, env: Env) -> Result<Value, Value> {
+    let [seq, func] = &args[..] else {
+        return Err(Value::error("InvalidArgs@group-by", args));
+    };
+    let mut groups: HashMap<Value, Vec<Value>> = HashMap::new();
+    for item in items(seq, "group-by")? {
+        let key = call(func, vec![item.clone()], &env)?;
+        groups.entry(key).or_default().push(item);
+    }
+    Ok(Value::Table(
+        groups
+            .into_iter()
+            .map(|(key, items)| (key, Value::List(items.into())))
+            .collect(),
+    ))
+}
+
+/// Count from a start to an end, not including the end
+fn range_list(start: Number, end: &Number, step: &Number, args: &[Value]) -> Result<Value, Value> {
+    let ascending = match step.clone().partial_cmp(Number::Int(0)) {
+        Some(Ordering::Greater) => Ordering::Less,
+        Some(Ordering::Less) => Ordering::Greater,
+        _ => return Err(Value::error("InvalidArgs@range", args.to_vec())),
+    };
+    let mut out = Vec::new();
+    let mut n = start;
+    while n.clone().partial_cmp(end.clone()) == Some(ascending) {
+        out.push(n.clone().into_value());
+        n = n + step.clone();
+    }
+    Ok(Value::List(out.into()))
+}
+
+/// `(range)` counts up from 0 forever, as a lazy sequence. `(range end)`, `(range start end)` and
+/// `(range start end step)` build a list.
+pub fn range(args: Vec<Value>, _env: Env) -> Result<Value, Value> {
+    let Some(numbers) = args
+        .iter()
+        .map(Number::from_value)
+        .collect::<Option<Vec<_>>>()
+    else {
+        return Err(Value::error("InvalidArgs@range", args));
+    };
+    match &numbers[..] {
+        [] => Ok(LazySeq::from_thunk(Thunk::Range {
+            start: Number::Int(0),
+            step: Number::Int(1),
+        })),
+        [end] => range_list(Number::Int(0), end, &Number::Int(1), &args),
+        [start, end] => range_list(start.clone(), end, &Number::Int(1), &args),
+        [start, end, step] => range_list(start.clone(), end, step, &args),
+        _ => Err(Value::error("InvalidArgs@range", args)),
+    }
+}
+
+/// `(repeat x n)` is a list of `x` n times, and `(repeat x)` is `x` forever, as a lazy sequence
+pub fn repeat(args: Vec<Value>, _env: Env) -> Result<Value, Value> {
+    match &args[..] {
+        [x] => Ok(LazySeq::from_thunk(Thunk::Cycle {
+            items: List::from([x.clone()]),
+            index: 0,
+        })),
+        [x, n] => count(n)
+            .map(|n| Value::List(vec![x.clone(); n].into()))
+            .ok_or_else(|| Value::error("InvalidArgs@repeat", args.clone())),
+        _ => Err(Value::error("InvalidArgs@repeat", args)),
+    }
+}
+
+pub fn iterate(args: Vec<Value>, env: Env) -> Result<Value, Value> {
+    let [func, x] = &args[..] else {
+        return Err(Value::error("InvalidArgs@iterate", args));
+    };
+    Ok(LazySeq::cons(
+        x.clone(),
+        LazySeq::from_thunk(Thunk::Iterate {
+            func: func.clone(),
+            prev: x.clone(),
+            env,
+        }),
+    ))
+}
+
+pub fn cycle(args: Vec<Value>, _env: Env) -> Result<Value, Value> {
+    let [seq] = &args[..] else {
+        return Err(Value::error("InvalidArgs@cycle", args));
+    };
+    Ok(LazySeq::from_thunk(Thunk::Cycle {
+        items: items(seq, "cycle")?.into(),
+        index: 0,
+    }))
+}
+
+pub fn realized(args: Vec<Value>, _env: Env) -> Result<Value, Value> {
+    let [Value::Lazy(seq)] = &args[..] else {
+        return Err(Value::error("InvalidArgs@realized?", args));
+    };
+    Ok(Value::symbol(if seq.is_realized() {
+        "true"
+    } else {
+        "false"
+    }))
+}
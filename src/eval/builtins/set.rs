@@ -0,0 +1,77 @@
+use im_rc::HashSet;
+
+use crate::{
+    env::Env,
+    types::{convert::IntoValue, Value},
+};
+
+/// Get every argument as a set
+fn sets<'a>(args: &'a [Value], name: &str) -> Result<Vec<&'a HashSet<Value>>, Value> {
+    args.iter()
+        .map(|arg| match arg {
+            Value::Set(s) => Ok(s),
+            _ => Err(Value::error(&format!("InvalidArgs@{name}"), args.to_vec())),
+        })
+        .collect()
+}
+
+pub fn set(args: Vec<Value>, _env: Env) -> Result<Value, Value> {
+    match &args[..] {
+        [Value::List(l)] => Ok(Value::Set(l.iter().cloned().collect())),
+        [Value::Set(_)] => Ok(args[0].clone()),
+        [Value::Table(t)] => Ok(Value::Set(t.keys().cloned().collect())),
+        _ => Err(Value::error("InvalidArgs@set", args)),
+    }
+}
+
+pub fn union(args: Vec<Value>, _env: Env) -> Result<Value, Value> {
+    Ok(Value::Set(HashSet::unions(
+        sets(&args, "union")?.into_iter().cloned(),
+    )))
+}
+
+pub fn intersection(args: Vec<Value>, _env: Env) -> Result<Value, Value> {
+    let sets = sets(&args, "intersection")?;
+    let Some((first, others)) = sets.split_first() else {
+        return Err(Value::error("InvalidArgs@intersection", args));
+    };
+    Ok(Value::Set(
+        first
+            .iter()
+            .filter(|v| others.iter().all(|s| s.contains(*v)))
+            .cloned()
+            .collect(),
+    ))
+}
+
+pub fn difference(args: Vec<Value>, _env: Env) -> Result<Value, Value> {
+    let sets = sets(&args, "difference")?;
+    let Some((first, others)) = sets.split_first() else {
+        return Err(Value::error("InvalidArgs@difference", args));
+    };
+    let mut out = (*first).clone();
+    for s in others {
+        for v in *s {
+            out.remove(v);
+        }
+    }
+    Ok(Value::Set(out))
+}
+
+pub fn subset(args: Vec<Value>, _env: Env) -> Result<Value, Value> {
+    let [Value::Set(a), Value::Set(b)] = &args[..] else {
+        return Err(Value::error("InvalidArgs@subset?", args));
+    };
+    Ok(a.is_subset(b).into_value())
+}
+
+pub fn disj(args: Vec<Value>, _env: Env) -> Result<Value, Value> {
+    let Some((Value::Set(s), values)) = args.split_first() else {
+        return Err(Value::error("InvalidArgs@disj", args));
+    };
+    let mut s = s.clone();
+    for v in values {
+        s.remove(v);
+    }
+    Ok(Value::Set(s))
+}
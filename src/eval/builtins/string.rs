@@ -0,0 +1,191 @@
+use core::fmt::Write as _;
+use std::rc::Rc;
+
+use crate::{
+    env::Env,
+    types::{convert::IntoValue, DynFn, Value},
+};
+
+/// Convert a char index or count to an int
+fn int(i: usize) -> Value {
+    Value::Int(i128::try_from(i).unwrap_or(i128::MAX))
+}
+
+/// Get a char index from an int, which must be at most `len`
+fn char_index(value: &Value, len: usize) -> Option<usize> {
+    let Value::Int(i) = value else {
+        return None;
+    };
+    usize::try_from(*i).ok().filter(|i| *i <= len)
+}
+
+pub fn substr(args: Vec<Value>, _env: Env) -> Result<Value, Value> {
+    let Some((Value::String(s), [start, end @ ..])) = args.split_first() else {
+        return Err(Value::error("InvalidArgs@substr", args));
+    };
+    let len = s.chars().count();
+    let (Some(start), Some(end)) = (
+        char_index(start, len),
+        match end {
+            [] => Some(len),
+            [end] => char_index(end, len),
+            _ => None,
+        },
+    ) else {
+        return Err(Value::error("IndexOutOfRange", args));
+    };
+    if start > end {
+        return Err(Value::error("InvalidArgs@substr", args));
+    }
+    Ok(Value::String(
+        s.chars().skip(start).take(end - start).collect(),
+    ))
+}
+
+pub fn split(args: Vec<Value>, _env: Env) -> Result<Value, Value> {
+    let parts: Vec<Value> = match &args[..] {
+        [Value::String(s)] => s
+            .split_whitespace()
+            .map(|part| Value::String(part.to_string()))
+            .collect(),
+        [Value::String(s), Value::String(sep)] if !sep.is_empty() => s
+            .split(sep.as_str())
+            .map(|part| Value::String(part.to_string()))
+            .collect(),
+        _ => return Err(Value::error("InvalidArgs@split", args)),
+    };
+    Ok(Value::List(parts.into()))
+}
+
+/// # Panics
+pub fn join(args: Vec<Value>, _env: Env) -> Result<Value, Value> {
+    let Some((Value::List(parts), sep)) = args.split_first() else {
+        return Err(Value::error("InvalidArgs@join", args));
+    };
+    let sep = match sep {
+        [] => "",
+        [Value::String(sep)] => sep,
+        _ => return Err(Value::error("InvalidArgs@join", args)),
+    };
+    let mut out = String::new();
+    for (i, part) in parts.iter().enumerate() {
+        if i > 0 {
+            out.push_str(sep);
+        }
+        write!(out, "{part}").unwrap();
+    }
+    Ok(Value::String(out))
+}
+
+/// Build a builtin that transforms a string
+#[must_use]
+pub fn map_str(name: &'static str, func: fn(&str) -> String) -> Rc<DynFn> {
+    Rc::new(move |args, _env| {
+        let [Value::String(s)] = &args[..] else {
+            return Err(Value::error(&format!("InvalidArgs@{name}"), args));
+        };
+        Ok(Value::String(func(s)))
+    })
+}
+
+/// Remove whitespace from the start and end of a string. Given a sequence and a count, `trim` is the
+/// older name for `drop`.
+pub fn trim(args: Vec<Value>, env: Env) -> Result<Value, Value> {
+    match &args[..] {
+        [Value::String(s)] => Ok(Value::String(s.trim().to_string())),
+        [_, _] => super::seq::drop(args, env),
+        _ => Err(Value::error("InvalidArgs@trim", args)),
+    }
+}
+
+pub fn starts_with(args: Vec<Value>, _env: Env) -> Result<Value, Value> {
+    let [Value::String(s), Value::String(prefix)] = &args[..] else {
+        return Err(Value::error("InvalidArgs@starts-with?", args));
+    };
+    Ok(s.starts_with(prefix.as_str()).into_value())
+}
+
+pub fn ends_with(args: Vec<Value>, _env: Env) -> Result<Value, Value> {
+    let [Value::String(s), Value::String(suffix)] = &args[..] else {
+        return Err(Value::error("InvalidArgs@ends-with?", args));
+    };
+    Ok(s.ends_with(suffix.as_str()).into_value())
+}
+
+pub fn index_of(args: Vec<Value>, _env: Env) -> Result<Value, Value> {
+    let [Value::String(s), Value::String(sub)] = &args[..] else {
+        return Err(Value::error("InvalidArgs@index-of", args));
+    };
+    Ok(s.find(sub.as_str())
+        .map_or_else(Value::nil, |byte| int(s[..byte].chars().count())))
+}
+
+pub fn replace(args: Vec<Value>, _env: Env) -> Result<Value, Value> {
+    let [Value::String(s), Value::String(from), Value::String(to)] = &args[..] else {
+        return Err(Value::error("InvalidArgs@replace", args));
+    };
+    if from.is_empty() {
+        return Err(Value::error("InvalidArgs@replace", args));
+    }
+    Ok(Value::String(s.replace(from.as_str(), to)))
+}
+
+pub fn chars(args: Vec<Value>, _env: Env) -> Result<Value, Value> {
+    let [Value::String(s)] = &args[..] else {
+        return Err(Value::error("InvalidArgs@chars", args));
+    };
+    Ok(Value::List(
+        s.chars().map(|c| Value::String(c.to_string())).collect(),
+    ))
+}
+
+pub fn ord(args: Vec<Value>, _env: Env) -> Result<Value, Value> {
+    let [Value::String(s)] = &args[..] else {
+        return Err(Value::error("InvalidArgs@ord", args));
+    };
+    let mut chars = s.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) => Ok(Value::Int(i128::from(u32::from(c)))),
+        _ => Err(Value::error("NotAChar", args)),
+    }
+}
+
+/// # Panics
+pub fn format(args: Vec<Value>, _env: Env) -> Result<Value, Value> {
+    let Some((Value::String(template), values)) = args.split_first() else {
+        return Err(Value::error("InvalidArgs@format", args));
+    };
+    let error = |reason: &str| {
+        Value::error(
+            "FormatError",
+            vec![
+                Value::String(reason.to_string()),
+                Value::String(template.clone()),
+            ],
+        )
+    };
+    let mut values = values.iter();
+    let mut out = String::new();
+    let mut chars = template.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (c, chars.peek()) {
+            ('{', Some('{')) | ('}', Some('}')) => {
+                chars.next();
+                out.push(c);
+            }
+            ('{', Some('}')) => {
+                chars.next();
+                let Some(value) = values.next() else {
+                    return Err(error("too few arguments"));
+                };
+                write!(out, "{value}").unwrap();
+            }
+            ('{' | '}', _) => return Err(error("unmatched brace")),
+            _ => out.push(c),
+        }
+    }
+    if values.next().is_some() {
+        return Err(error("too many arguments"));
+    }
+    Ok(Value::String(out))
+}
@@ -0,0 +1,249 @@
+use std::rc::Rc;
+
+use crate::{
+    env::Env,
+    eval::expand,
+    types::{symbol::SymbolMap, List, Symbol, Value},
+};
+
+pub fn gensym(args: Vec<Value>, _env: Env) -> Result<Value, Value> {
+    match &args[..] {
+        [] => Ok(Value::Symbol(Symbol::gensym("G"))),
+        [Value::String(prefix)] => Ok(Value::Symbol(Symbol::gensym(prefix))),
+        [Value::Symbol(prefix)] => Ok(Value::Symbol(Symbol::gensym(&prefix.name()))),
+        _ => Err(Value::error("InvalidArgs@gensym", args)),
+    }
+}
+
+pub fn macroexpand_1(args: Vec<Value>, env: Env) -> Result<Value, Value> {
+    let [form] = &args[..] else {
+        return Err(Value::error("InvalidArgs@macroexpand-1", args));
+    };
+    Ok(expand::expand_1(form, &env)?.map_or_else(|| form.clone(), |(_, expansion)| expansion))
+}
+
+pub fn macroexpand(mut args: Vec<Value>, env: Env) -> Result<Value, Value> {
+    if args.len() != 1 {
+        return Err(Value::error("InvalidArgs@macroexpand", args));
+    }
+    expand::expand(args.remove(0), &env, |_, _| {})
+}
+
+pub fn macroexpand_all(mut args: Vec<Value>, env: Env) -> Result<Value, Value> {
+    if args.len() != 1 {
+        return Err(Value::error("InvalidArgs@macroexpand-all", args));
+    }
+    expand::expand_all(args.remove(0), &env, &mut |_, _| {})
+}
+
+/// What a pattern variable matched
+#[derive(Clone)]
+enum Binding {
+    One(Value),
+    /// What it matched in each form matched by a pattern followed by `...`
+    Many(Vec<Self>),
+}
+
+type Bindings = SymbolMap<Binding>;
+
+/// The rules of a macro made by `syntax-rules`
+struct Rules {
+    /// Symbols that patterns match literally, rather than binding
+    literals: Vec<Symbol>,
+    /// The patterns for the arguments of each rule, without the macro's name, and their templates
+    rules: Vec<(List, Value)>,
+}
+
+pub fn syntax_rules(args: Vec<Value>, _env: Env) -> Result<Value, Value> {
+    let invalid = || Value::error("InvalidArgs@syntax-rules", args.clone());
+    let Some((Value::List(literals), rules)) = args.split_first() else {
+        return Err(invalid());
+    };
+    let literals = literals
+        .iter()
+        .map(|literal| match literal {
+            Value::Symbol(name) => Ok(*name),
+            _ => Err(invalid()),
+        })
+        .collect::<Result<_, _>>()?;
+    let rules = rules
+        .iter()
+        .map(|rule| match rule {
+            Value::List(rule) => match &rule.values()[..] {
+                [Value::List(pattern), template] if !pattern.is_empty() => {
+                    Ok((pattern.rest(), template.clone()))
+                }
+                _ => Err(invalid()),
+            },
+            _ => Err(invalid()),
+        })
+        .collect::<Result<_, _>>()?;
+    let rules = Rules { literals, rules };
+    Ok(Value::Function {
+        fn_ref: Rc::new(move |forms, env| rules.expand(forms, env)),
+        is_macro: true,
+    })
+}
+
+impl Rules {
+    /// Fill in the template of the first rule whose pattern matches the forms a macro is given
+    fn expand(&self, forms: Vec<Value>, env: Env) -> Result<Value, Value> {
+        for (pattern, template) in &self.rules {
+            let mut bindings = Bindings::default();
+            if self.match_all(&pattern.values(), &forms, &mut bindings) {
+                return self.fill(template, &bindings, false)?.quasiquote(&env);
+            }
+        }
+        Err(Value::error("NoMatchingRule", forms))
+    }
+
+    fn is_variable(&self, name: Symbol) -> bool {
+        name != Symbol::WILDCARD && name != Symbol::ELLIPSIS && !self.literals.contains(&name)
+    }
+
+    /// Add the pattern variables in a pattern
+    fn variables(&self, pattern: &Value, out: &mut Vec<Symbol>) {
+        match pattern {
+            Value::Symbol(name) if self.is_variable(*name) => out.push(*name),
+            Value::List(patterns) => {
+                for pattern in patterns {
+                    self.variables(pattern, out);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn matches(&self, pattern: &Value, form: &Value, out: &mut Bindings) -> bool {
+        match pattern {
+            Value::Symbol(name) if !self.is_variable(*name) => {
+                *name == Symbol::WILDCARD || form.is_symbol(*name)
+            }
+            Value::Symbol(name) => {
+                out.insert(*name, Binding::One(form.clone()));
+                true
+            }
+            Value::List(patterns) => {
+                let Value::List(forms) = form else {
+                    return false;
+                };
+                self.match_all(&patterns.values(), &forms.values(), out)
+            }
+            other => other == form,
+        }
+    }
+
+    /// Match a list of forms against a list of patterns, one of which can be followed by `...` to match any
+    /// number of forms
+    fn match_all(&self, patterns: &[Value], forms: &[Value], out: &mut Bindings) -> bool {
+        let ellipsis = patterns
+            .iter()
+            .skip(1)
+            .position(|pattern| pattern.is_symbol(Symbol::ELLIPSIS));
+        let Some(i) = ellipsis else {
+            return patterns.len() == forms.len()
+                && patterns
+                    .iter()
+                    .zip(forms)
+                    .all(|(pattern, form)| self.matches(pattern, form, out));
+        };
+        let (before, repeated, after) = (&patterns[..i], &patterns[i], &patterns[i + 2..]);
+        if forms.len() < before.len() + after.len() {
+            return false;
+        }
+        let end = forms.len() - after.len();
+        if !self.match_all(before, &forms[..i], out) || !self.match_all(after, &forms[end..], out) {
+            return false;
+        }
+        let mut each = Vec::new();
+        for form in &forms[i..end] {
+            let mut bindings = Bindings::default();
+            if !self.matches(repeated, form, &mut bindings) {
+                return false;
+            }
+            each.push(bindings);
+        }
+        let mut names = Vec::new();
+        self.variables(repeated, &mut names);
+        for name in names {
+            let matched = each.iter_mut().filter_map(|b| b.remove(&name)).collect();
+            out.insert(name, Binding::Many(matched));
+        }
+        true
+    }
+
+    /// Turn a template into a quasiquote template, with each pattern variable replaced by what it matched.
+    /// Inside an unquoted expression, which is evaluated when the macro expands, what a variable matched is
+    /// quoted.
+    fn fill(&self, template: &Value, bindings: &Bindings, unquoted: bool) -> Result<Value, Value> {
+        match template {
+            Value::Symbol(name) => match bindings.get(name) {
+                Some(Binding::One(form)) => {
+                    let quoted =
+                        Value::List(List::from([Value::Symbol(Symbol::QUOTE), form.clone()]));
+                    Ok(if unquoted {
+                        quoted
+                    } else {
+                        Value::List(List::from([Value::Symbol(Symbol::UNQUOTE), quoted]))
+                    })
+                }
+                Some(Binding::Many(_)) => {
+                    Err(Value::error("InvalidTemplate", vec![template.clone()]))
+                }
+                None => Ok(template.clone()),
+            },
+            Value::List(items)
+                if !unquoted
+                    && items.first().is_some_and(|head| {
+                        head.is_symbol(Symbol::UNQUOTE) || head.is_symbol(Symbol::SPLICE_UNQUOTE)
+                    }) =>
+            {
+                let mut filled: Vec<_> = items.iter().take(1).cloned().collect();
+                for item in items.iter().skip(1) {
+                    filled.push(self.fill(item, bindings, true)?);
+                }
+                Ok(Value::List(filled.into()))
+            }
+            Value::List(items) => {
+                let mut filled = Vec::new();
+                let mut items = items.iter().peekable();
+                while let Some(item) = items.next() {
+                    if items
+                        .next_if(|next| next.is_symbol(Symbol::ELLIPSIS))
+                        .is_none()
+                    {
+                        filled.push(self.fill(item, bindings, unquoted)?);
+                        continue;
+                    }
+                    let mut names = Vec::new();
+                    self.variables(item, &mut names);
+                    let repeated: Vec<(Symbol, &[Binding])> = names
+                        .into_iter()
+                        .filter_map(|name| match bindings.get(&name) {
+                            Some(Binding::Many(matched)) => Some((name, &matched[..])),
+                            _ => None,
+                        })
+                        .collect();
+                    let Some(&(_, first)) = repeated.first() else {
+                        return Err(Value::error("InvalidTemplate", vec![item.clone()]));
+                    };
+                    if repeated
+                        .iter()
+                        .any(|(_, matched)| matched.len() != first.len())
+                    {
+                        return Err(Value::error("InvalidTemplate", vec![item.clone()]));
+                    }
+                    for n in 0..first.len() {
+                        let mut inner = bindings.clone();
+                        for (name, matched) in &repeated {
+                            inner.insert(*name, matched[n].clone());
+                        }
+                        filled.push(self.fill(item, &inner, unquoted)?);
+                    }
+                }
+                Ok(Value::List(filled.into()))
+            }
+            other => Ok(other.clone()),
+        }
+    }
+}
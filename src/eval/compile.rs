@@ -0,0 +1,498 @@
+use std::rc::Rc;
+
+use super::scope::{self, Analysis};
+use crate::{
+    span::{self, Span},
+    types::{List, Symbol, Value},
+};
+
+/// An instruction for the VM. Instructions that refer to values, forms, or errors hold the index of a
+/// constant in their chunk, and those that refer to names hold the symbol.
+#[derive(Clone, Copy, Debug)]
+pub(super) enum Op {
+    /// Push a constant
+    Const(usize),
+    /// Push the value a symbol is bound to
+    Load(Symbol),
+    /// Push the value of a local variable, from the slot of the scope `depth` scopes out where the compiler
+    /// found it. Looks the name up as `Load` does if it isn't there.
+    Local {
+        name: Symbol,
+        depth: usize,
+        index: usize,
+    },
+    /// Discard the value on top of the stack
+    Pop,
+    /// Continue from another instruction
+    Jump(usize),
+    /// Pop a value, and continue from another instruction if it is falsy
+    JumpIfFalse(usize),
+    /// Raise a constant error
+    Fail(usize),
+    /// Create a lambda from a parameter pattern and a body, capturing the environment
+    Lambda { params: usize, body: usize },
+    /// Continue in a new environment inside the current one, for the rest of the frame
+    NewScope,
+    /// Save the environment and continue in a new one inside it, until `ExitScope`
+    EnterScope,
+    /// Go back to the environment saved by the last `EnterScope`
+    ExitScope,
+    /// Pop a value and bind it to a pattern, raising a constant error if it doesn't match
+    Bind { pattern: usize, err: usize },
+    /// Mark the names of the current scope as bound, once each of its bindings is made, before a constant
+    /// form is evaluated in it
+    Seal(usize),
+    /// Pop a value and define a symbol as it, as `def!` does. Pushes nil.
+    Define(Symbol),
+    /// Create a lazy sequence that evaluates a body when it is first needed
+    LazySeq(usize),
+    /// Fill in a quasiquoted template
+    Quasiquote(usize),
+    /// Pop values into an error and raise it
+    Raise(usize),
+    /// Pop a value for each key in a constant list of keys, making a table
+    Table(usize),
+    /// Pop values into a set
+    Set(usize),
+    /// Pop a sequence and a value, and push the sequence with the value added to the front
+    Cons,
+    /// Pop a value to add to the front of the sequence the frame returns, as `cons` in tail position does
+    TailCons,
+    /// Pop a sequence. If it is empty, continue from another instruction. Otherwise push the rest of it,
+    /// then its first value.
+    Step(usize),
+    /// If the value on top of the stack is a macro, pop it and evaluate its expansion of a constant call
+    /// form, continuing from `after`. Otherwise continue on to evaluate the arguments of the call.
+    Expand {
+        form: usize,
+        after: usize,
+        tail: bool,
+    },
+    /// Pop arguments and a function, and call the function. A lambda in tail position replaces the frame.
+    Call {
+        argc: usize,
+        form: usize,
+        tail: bool,
+    },
+    /// Make `recur` go back to an instruction with new values for a constant list of patterns, then
+    /// continue in a new environment to bind their first values
+    Loop {
+        patterns: usize,
+        arity: usize,
+        /// The constant body of the loop
+        form: usize,
+        body: usize,
+    },
+    /// Raise a constant error unless there is a loop or lambda to recur to
+    CheckRecur(usize),
+    /// Pop arguments and go back to the innermost loop or lambda with them
+    Recur(usize),
+    /// Evaluate a chunk in a new frame, handling its errors with a constant list of catch blocks
+    Try { body: usize, catches: usize },
+    /// Evaluate a chunk in a new frame
+    Nested(usize),
+    /// Evaluate a constant form with the tree-walking engine
+    Walk(usize),
+    /// Pop the value the frame returns
+    Return,
+}
+
+/// A form compiled to instructions
+#[derive(Default)]
+pub(super) struct Chunk {
+    pub(super) code: Vec<Op>,
+    pub(super) constants: Vec<Value>,
+    /// Chunks evaluated in frames of their own, by `Try` and `Nested`
+    pub(super) chunks: Vec<Rc<Self>>,
+    /// For each instruction, the innermost form it came from that has a location, as an index into `spans`
+    pub(super) locations: Vec<Option<usize>>,
+    pub(super) spans: Vec<Span>,
+    /// For each instruction, whether it is part of a form outside of tail position, which the tree-walking
+    /// engine evaluates separately
+    pub(super) inner: Vec<bool>,
+}
+
+/// Compile a form, so that running the chunk evaluates the form with the same results as the tree-walking
+/// engine.
+///
+/// Special forms are recognized by name as they are compiled. Whether a call is to a macro can only be known
+/// once the function is evaluated, so a call checks for a macro when it is run and compiles its expansion then.
+/// `doc`, `help`, `import` and `require` are left to the tree-walking engine.
+pub(super) fn compile(form: &Value) -> Chunk {
+    Compiler::default().finish(form)
+}
+
+/// Compile the body of a lambda, resolving the variables its pattern and the scopes in it bind to where
+/// they will be bound. The variables it captures are looked up by name, since globals are among them.
+pub(super) fn compile_lambda(analysis: &Analysis, body: &Value) -> Chunk {
+    let mut compiler = Compiler::default();
+    if !analysis.dynamic {
+        compiler.scopes = Some(vec![analysis.params.clone()]);
+    }
+    compiler.finish(body)
+}
+
+#[derive(Default)]
+struct Compiler {
+    chunk: Chunk,
+    /// The innermost form being compiled that has a location
+    location: Option<usize>,
+    /// Whether the form being compiled is outside of tail position
+    inner: bool,
+    /// The names bound in each scope around the form being compiled, innermost last, starting from a
+    /// lambda's pattern. `None` outside of a lambda, where variables are looked up by name.
+    scopes: Option<Vec<Vec<Symbol>>>,
+}
+
+impl Compiler {
+    fn finish(mut self, form: &Value) -> Chunk {
+        self.expr(form, true);
+        self.emit(Op::Return);
+        self.chunk
+    }
+
+    fn emit(&mut self, op: Op) -> usize {
+        self.chunk.code.push(op);
+        self.chunk.locations.push(self.location);
+        self.chunk.inner.push(self.inner);
+        self.chunk.code.len() - 1
+    }
+
+    fn constant(&mut self, value: Value) -> usize {
+        self.chunk.constants.push(value);
+        self.chunk.constants.len() - 1
+    }
+
+    fn push(&mut self, value: Value) {
+        let i = self.constant(value);
+        self.emit(Op::Const(i));
+    }
+
+    fn fail(&mut self, name: &str, args: Vec<Value>) {
+        let i = self.constant(Value::error(name, args));
+        self.emit(Op::Fail(i));
+    }
+
+    /// The index of the next instruction
+    const fn here(&self) -> usize {
+        self.chunk.code.len()
+    }
+
+    /// Point a jump at the next instruction
+    fn patch(&mut self, at: usize) {
+        let here = self.here();
+        match &mut self.chunk.code[at] {
+            Op::Jump(target) | Op::JumpIfFalse(target) | Op::Step(target) => *target = here,
+            Op::Expand { after, .. } => *after = here,
+            Op::Loop { body, .. } => *body = here,
+            _ => unreachable!(),
+        }
+    }
+
+    /// Compile a form into a chunk that runs in a frame of its own, in the same scope
+    fn nested(&mut self, form: &Value) -> usize {
+        let compiler = Self {
+            scopes: self.scopes.clone(),
+            ..Self::default()
+        };
+        self.chunk.chunks.push(Rc::new(compiler.finish(form)));
+        self.chunk.chunks.len() - 1
+    }
+
+    fn enter_scope(&mut self) {
+        if let Some(scopes) = &mut self.scopes {
+            scopes.push(Vec::new());
+        }
+    }
+
+    fn exit_scope(&mut self) {
+        if let Some(scopes) = &mut self.scopes {
+            scopes.pop();
+        }
+    }
+
+    /// Add the names a pattern binds to the current scope
+    fn bind(&mut self, pattern: &Value) {
+        if let Some(scope) = self.scopes.as_mut().and_then(|scopes| scopes.last_mut()) {
+            scope::names(pattern, scope);
+        }
+    }
+
+    fn load(&mut self, name: Symbol) {
+        let slot = self.scopes.as_ref().and_then(|scopes| {
+            scopes.iter().rev().enumerate().find_map(|(depth, scope)| {
+                let index = scope.iter().position(|n| *n == name)?;
+                Some((depth, index))
+            })
+        });
+        self.emit(match slot {
+            Some((depth, index)) => Op::Local { name, depth, index },
+            None => Op::Load(name),
+        });
+    }
+
+    /// Compile a form. A form in tail position is the last thing its frame evaluates, so it can replace the
+    /// frame, as lambda calls and `recur` do.
+    fn expr(&mut self, form: &Value, tail: bool) {
+        let outer = self.inner;
+        self.inner |= !tail;
+        self.form(form, tail);
+        self.inner = outer;
+    }
+
+    fn form(&mut self, form: &Value, tail: bool) {
+        match form {
+            Value::List(arr) => {
+                let outer = self.location;
+                if let Some(span) = span::of(arr) {
+                    self.chunk.spans.push(span);
+                    self.location = Some(self.chunk.spans.len() - 1);
+                }
+                self.list(form, &arr.values(), tail);
+                self.location = outer;
+            }
+            Value::Symbol(Symbol::TRUE | Symbol::FALSE | Symbol::NIL) => self.push(form.clone()),
+            Value::Symbol(id) => self.load(*id),
+            Value::Table(table) => {
+                let mut keys = Vec::new();
+                for (k, v) in table {
+                    keys.push(k.clone());
+                    self.expr(v, false);
+                }
+                let i = self.constant(Value::List(keys.into()));
+                self.emit(Op::Table(i));
+            }
+            Value::Set(set) => {
+                for v in set {
+                    self.expr(v, false);
+                }
+                self.emit(Op::Set(set.len()));
+            }
+            other => self.push(other.clone()),
+        }
+    }
+
+    #[allow(clippy::too_many_lines)]
+    fn list(&mut self, form: &Value, arr: &[Value], tail: bool) {
+        let Some(Value::Symbol(head)) = arr.first() else {
+            if arr.is_empty() {
+                self.push(Value::List(List::new()));
+            } else {
+                self.call(form, arr, tail);
+            }
+            return;
+        };
+        match *head {
+            Symbol::LAMBDA => {
+                let [param, body] = &arr[1..] else {
+                    return self.fail("InvalidLambdaError", arr.to_vec());
+                };
+                let params = self.constant(param.clone());
+                let body = self.constant(body.clone());
+                self.emit(Op::Lambda { params, body });
+            }
+            Symbol::IF => {
+                let (condition, t, f) = match &arr[1..] {
+                    [condition, t] => (condition, t, None),
+                    [condition, t, f] => (condition, t, Some(f)),
+                    _ => return self.fail("InvalidArgs@if", vec![form.clone()]),
+                };
+                self.expr(condition, false);
+                let otherwise = self.emit(Op::JumpIfFalse(0));
+                self.expr(t, tail);
+                let end = self.emit(Op::Jump(0));
+                self.patch(otherwise);
+                match f {
+                    Some(f) => self.expr(f, tail),
+                    None => self.push(Value::nil()),
+                }
+                self.patch(end);
+            }
+            Symbol::QUOTE => match arr.get(1) {
+                Some(quoted) => self.push(quoted.clone()),
+                None => self.fail("InvalidArgs@quote", arr.to_vec()),
+            },
+            Symbol::QUASIQUOTE => match arr.get(1) {
+                Some(template) => {
+                    let i = self.constant(template.clone());
+                    self.emit(Op::Quasiquote(i));
+                }
+                None => self.fail("InvalidArgs@quasiquote", arr.to_vec()),
+            },
+            Symbol::ERR => {
+                // the name isn't evaluated, but everything after it is
+                if let Some(name) = arr.get(1) {
+                    self.push(name.clone());
+                }
+                for arg in arr.iter().skip(2) {
+                    self.expr(arg, false);
+                }
+                self.emit(Op::Raise(arr.len() - 1));
+            }
+            Symbol::CONS => {
+                let [_, first, rest] = arr else {
+                    return self.fail("InvalidArgs@cons", arr.to_vec());
+                };
+                self.expr(first, false);
+                if tail {
+                    self.emit(Op::TailCons);
+                    self.expr(rest, true);
+                } else {
+                    self.expr(rest, false);
+                    self.emit(Op::Cons);
+                }
+            }
+            Symbol::LAZY_SEQ => {
+                let [_, body] = arr else {
+                    return self.fail("InvalidArgs@lazy-seq", arr.to_vec());
+                };
+                let i = self.constant(body.clone());
+                self.emit(Op::LazySeq(i));
+            }
+            Symbol::DOSEQ => {
+                let Some((Value::List(binding), body)) = arr[1..].split_first() else {
+                    return self.fail("InvalidArgs@doseq", arr.to_vec());
+                };
+                let [pattern, seq] = &binding.values()[..] else {
+                    return self.fail("InvalidArgs@doseq", arr.to_vec());
+                };
+                self.expr(seq, false);
+                let top = self.emit(Op::Step(0));
+                self.emit(Op::EnterScope);
+                self.enter_scope();
+                self.bind(pattern);
+                let pattern = self.constant(pattern.clone());
+                let err = self.constant(Value::error("PatternMismatch", arr.to_vec()));
+                self.emit(Op::Bind { pattern, err });
+                let i = self.constant(form.clone());
+                self.emit(Op::Seal(i));
+                for form in body {
+                    self.expr(form, false);
+                    self.emit(Op::Pop);
+                }
+                self.emit(Op::ExitScope);
+                self.exit_scope();
+                self.emit(Op::Jump(top));
+                self.patch(top);
+                self.push(Value::nil());
+            }
+            Symbol::LET => {
+                let [_, Value::List(assigns), body] = arr else {
+                    return self.fail("InvalidArgs@let*", arr.to_vec());
+                };
+                let assigns = assigns.values();
+                self.emit(if tail { Op::NewScope } else { Op::EnterScope });
+                self.enter_scope();
+                let err = self.constant(Value::error("PatternMismatch", arr.to_vec()));
+                for i in 0..(assigns.len() / 2) {
+                    self.expr(&assigns[2 * i + 1], false);
+                    self.bind(&assigns[2 * i]);
+                    let pattern = self.constant(assigns[2 * i].clone());
+                    self.emit(Op::Bind { pattern, err });
+                }
+                let i = self.constant(body.clone());
+                self.emit(Op::Seal(i));
+                self.expr(body, tail);
+                if !tail {
+                    self.emit(Op::ExitScope);
+                }
+                self.exit_scope();
+            }
+            Symbol::LOOP if !tail => {
+                // a loop needs a frame to recur in
+                let i = self.nested(form);
+                self.emit(Op::Nested(i));
+            }
+            Symbol::LOOP => {
+                let [_, Value::List(assigns), body] = arr else {
+                    return self.fail("InvalidArgs@loop", arr.to_vec());
+                };
+                let assigns = assigns.values();
+                if assigns.len() % 2 != 0 {
+                    return self.fail("InvalidArgs@loop", arr.to_vec());
+                }
+                let patterns =
+                    self.constant(Value::List(assigns.iter().step_by(2).cloned().collect()));
+                let i = self.constant(body.clone());
+                let start = self.emit(Op::Loop {
+                    patterns,
+                    arity: assigns.len() / 2,
+                    form: i,
+                    body: 0,
+                });
+                self.enter_scope();
+                let err = self.constant(Value::error("PatternMismatch", arr.to_vec()));
+                for pair in assigns.chunks(2) {
+                    self.expr(&pair[1], false);
+                    self.bind(&pair[0]);
+                    let pattern = self.constant(pair[0].clone());
+                    self.emit(Op::Bind { pattern, err });
+                }
+                self.emit(Op::Seal(i));
+                self.patch(start);
+                self.expr(body, true);
+                self.exit_scope();
+            }
+            Symbol::RECUR if !tail => self.fail("RecurNotInTailPosition", arr.to_vec()),
+            Symbol::RECUR => {
+                let err = self.constant(Value::error("RecurNotInTailPosition", arr.to_vec()));
+                self.emit(Op::CheckRecur(err));
+                for arg in arr.iter().skip(1) {
+                    self.expr(arg, false);
+                }
+                self.emit(Op::Recur(arr.len() - 1));
+            }
+            Symbol::DEF => {
+                let [_, Value::Symbol(name), value] = arr else {
+                    return self.fail("InvalidArgs@def!", arr.to_vec());
+                };
+                self.expr(value, false);
+                self.emit(Op::Define(*name));
+            }
+            Symbol::DO => {
+                for form in arr.iter().take(arr.len() - 1).skip(1) {
+                    self.expr(form, false);
+                    self.emit(Op::Pop);
+                }
+                self.expr(arr.last().unwrap(), tail);
+            }
+            Symbol::TRY if !tail => {
+                // the catch blocks are evaluated in the frame the try* is in
+                let i = self.nested(form);
+                self.emit(Op::Nested(i));
+            }
+            Symbol::TRY => {
+                let Some(body) = arr.get(1) else {
+                    return self.fail("InvalidArgs@try*", arr.to_vec());
+                };
+                let body = self.nested(body);
+                let catches = self.constant(Value::List(arr[2..].into()));
+                self.emit(Op::Try { body, catches });
+            }
+            Symbol::DOC | Symbol::IMPORT | Symbol::REQUIRE | Symbol::HELP => {
+                let i = self.constant(form.clone());
+                self.emit(Op::Walk(i));
+            }
+            _ => self.call(form, arr, tail),
+        }
+    }
+
+    fn call(&mut self, form: &Value, arr: &[Value], tail: bool) {
+        let form = self.constant(form.clone());
+        self.expr(&arr[0], false);
+        let expand = self.emit(Op::Expand {
+            form,
+            after: 0,
+            tail,
+        });
+        for arg in arr.iter().skip(1) {
+            self.expr(arg, false);
+        }
+        self.emit(Op::Call {
+            argc: arr.len() - 1,
+            form,
+            tail,
+        });
+        self.patch(expand);
+    }
+}
@@ -0,0 +1,290 @@
+//! Expanding macros without evaluating what they expand to, so expansions can be looked at.
+//!
+//! Only forms whose head is a name bound to a macro are expanded, since finding out what any other head
+//! evaluates to could run code. The names of special forms are never macros, as the evaluator handles them
+//! before looking at what the head is bound to.
+use crate::{
+    env::{new_env, Env},
+    types::{List, Symbol, Value},
+};
+
+use super::{destructure, qualified};
+
+/// Expand a macro given the unevaluated arguments of the form it heads
+pub(crate) fn apply(mac: Value, args: &[Value], env: &Env) -> Result<Value, Value> {
+    match mac {
+        Value::Function { fn_ref, .. } => fn_ref(args.to_vec(), env.clone()),
+        Value::Lambda {
+            args: params,
+            body,
+            captures,
+            ..
+        } => {
+            let sub_env = new_env(captures);
+            if destructure(&params, Value::List(args.into()), &sub_env).is_none() {
+                return Err(Value::error("PatternMismatch", vec![*params]));
+            }
+            sub_env.borrow_mut().seal(&body);
+            super::eval(*body, sub_env)
+        }
+        other => Err(Value::error("NotAFunction", vec![other])),
+    }
+}
+
+pub(super) const fn is_special(name: Symbol) -> bool {
+    matches!(
+        name,
+        Symbol::LAMBDA
+            | Symbol::IF
+            | Symbol::QUOTE
+            | Symbol::QUASIQUOTE
+            | Symbol::ERR
+            | Symbol::CONS
+            | Symbol::LAZY_SEQ
+            | Symbol::DOSEQ
+            | Symbol::LET
+            | Symbol::LOOP
+            | Symbol::RECUR
+            | Symbol::DEF
+            | Symbol::DO
+            | Symbol::TRY
+            | Symbol::DOC
+            | Symbol::IMPORT
+            | Symbol::REQUIRE
+            | Symbol::HELP
+    )
+}
+
+/// The name and value of the macro a form is headed by, if it is headed by one
+fn macro_of(form: &Value, env: &Env) -> Option<(Symbol, Value)> {
+    let Value::List(list) = form else {
+        return None;
+    };
+    let Some(Value::Symbol(name)) = list.first() else {
+        return None;
+    };
+    if is_special(*name) {
+        return None;
+    }
+    let value = env.borrow().get(*name).or_else(|| qualified(*name, env))?;
+    matches!(
+        value,
+        Value::Function { is_macro: true, .. } | Value::Lambda { is_macro: true, .. }
+    )
+    .then_some((*name, value))
+}
+
+/// Expand a form once if it is headed by a macro, returning the macro's name and what it expanded to
+///
+/// # Errors
+/// Whatever the macro raises
+pub fn expand_1(form: &Value, env: &Env) -> Result<Option<(Symbol, Value)>, Value> {
+    let Some((name, mac)) = macro_of(form, env) else {
+        return Ok(None);
+    };
+    let Value::List(list) = form else {
+        unreachable!()
+    };
+    Ok(Some((name, apply(mac, &list.values()[1..], env)?)))
+}
+
+/// Expand a form until it isn't headed by a macro, calling `step` with the macro responsible for and the
+/// result of each expansion
+///
+/// # Errors
+/// Whatever a macro raises
+pub fn expand(
+    mut form: Value,
+    env: &Env,
+    mut step: impl FnMut(Symbol, &Value),
+) -> Result<Value, Value> {
+    while let Some((name, expansion)) = expand_1(&form, env)? {
+        step(name, &expansion);
+        form = expansion;
+    }
+    Ok(form)
+}
+
+/// Expand a form and every form within it that is evaluated as code, calling `step` as [`expand`] does.
+///
+/// Quoted forms, patterns, and the names bound by special forms are left as they are, as are quasiquoted
+/// templates apart from the parts of them that are unquoted. A form isn't expanded if its head is a name
+/// that a lambda, `let*`, `loop`, `doseq` or `catch*` around it binds, since that name doesn't refer to a
+/// macro there.
+///
+/// # Errors
+/// Whatever a macro raises
+pub fn expand_all<F: FnMut(Symbol, &Value)>(
+    form: Value,
+    env: &Env,
+    step: &mut F,
+) -> Result<Value, Value> {
+    Expander { env, step }.form(form, &[])
+}
+
+struct Expander<'a, F> {
+    env: &'a Env,
+    step: &'a mut F,
+}
+
+impl<F: FnMut(Symbol, &Value)> Expander<'_, F> {
+    /// Expand the forms evaluated in a scope where `local` names are bound
+    fn all(&mut self, forms: &[Value], local: &[Symbol]) -> Result<Vec<Value>, Value> {
+        forms
+            .iter()
+            .map(|form| self.form(form.clone(), local))
+            .collect()
+    }
+
+    /// Expand a form evaluated in a scope where `local` names are bound
+    fn form(&mut self, mut form: Value, local: &[Symbol]) -> Result<Value, Value> {
+        while !form
+            .as_list()
+            .and_then(List::first)
+            .is_some_and(|head| matches!(head, Value::Symbol(name) if local.contains(name)))
+        {
+            let Some((name, expansion)) = expand_1(&form, self.env)? else {
+                break;
+            };
+            (self.step)(name, &expansion);
+            form = expansion;
+        }
+        let Value::List(list) = &form else {
+            return Ok(form);
+        };
+        let list = list.values();
+        let Some(Value::Symbol(head)) = list.first() else {
+            return Ok(Value::List(self.all(&list, local)?.into()));
+        };
+        let args = &list[1..];
+        let expanded = match *head {
+            Symbol::QUOTE | Symbol::DOC | Symbol::HELP | Symbol::IMPORT | Symbol::REQUIRE => {
+                return Ok(form);
+            }
+            Symbol::QUASIQUOTE => args
+                .iter()
+                .map(|template| self.template(template, 0, local))
+                .collect::<Result<_, _>>()?,
+            Symbol::ERR => match args.split_first() {
+                Some((name, rest)) => [vec![name.clone()], self.all(rest, local)?].concat(),
+                None => Vec::new(),
+            },
+            Symbol::DEF => match args {
+                [name, code] => vec![name.clone(), self.form(code.clone(), local)?],
+                _ => args.to_vec(),
+            },
+            Symbol::LAMBDA => match args {
+                [pattern, body] => {
+                    let local = bind(local, pattern);
+                    vec![pattern.clone(), self.form(body.clone(), &local)?]
+                }
+                _ => args.to_vec(),
+            },
+            Symbol::LET | Symbol::LOOP => match args {
+                [Value::List(assigns), body] => {
+                    // each value is evaluated after the names before it are bound
+                    let mut local = local.to_vec();
+                    let mut expanded = Vec::new();
+                    for pair in assigns.values().chunks(2) {
+                        expanded.push(pair[0].clone());
+                        expanded.extend(self.all(&pair[1..], &local)?);
+                        local = bind(&local, &pair[0]);
+                    }
+                    vec![
+                        Value::List(expanded.into()),
+                        self.form(body.clone(), &local)?,
+                    ]
+                }
+                _ => args.to_vec(),
+            },
+            Symbol::DOSEQ => match args.split_first() {
+                Some((Value::List(binding), body)) if binding.len() == 2 => {
+                    let binding = binding.values();
+                    let inner = bind(local, &binding[0]);
+                    let binding = [binding[0].clone(), self.form(binding[1].clone(), local)?];
+                    [
+                        vec![Value::List(List::from(binding))],
+                        self.all(body, &inner)?,
+                    ]
+                    .concat()
+                }
+                _ => args.to_vec(),
+            },
+            Symbol::TRY => match args.split_first() {
+                Some((body, catches)) => {
+                    let mut expanded = vec![self.form(body.clone(), local)?];
+                    for catch in catches {
+                        expanded.push(match catch {
+                            // (catch* name body) or (catch* ErrorName name body)
+                            Value::List(catch) if catch.len() >= 3 => {
+                                let [start @ .., name, body] = &catch.values()[..] else {
+                                    unreachable!()
+                                };
+                                let local = bind(local, name);
+                                let mut catch = start.to_vec();
+                                catch.push(name.clone());
+                                catch.push(self.form(body.clone(), &local)?);
+                                Value::List(catch.into())
+                            }
+                            other => other.clone(),
+                        });
+                    }
+                    expanded
+                }
+                None => Vec::new(),
+            },
+            _ if is_special(*head) => self.all(args, local)?,
+            _ => return Ok(Value::List(self.all(&list, local)?.into())),
+        };
+        Ok(Value::List(
+            [vec![list[0].clone()], expanded].concat().into(),
+        ))
+    }
+
+    /// Expand the unquoted parts of a quasiquoted template nested `depth` quasiquotes deep
+    fn template(
+        &mut self,
+        template: &Value,
+        depth: usize,
+        local: &[Symbol],
+    ) -> Result<Value, Value> {
+        let Value::List(list) = template else {
+            return Ok(template.clone());
+        };
+        let list = list.values();
+        let expanded = match list.first() {
+            Some(head)
+                if head.is_symbol(Symbol::UNQUOTE) || head.is_symbol(Symbol::SPLICE_UNQUOTE) =>
+            {
+                let parts = if depth == 0 {
+                    self.all(&list[1..], local)?
+                } else {
+                    list[1..]
+                        .iter()
+                        .map(|part| self.template(part, depth - 1, local))
+                        .collect::<Result<_, _>>()?
+                };
+                [vec![head.clone()], parts].concat()
+            }
+            Some(head) if head.is_symbol(Symbol::QUASIQUOTE) => {
+                let parts = list[1..]
+                    .iter()
+                    .map(|part| self.template(part, depth + 1, local))
+                    .collect::<Result<Vec<_>, _>>()?;
+                [vec![head.clone()], parts].concat()
+            }
+            _ => list
+                .iter()
+                .map(|part| self.template(part, depth, local))
+                .collect::<Result<_, _>>()?,
+        };
+        Ok(Value::List(expanded.into()))
+    }
+}
+
+/// The local names with those a pattern binds added
+fn bind(local: &[Symbol], pattern: &Value) -> Vec<Symbol> {
+    let mut local = local.to_vec();
+    super::scope::names(pattern, &mut local);
+    local
+}
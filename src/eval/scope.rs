@@ -0,0 +1,352 @@
+use core::cell::RefCell;
+use std::{collections::HashSet, rc::Rc};
+
+use smallvec::SmallVec;
+
+use super::{expand::is_special, qualified};
+use crate::{
+    env::{self, Env},
+    span::ListTable,
+    types::{Symbol, Value},
+};
+
+/// What a lambda's body binds and uses, found once per body
+pub(super) struct Analysis {
+    /// The pattern the analysis is for. A body can be shared by lambdas with different patterns.
+    pattern: Value,
+    /// The names the pattern binds, in the order they are bound
+    pub(super) params: Vec<Symbol>,
+    /// The names the body uses without binding them, in the order they first appear
+    pub(super) free: Vec<Symbol>,
+    /// Whether the body can define names as it runs, with `def!`, `import`, `require` or `eval`, so the
+    /// names it uses can't be known ahead of time
+    pub(super) dynamic: bool,
+}
+
+thread_local! {
+    static ANALYSES: RefCell<ListTable<Rc<Analysis>>> = RefCell::new(ListTable::new());
+}
+
+/// Analyze the body of a lambda, reusing the analysis from the last time a lambda with the same body and
+/// pattern was made
+pub(super) fn analyze(pattern: &Value, body: &Value) -> Rc<Analysis> {
+    let Value::List(list) = body else {
+        return Rc::new(Analysis::new(pattern, body));
+    };
+    let cached = ANALYSES.with_borrow(|analyses| analyses.get(list).cloned());
+    if let Some(analysis) = cached.filter(|analysis| analysis.pattern == *pattern) {
+        return analysis;
+    }
+    let analysis = Rc::new(Analysis::new(pattern, body));
+    ANALYSES.with_borrow_mut(|analyses| analyses.insert(list, Rc::clone(&analysis)));
+    analysis
+}
+
+impl Analysis {
+    fn new(pattern: &Value, body: &Value) -> Self {
+        let mut params = Vec::new();
+        names(pattern, &mut params);
+        let mut walker = Walker {
+            scopes: vec![params.clone()],
+            free: Vec::new(),
+            dynamic: false,
+        };
+        walker.expr(body);
+        Self {
+            pattern: pattern.clone(),
+            params,
+            free: walker.free,
+            dynamic: walker.dynamic,
+        }
+    }
+}
+
+/// Add the names a pattern binds, in the order `destructure` binds them
+pub(super) fn names(pattern: &Value, out: &mut Vec<Symbol>) {
+    match pattern {
+        Value::Symbol(name) if !out.contains(name) => out.push(*name),
+        Value::List(patterns) => {
+            for pattern in patterns {
+                names(pattern, out);
+            }
+        }
+        _ => {}
+    }
+}
+
+struct Walker {
+    /// The names bound by the lambda and the scopes inside it around the form being walked
+    scopes: Vec<Vec<Symbol>>,
+    free: Vec<Symbol>,
+    dynamic: bool,
+}
+
+impl Walker {
+    fn reference(&mut self, name: Symbol) {
+        if matches!(name, Symbol::TRUE | Symbol::FALSE | Symbol::NIL) {
+            return;
+        }
+        if name == Symbol::EVAL {
+            self.dynamic = true;
+        }
+        if !self.scopes.iter().any(|scope| scope.contains(&name)) && !self.free.contains(&name) {
+            self.free.push(name);
+        }
+    }
+
+    /// Walk forms evaluated in a new scope that binds the names of `patterns`
+    fn scoped<'a>(&mut self, patterns: &[&Value], body: impl IntoIterator<Item = &'a Value>) {
+        let mut scope = Vec::new();
+        for pattern in patterns {
+            names(pattern, &mut scope);
+        }
+        self.scopes.push(scope);
+        for form in body {
+            self.expr(form);
+        }
+        self.scopes.pop();
+    }
+
+    /// Walk the bindings of a `let*` or `loop`. Each value is evaluated after the names before it are bound.
+    fn bindings(&mut self, assigns: &[Value], body: &Value) {
+        self.scopes.push(Vec::new());
+        for pair in assigns.chunks_exact(2) {
+            self.expr(&pair[1]);
+            let scope = self.scopes.last_mut().expect("a scope was pushed");
+            names(&pair[0], scope);
+        }
+        self.expr(body);
+        self.scopes.pop();
+    }
+
+    /// Walk the parts of a quasiquoted template that are evaluated, in a template nested `depth`
+    /// quasiquotes deep
+    fn template(&mut self, template: &Value, depth: usize) {
+        let Value::List(list) = template else {
+            return;
+        };
+        let head = list.first();
+        let unquote = head.is_some_and(|head| {
+            head.is_symbol(Symbol::UNQUOTE) || head.is_symbol(Symbol::SPLICE_UNQUOTE)
+        });
+        if unquote && depth == 0 {
+            self.all(list.iter().skip(1));
+        } else if unquote {
+            list.iter()
+                .skip(1)
+                .for_each(|part| self.template(part, depth - 1));
+        } else if head.is_some_and(|head| head.is_symbol(Symbol::QUASIQUOTE)) {
+            list.iter()
+                .skip(1)
+                .for_each(|part| self.template(part, depth + 1));
+        } else {
+            list.iter().for_each(|part| self.template(part, depth));
+        }
+    }
+
+    fn all<'a>(&mut self, forms: impl IntoIterator<Item = &'a Value>) {
+        for form in forms {
+            self.expr(form);
+        }
+    }
+
+    fn expr(&mut self, form: &Value) {
+        match form {
+            Value::Symbol(name) => self.reference(*name),
+            Value::Table(table) => table.values().for_each(|v| self.expr(v)),
+            Value::Set(set) => set.iter().for_each(|v| self.expr(v)),
+            Value::List(list) => {
+                let Some(Value::Symbol(head)) = list.first() else {
+                    return list.iter().for_each(|v| self.expr(v));
+                };
+                let list = list.values();
+                let args = &list[1..];
+                match *head {
+                    Symbol::QUOTE | Symbol::DOC | Symbol::HELP => {}
+                    Symbol::IF | Symbol::DO | Symbol::CONS | Symbol::LAZY_SEQ | Symbol::RECUR => {
+                        self.all(args);
+                    }
+                    Symbol::QUASIQUOTE => args.iter().for_each(|v| self.template(v, 0)),
+                    Symbol::ERR => args.iter().skip(1).for_each(|v| self.expr(v)),
+                    Symbol::DEF | Symbol::IMPORT | Symbol::REQUIRE => {
+                        self.dynamic = true;
+                        self.all(args);
+                    }
+                    Symbol::LAMBDA => {
+                        if let [pattern, body] = args {
+                            self.scoped(&[pattern], [body]);
+                        }
+                    }
+                    Symbol::LET | Symbol::LOOP => {
+                        if let [Value::List(assigns), body] = args {
+                            self.bindings(&assigns.values(), body);
+                        }
+                    }
+                    Symbol::DOSEQ => {
+                        if let Some((Value::List(binding), body)) = args.split_first() {
+                            if let [pattern, seq] = &binding.values()[..] {
+                                self.expr(seq);
+                                self.scoped(&[pattern], body);
+                            }
+                        }
+                    }
+                    Symbol::TRY => {
+                        let Some((body, catches)) = args.split_first() else {
+                            return;
+                        };
+                        self.expr(body);
+                        for catch in catches {
+                            let Value::List(catch) = catch else {
+                                continue;
+                            };
+                            // (catch* name body) or (catch* ErrorName name body)
+                            if let [_, name, body] | [_, _, name, body] = &catch.values()[..] {
+                                self.scoped(&[&Value::Symbol(Symbol::TRACE), name], [body]);
+                            }
+                        }
+                    }
+                    _ => list.iter().for_each(|v| self.expr(v)),
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// What a form evaluated in a scope could define in that scope, found once per form
+struct Definitions {
+    /// Whether the form uses `def!`, `import`, `require` or `eval` outside of a lambda
+    direct: bool,
+    /// The names the form calls outside of a lambda, any of which could be a macro that expands to a
+    /// definition. A qualified name is kept as the name in its module.
+    heads: Vec<Symbol>,
+}
+
+thread_local! {
+    static DEFINITIONS: RefCell<ListTable<Rc<Definitions>>> = RefCell::new(ListTable::new());
+    /// Every name a macro has been bound to
+    static MACRO_NAMES: RefCell<HashSet<Symbol>> = RefCell::new(HashSet::new());
+}
+
+impl Definitions {
+    fn walk(&mut self, form: &Value) {
+        match form {
+            Value::Symbol(Symbol::EVAL) => self.direct = true,
+            Value::Table(table) => table.values().for_each(|v| self.walk(v)),
+            Value::Set(set) => set.iter().for_each(|v| self.walk(v)),
+            Value::List(list) => match list.first() {
+                // a lambda defines names in the scope of its call
+                Some(Value::Symbol(
+                    Symbol::QUOTE | Symbol::DOC | Symbol::HELP | Symbol::LAMBDA,
+                )) => {}
+                Some(Value::Symbol(Symbol::DEF | Symbol::IMPORT | Symbol::REQUIRE)) => {
+                    self.direct = true;
+                }
+                Some(Value::Symbol(head)) => {
+                    if !is_special(*head) {
+                        let text = head.name();
+                        let name = match text.split_once('/') {
+                            Some((namespace, name))
+                                if !namespace.is_empty() && !name.is_empty() =>
+                            {
+                                Symbol::intern(name)
+                            }
+                            _ => *head,
+                        };
+                        if !self.heads.contains(&name) {
+                            self.heads.push(name);
+                        }
+                    }
+                    list.iter().skip(1).for_each(|v| self.walk(v));
+                }
+                _ => list.iter().for_each(|v| self.walk(v)),
+            },
+            _ => {}
+        }
+    }
+}
+
+/// Note that a name was bound to a value, so scopes that call a macro by that name are known to be able
+/// to define names
+pub fn bound(name: Symbol, value: &Value) {
+    if is_macro(value) {
+        MACRO_NAMES.with_borrow_mut(|names| names.insert(name));
+    }
+}
+
+/// Whether evaluating `body` in a scope could define a name in that scope after the names it was made
+/// for are bound. Lambdas made in such a scope keep it rather than copying values out of it.
+pub fn can_define(body: &Value) -> bool {
+    let Value::List(list) = body else {
+        return false;
+    };
+    let cached = DEFINITIONS.with_borrow(|definitions| definitions.get(list).cloned());
+    let definitions = cached.unwrap_or_else(|| {
+        let mut definitions = Definitions {
+            direct: false,
+            heads: Vec::new(),
+        };
+        definitions.walk(body);
+        let definitions = Rc::new(definitions);
+        DEFINITIONS.with_borrow_mut(|cache| cache.insert(list, Rc::clone(&definitions)));
+        definitions
+    });
+    definitions.direct
+        || MACRO_NAMES
+            .with_borrow(|names| definitions.heads.iter().any(|head| names.contains(head)))
+}
+
+const fn is_macro(value: &Value) -> bool {
+    matches!(
+        value,
+        Value::Function { is_macro: true, .. } | Value::Lambda { is_macro: true, .. }
+    )
+}
+
+/// Make the environment a lambda made in `env` captures.
+///
+/// The values of the local variables the lambda uses are copied out of the fixed local scopes around it,
+/// so it doesn't keep those scopes alive. Names it doesn't find there are looked up by name, as they always
+/// are, in the innermost scope that can still change. A lambda that can define names, uses a name that
+/// isn't bound yet, or uses a macro, whose expansion could refer to anything, keeps `env` as it is.
+pub(super) fn capture(analysis: &Analysis, env: &Env) -> Env {
+    if analysis.dynamic || !env.borrow().is_fixed() {
+        return env.clone();
+    }
+    let mut outer = env.clone();
+    while outer.borrow().is_fixed() {
+        let parent = outer.borrow().parent().cloned();
+        let Some(parent) = parent else {
+            return env.clone();
+        };
+        outer = parent;
+    }
+    let mut slots = SmallVec::new();
+    for &name in &analysis.free {
+        let mut scope = env.clone();
+        let found = loop {
+            if Rc::ptr_eq(&scope, &outer) {
+                break None;
+            }
+            let parent = {
+                let data = scope.borrow();
+                if let Some(value) = data.lookup(name) {
+                    break Some(value.clone());
+                }
+                data.parent().cloned().expect("a fixed scope has a parent")
+            };
+            scope = parent;
+        };
+        match found {
+            Some(value) if !is_macro(&value) => slots.push((name, value)),
+            Some(_) => return env.clone(),
+            None => {
+                let value = outer.borrow().get(name).or_else(|| qualified(name, &outer));
+                if value.as_ref().is_none_or(is_macro) {
+                    return env.clone();
+                }
+            }
+        }
+    }
+    env::new_sealed(outer, slots)
+}
@@ -0,0 +1,599 @@
+use core::{cell::RefCell, mem};
+use std::rc::Rc;
+
+use im_rc::{HashMap, HashSet};
+
+use super::{
+    builtins, catch,
+    compile::{compile, compile_lambda, Chunk, Op},
+    define, destructure, expand, finish_cons, qualified, scope, walk, DepthGuard, RED_ZONE,
+    STACK_SEGMENT,
+};
+use crate::{
+    env::{new_env, Env},
+    span::{self, ListTable, Span},
+    trace::{self, FrameGuard},
+    types::{
+        lazy::{self, LazySeq, Thunk},
+        Symbol, Value,
+    },
+};
+
+/// How many compiled forms are kept before the cache is emptied
+const CACHED_CHUNKS: usize = 4096;
+
+/// Evaluated forms, and lambda bodies with the patterns they were compiled for, by the list they were
+/// compiled from
+struct ChunkCache {
+    chunks: ListTable<Rc<Chunk>>,
+    lambdas: ListTable<(Value, Rc<Chunk>)>,
+    len: usize,
+}
+
+impl ChunkCache {
+    /// Make room for another chunk
+    fn reserve(&mut self) {
+        if self.len >= CACHED_CHUNKS {
+            self.chunks = ListTable::new();
+            self.lambdas = ListTable::new();
+            self.len = 0;
+        }
+        self.len += 1;
+    }
+}
+
+thread_local! {
+    static CHUNKS: RefCell<ChunkCache> = RefCell::new(ChunkCache {
+        chunks: ListTable::new(),
+        lambdas: ListTable::new(),
+        len: 0,
+    });
+}
+
+/// Get the compiled form of a form, compiling it if it hasn't been yet.
+///
+/// A chunk holds on to the form it was compiled from, so entries never die on their own; the cache is
+/// emptied whenever it fills up instead.
+fn chunk_for(form: &Value) -> Rc<Chunk> {
+    let Value::List(list) = form else {
+        return Rc::new(compile(form));
+    };
+    if let Some(chunk) = CHUNKS.with_borrow(|cache| cache.chunks.get(list).cloned()) {
+        return chunk;
+    }
+    let chunk = Rc::new(compile(form));
+    CHUNKS.with_borrow_mut(|cache| {
+        cache.reserve();
+        cache.chunks.insert(list, Rc::clone(&chunk));
+    });
+    chunk
+}
+
+/// Get the compiled body of a lambda, whose locals are addressed by where its pattern and the scopes in
+/// it bind them
+fn lambda_chunk(params: &Value, body: &Value) -> Rc<Chunk> {
+    let Value::List(list) = body else {
+        return Rc::new(compile(body));
+    };
+    let cached = CHUNKS.with_borrow(|cache| {
+        cache
+            .lambdas
+            .get(list)
+            .filter(|(pattern, _)| pattern == params)
+            .map(|(_, chunk)| Rc::clone(chunk))
+    });
+    if let Some(chunk) = cached {
+        return chunk;
+    }
+    let chunk = Rc::new(compile_lambda(&scope::analyze(params, body), body));
+    CHUNKS.with_borrow_mut(|cache| {
+        cache.reserve();
+        cache
+            .lambdas
+            .insert(list, (params.clone(), Rc::clone(&chunk)));
+    });
+    chunk
+}
+
+/// Look up a local variable by where it was bound, falling back to looking it up by name if a `def!` has
+/// been made in a scope in the way, or the lambda didn't capture the scopes it was compiled for
+fn local(env: &Env, name: Symbol, depth: usize, index: usize) -> Option<Value> {
+    let mut scope = env.clone();
+    for _ in 0..depth {
+        let parent = {
+            let data = scope.borrow();
+            if data.is_defined() {
+                return lookup(env, name);
+            }
+            data.parent().cloned()
+        };
+        match parent {
+            Some(parent) => scope = parent,
+            None => return lookup(env, name),
+        }
+    }
+    let data = scope.borrow();
+    match data.slot(index) {
+        Some((slot, value)) if slot == name => Some(value.clone()),
+        _ => {
+            drop(data);
+            lookup(env, name)
+        }
+    }
+}
+
+fn lookup(env: &Env, name: Symbol) -> Option<Value> {
+    let value = env.borrow().get(name);
+    value.or_else(|| qualified(name, env))
+}
+
+/// Evaluate a form by compiling it and running the bytecode
+pub(super) fn eval(syn: &Value, env: Env) -> Result<Value, Value> {
+    let frame = Frame::new(chunk_for(syn), env, 0)?;
+    run(frame)
+}
+
+/// Evaluate the body of a lambda whose arguments are bound in `env`
+pub(super) fn eval_lambda(
+    params: Value,
+    body: &Value,
+    captures: Env,
+    env: Env,
+) -> Result<Value, Value> {
+    let chunk = lambda_chunk(&params, body);
+    let mut frame = Frame::new(Rc::clone(&chunk), env, 0)?;
+    frame.recur = Some(Target {
+        pattern: params,
+        arity: None,
+        env: captures,
+        body: body.clone(),
+        chunk,
+        ip: 0,
+    });
+    run(frame)
+}
+
+fn run(frame: Frame) -> Result<Value, Value> {
+    // builtins that call back into the evaluator still recurse
+    stacker::maybe_grow(RED_ZONE, STACK_SEGMENT, || {
+        Machine {
+            frames: vec![frame],
+            stack: Vec::new(),
+        }
+        .run()
+    })
+}
+
+/// Where `recur` goes back to
+struct Target {
+    pattern: Value,
+    /// How many values a `loop` takes, or `None` for a lambda, whose pattern decides
+    arity: Option<usize>,
+    /// The environment the new values are bound in a child of
+    env: Env,
+    /// The form evaluated once they are bound
+    body: Value,
+    chunk: Rc<Chunk>,
+    ip: usize,
+}
+
+/// The evaluation of a chunk. A frame is made wherever the tree-walking engine would start a new
+/// evaluation, so calls in tail position replace the frame rather than adding one.
+struct Frame {
+    chunk: Rc<Chunk>,
+    ip: usize,
+    env: Env,
+    /// Where the frame's values start on the stack
+    base: usize,
+    /// Environments to go back to when a `let*` or `doseq` that isn't in tail position ends
+    scopes: Vec<Env>,
+    recur: Option<Target>,
+    /// Values to add to the front of the result, from `cons` in tail position
+    cons: Vec<Value>,
+    /// Where the call or macro that the frame started evaluating was made
+    location: Option<Span>,
+    /// The catch blocks of the `try*` whose body this frame evaluates
+    catches: Option<Value>,
+    trace: Option<FrameGuard>,
+    _depth: DepthGuard,
+}
+
+impl Frame {
+    fn new(chunk: Rc<Chunk>, env: Env, base: usize) -> Result<Self, Value> {
+        Ok(Self {
+            chunk,
+            ip: 0,
+            env,
+            base,
+            scopes: Vec::new(),
+            recur: None,
+            cons: Vec::new(),
+            location: None,
+            catches: None,
+            trace: None,
+            _depth: DepthGuard::enter()?,
+        })
+    }
+
+    /// Where the form of the instruction that last ran came from
+    fn location(&self) -> Option<Span> {
+        self.ip
+            .checked_sub(1)
+            .and_then(|i| self.chunk.locations[i])
+            .map(|i| self.chunk.spans[i].clone())
+            .or_else(|| self.location.clone())
+    }
+
+    /// Continue by evaluating another chunk in place of this one
+    fn replace(&mut self, chunk: Rc<Chunk>, ip: usize, stack: &mut Vec<Value>) {
+        self.chunk = chunk;
+        self.ip = ip;
+        self.scopes.clear();
+        stack.truncate(self.base);
+    }
+}
+
+/// Errors are lists, and their locations and traces are kept by list, so a constant error is copied
+/// before it is raised
+fn fresh(err: &Value) -> Value {
+    match err {
+        Value::List(l) => Value::List(l.iter().cloned().collect()),
+        other => other.clone(),
+    }
+}
+
+const fn is_macro(value: &Value) -> bool {
+    matches!(
+        value,
+        Value::Function { is_macro: true, .. } | Value::Lambda { is_macro: true, .. }
+    )
+}
+
+struct Machine {
+    frames: Vec<Frame>,
+    stack: Vec<Value>,
+}
+
+impl Machine {
+    fn run(&mut self) -> Result<Value, Value> {
+        loop {
+            match self.step() {
+                Ok(Some(value)) => return Ok(value),
+                Ok(None) => {}
+                Err(err) => self.unwind(err)?,
+            }
+        }
+    }
+
+    fn pop(&mut self) -> Value {
+        self.stack.pop().expect("the stack has a value")
+    }
+
+    fn pop_n(&mut self, n: usize) -> Vec<Value> {
+        self.stack.split_off(self.stack.len() - n)
+    }
+
+    /// End frames until one handles the error, raising it if none do. Each frame that ends locates the error
+    /// and captures its trace, as an evaluation in the tree-walking engine does when it ends.
+    fn unwind(&mut self, mut err: Value) -> Result<(), Value> {
+        // an error from a form that isn't in tail position is raised with the frame's call still on the stack
+        let frame = self.frames.last().expect("a frame is running");
+        if frame
+            .ip
+            .checked_sub(1)
+            .is_some_and(|i| frame.chunk.inner[i])
+        {
+            if let Some(location) = frame.location() {
+                span::locate_error(&err, location);
+            }
+            trace::capture(&err);
+        }
+        loop {
+            let mut frame = self.frames.pop().expect("a frame is running");
+            let location = frame.location();
+            let catches = frame.catches.take();
+            self.stack.truncate(frame.base);
+            if let Some(location) = location {
+                span::locate_error(&err, location);
+            }
+            // the trace is captured while the frame's call is still on the stack
+            trace::capture(&err);
+            drop(frame);
+            let (Some(Value::List(catches)), Some(parent)) = (catches, self.frames.last_mut())
+            else {
+                if self.frames.is_empty() {
+                    return Err(err);
+                }
+                continue;
+            };
+            // the catch block is evaluated in the frame the try* is in
+            match catch(err, &catches.values(), parent.env.clone()) {
+                Ok((env, body)) => {
+                    parent.location = parent.location();
+                    parent.env = env;
+                    parent.replace(chunk_for(&body), 0, &mut self.stack);
+                    return Ok(());
+                }
+                Err(e) => err = e,
+            }
+        }
+    }
+
+    /// Run one instruction, returning the result once the first frame returns
+    #[allow(clippy::too_many_lines)]
+    fn step(&mut self) -> Result<Option<Value>, Value> {
+        let frame = self.frames.last_mut().expect("a frame is running");
+        let chunk = Rc::clone(&frame.chunk);
+        let constants = &chunk.constants;
+        let op = chunk.code[frame.ip];
+        frame.ip += 1;
+        match op {
+            Op::Const(i) => self.stack.push(constants[i].clone()),
+            Op::Load(id) => match lookup(&frame.env, id) {
+                Some(value) => self.stack.push(value),
+                None => {
+                    return Err(Value::error(
+                        "UnresolvedIdentifier",
+                        vec![Value::Symbol(id)],
+                    ))
+                }
+            },
+            Op::Local { name, depth, index } => match local(&frame.env, name, depth, index) {
+                Some(value) => self.stack.push(value),
+                None => {
+                    return Err(Value::error(
+                        "UnresolvedIdentifier",
+                        vec![Value::Symbol(name)],
+                    ))
+                }
+            },
+            Op::Pop => {
+                self.pop();
+            }
+            Op::Jump(target) => frame.ip = target,
+            Op::JumpIfFalse(target) => {
+                if !self.stack.pop().is_some_and(|v| v.is_truthy()) {
+                    frame.ip = target;
+                }
+            }
+            Op::Fail(err) => return Err(fresh(&constants[err])),
+            Op::Lambda { params, body } => {
+                let lambda = super::lambda(&constants[params], &constants[body], &frame.env);
+                self.stack.push(lambda);
+            }
+            Op::NewScope => frame.env = new_env(frame.env.clone()),
+            Op::EnterScope => {
+                let inner = new_env(frame.env.clone());
+                frame.scopes.push(mem::replace(&mut frame.env, inner));
+            }
+            Op::ExitScope => frame.env = frame.scopes.pop().expect("a scope was entered"),
+            Op::Seal(body) => frame.env.borrow_mut().seal(&constants[body]),
+            Op::Bind { pattern, err } => {
+                let value = self.pop();
+                let frame = self.frames.last_mut().expect("a frame is running");
+                if destructure(&constants[pattern], value, &frame.env).is_none() {
+                    return Err(fresh(&constants[err]));
+                }
+            }
+            Op::Define(name) => {
+                let value = self.pop();
+                let frame = self.frames.last_mut().expect("a frame is running");
+                define(name, value, &frame.env);
+                self.stack.push(Value::nil());
+            }
+            Op::LazySeq(i) => self.stack.push(LazySeq::from_thunk(Thunk::Body {
+                body: constants[i].clone(),
+                env: frame.env.clone(),
+            })),
+            Op::Quasiquote(i) => {
+                let value = constants[i].quasiquote(&frame.env)?;
+                self.stack.push(value);
+            }
+            Op::Raise(n) => return Err(Value::List(self.pop_n(n).into())),
+            Op::Table(keys) => {
+                let Value::List(keys) = &constants[keys] else {
+                    unreachable!()
+                };
+                let values = self.pop_n(keys.len());
+                let table: HashMap<_, _> = keys.iter().cloned().zip(values).collect();
+                self.stack.push(Value::Table(table));
+            }
+            Op::Set(n) => {
+                let set: HashSet<_> = self.pop_n(n).into_iter().collect();
+                self.stack.push(Value::Set(set));
+            }
+            Op::Cons => {
+                let rest = self.pop();
+                let first = self.pop();
+                self.stack.push(finish_cons(vec![first], rest)?);
+            }
+            Op::TailCons => {
+                let value = self.pop();
+                self.frames
+                    .last_mut()
+                    .expect("a frame is running")
+                    .cons
+                    .push(value);
+            }
+            Op::Step(end) => {
+                let seq = self.pop();
+                if let Some((first, rest)) = lazy::step(&seq)? {
+                    self.stack.push(rest);
+                    self.stack.push(first);
+                } else {
+                    self.frames.last_mut().expect("a frame is running").ip = end;
+                }
+            }
+            Op::Expand { form, after, tail } => {
+                if !self.stack.last().is_some_and(is_macro) {
+                    return Ok(None);
+                }
+                let Value::List(arr) = &constants[form] else {
+                    unreachable!()
+                };
+                let env = frame.env.clone();
+                let mac = self.pop();
+                let expansion = expand::apply(mac, &arr.values()[1..], &env)?;
+                let frame = self.frames.last_mut().expect("a frame is running");
+                let location = frame.location();
+                // expansions are made fresh each time, so they aren't worth caching
+                let expansion = Rc::new(compile(&expansion));
+                if tail {
+                    frame.location = location;
+                    frame.replace(expansion, 0, &mut self.stack);
+                } else {
+                    frame.ip = after;
+                    let mut nested = Frame::new(expansion, env, self.stack.len())?;
+                    nested.location = location;
+                    self.frames.push(nested);
+                }
+            }
+            Op::Call { argc, form, tail } => {
+                let mut vals = self.pop_n(argc);
+                let mut func = self.pop();
+                // call the function `apply` is given here, so that it is a tail call too
+                while builtins::is_apply(&func) {
+                    let [f, Value::List(l)] = &vals[..] else {
+                        break;
+                    };
+                    (func, vals) = (f.clone(), l.to_vec());
+                }
+                let frame = self.frames.last_mut().expect("a frame is running");
+                match func {
+                    Value::Function {
+                        fn_ref: func,
+                        is_macro: false,
+                    } => {
+                        let value = func(vals, frame.env.clone())?;
+                        self.stack.push(value);
+                    }
+                    Value::Lambda {
+                        args,
+                        body,
+                        captures,
+                        is_macro: false,
+                        name,
+                    } => {
+                        let location = frame.location();
+                        let call = trace::Frame {
+                            name,
+                            form: constants[form].clone(),
+                            location: location.clone(),
+                        };
+                        let chunk = lambda_chunk(&args, &body);
+                        let target = Target {
+                            pattern: (*args).clone(),
+                            arity: None,
+                            env: captures.clone(),
+                            body: (*body).clone(),
+                            chunk: Rc::clone(&chunk),
+                            ip: 0,
+                        };
+                        let env = new_env(captures);
+                        if tail {
+                            match &mut frame.trace {
+                                Some(trace) => trace.replace(call),
+                                None => frame.trace = Some(FrameGuard::push(call)),
+                            }
+                            frame.env = env.clone();
+                            frame.location = location;
+                            frame.recur = Some(target);
+                            frame.replace(chunk, 0, &mut self.stack);
+                        } else {
+                            let mut nested = Frame::new(chunk, env.clone(), self.stack.len())?;
+                            nested.trace = Some(FrameGuard::push(call));
+                            nested.location = location;
+                            nested.recur = Some(target);
+                            self.frames.push(nested);
+                        }
+                        // the arguments are bound in the lambda's frame
+                        if destructure(&args, Value::List(vals.into()), &env).is_none() {
+                            return Err(Value::error("PatternMismatch", vec![*args]));
+                        }
+                        env.borrow_mut().seal(&body);
+                    }
+                    key @ Value::Keyword(_) => {
+                        self.stack.push(builtins::keyword_get(&key, vals)?);
+                    }
+                    Value::Atom(at) if vals.is_empty() => {
+                        let value = at.read().unwrap().clone();
+                        self.stack.push(value);
+                    }
+                    other => return Err(Value::error("NotAFunction", vec![other])),
+                }
+            }
+            Op::Loop {
+                patterns,
+                arity,
+                form,
+                body,
+            } => {
+                frame.recur = Some(Target {
+                    pattern: constants[patterns].clone(),
+                    arity: Some(arity),
+                    env: frame.env.clone(),
+                    body: constants[form].clone(),
+                    chunk: Rc::clone(&chunk),
+                    ip: body,
+                });
+                frame.env = new_env(frame.env.clone());
+            }
+            Op::CheckRecur(err) => {
+                if frame.recur.is_none() {
+                    return Err(fresh(&constants[err]));
+                }
+            }
+            Op::Recur(argc) => {
+                let vals = self.stack.split_off(self.stack.len() - argc);
+                let target = frame.recur.as_ref().expect("recur was checked");
+                if target.arity.is_some_and(|arity| arity != vals.len()) {
+                    return Err(Value::error("InvalidArgs@recur", vals));
+                }
+                frame.env = new_env(target.env.clone());
+                if destructure(&target.pattern, Value::List(vals.into()), &frame.env).is_none() {
+                    return Err(Value::error(
+                        "PatternMismatch",
+                        vec![target.pattern.clone()],
+                    ));
+                }
+                frame.env.borrow_mut().seal(&target.body);
+                let (chunk, ip) = (Rc::clone(&target.chunk), target.ip);
+                frame.replace(chunk, ip, &mut self.stack);
+            }
+            Op::Try { body, catches } => {
+                let mut nested = Frame::new(
+                    Rc::clone(&chunk.chunks[body]),
+                    frame.env.clone(),
+                    self.stack.len(),
+                )?;
+                nested.catches = Some(constants[catches].clone());
+                self.frames.push(nested);
+            }
+            Op::Nested(i) => {
+                let nested = Frame::new(
+                    Rc::clone(&chunk.chunks[i]),
+                    frame.env.clone(),
+                    self.stack.len(),
+                )?;
+                self.frames.push(nested);
+            }
+            Op::Walk(i) => {
+                let value = walk(constants[i].clone(), frame.env.clone(), None)?;
+                self.stack.push(value);
+            }
+            Op::Return => {
+                let value = self.pop();
+                let frame = self.frames.last_mut().expect("a frame is running");
+                let value = finish_cons(mem::take(&mut frame.cons), value)?;
+                let frame = self.frames.pop().expect("a frame is running");
+                self.stack.truncate(frame.base);
+                drop(frame);
+                if self.frames.is_empty() {
+                    return Ok(Some(value));
+                }
+                self.stack.push(value);
+            }
+        }
+        Ok(None)
+    }
+}
@@ -0,0 +1,428 @@
+//! A cycle collector for the reference counted parts of values.
+//!
+//! Values are reference counted, so a lambda stored in the environment it captures, or an atom that holds
+//! itself, is never freed on its own. Environments captured by lambdas or lazy sequences, atoms, and lazy
+//! sequences that evaluate code are tracked as they are made. Every so often the collector looks at what
+//! can be reached from them and counts the references it finds. Anything with more references than were
+//! found is held from outside, by a variable, the evaluator or Rust code, and is kept along with everything
+//! it reaches. What is left can only be reached through cycles, and is freed by emptying it.
+//!
+//! Tables and sets share structure that can't be counted, so the tables and sets an environment or atom
+//! holds are copied when they reference something the collector counts. Nothing else shares the copies,
+//! so what they reference is counted as referenced from the environment or atom.
+//!
+//! The collector errs on the side of keeping things. It doesn't look inside builtins, lambda bodies, lists
+//! built by `cons` and `conj`, or tables and sets held in a list or lazy sequence, so whatever is only
+//! referenced from them is kept.
+use core::{
+    cell::{Cell, RefCell},
+    mem,
+};
+use std::{
+    collections::{HashMap, HashSet},
+    rc::{Rc, Weak},
+    sync::RwLock,
+};
+
+use crate::{
+    env::{Env, EnvData},
+    types::{lazy::LazySeq, Value},
+};
+
+/// How many objects are tracked before the collector first runs. It runs again once twice as many are
+/// tracked as survived the last collection.
+const MIN_THRESHOLD: usize = 1024;
+
+/// Counts of the objects alive and collected on this thread
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Stats {
+    /// Environments, including the scopes of calls in progress
+    pub envs: usize,
+    pub atoms: usize,
+    pub lazy_seqs: usize,
+    /// Objects the collector checks for cycles
+    pub tracked: usize,
+    /// How many times the collector has run
+    pub collections: usize,
+    /// How many objects the collector has freed
+    pub collected: usize,
+}
+
+pub(crate) enum Kind {
+    Env,
+    Lazy,
+}
+
+struct Heap {
+    tracked: Vec<Tracked>,
+    threshold: usize,
+    collections: usize,
+    collected: usize,
+}
+
+thread_local! {
+    static HEAP: RefCell<Heap> = const {
+        RefCell::new(Heap {
+            tracked: Vec::new(),
+            threshold: MIN_THRESHOLD,
+            collections: 0,
+            collected: 0,
+        })
+    };
+    static LIVE_ENVS: Cell<usize> = const { Cell::new(0) };
+    static LIVE_LAZY_SEQS: Cell<usize> = const { Cell::new(0) };
+}
+
+const fn live(kind: &Kind) -> &'static std::thread::LocalKey<Cell<usize>> {
+    match kind {
+        Kind::Env => &LIVE_ENVS,
+        Kind::Lazy => &LIVE_LAZY_SEQS,
+    }
+}
+
+/// Count an object as alive
+pub(crate) fn allocated(kind: &Kind) {
+    let _ = live(kind).try_with(|n| n.set(n.get() + 1));
+}
+
+/// Count an object as dropped. Objects can be dropped while the thread is exiting, after the count is gone.
+pub(crate) fn freed(kind: &Kind) {
+    let _ = live(kind).try_with(|n| n.set(n.get().saturating_sub(1)));
+}
+
+/// An object the collector checks, which doesn't keep it alive
+enum Tracked {
+    Env(Weak<RefCell<EnvData>>),
+    Atom(Weak<RwLock<Value>>),
+    Lazy(Weak<LazySeq>),
+}
+
+impl Tracked {
+    fn upgrade(&self) -> Option<Node> {
+        match self {
+            Self::Env(env) => env.upgrade().map(Node::Env),
+            Self::Atom(atom) => atom.upgrade().map(Node::Atom),
+            Self::Lazy(seq) => seq.upgrade().map(Node::Lazy),
+        }
+    }
+}
+
+/// Check an environment a lambda or lazy sequence captures for cycles
+pub(crate) fn track_env(env: &Env) {
+    track(Tracked::Env(Rc::downgrade(env)));
+}
+
+/// Check an atom for cycles
+pub(crate) fn track_atom(atom: &Rc<RwLock<Value>>) {
+    track(Tracked::Atom(Rc::downgrade(atom)));
+}
+
+/// Check a lazy sequence for cycles
+pub(crate) fn track_lazy(seq: &Rc<LazySeq>) {
+    track(Tracked::Lazy(Rc::downgrade(seq)));
+}
+
+fn track(tracked: Tracked) {
+    let full = HEAP.with_borrow_mut(|heap| {
+        heap.tracked.push(tracked);
+        heap.tracked.len() >= heap.threshold
+    });
+    if full {
+        collect();
+    }
+}
+
+/// A reference counted object that can be part of a cycle
+enum Node {
+    Env(Env),
+    Atom(Rc<RwLock<Value>>),
+    Lazy(Rc<LazySeq>),
+    List(Rc<[Value]>),
+}
+
+impl Node {
+    fn key(&self) -> *const () {
+        match self {
+            Self::Env(env) => Rc::as_ptr(env).cast(),
+            Self::Atom(atom) => Rc::as_ptr(atom).cast(),
+            Self::Lazy(seq) => Rc::as_ptr(seq).cast(),
+            Self::List(values) => Rc::as_ptr(values).cast(),
+        }
+    }
+
+    fn strong_count(&self) -> usize {
+        match self {
+            Self::Env(env) => Rc::strong_count(env),
+            Self::Atom(atom) => Rc::strong_count(atom),
+            Self::Lazy(seq) => Rc::strong_count(seq),
+            Self::List(values) => Rc::strong_count(values),
+        }
+    }
+
+    fn tracked(&self) -> Option<Tracked> {
+        match self {
+            Self::Env(env) => Some(Tracked::Env(Rc::downgrade(env))),
+            Self::Atom(atom) => Some(Tracked::Atom(Rc::downgrade(atom))),
+            Self::Lazy(seq) => Some(Tracked::Lazy(Rc::downgrade(seq))),
+            Self::List(_) => None,
+        }
+    }
+
+    /// Add the nodes this one references. Returns false if it is in use, so what it references can't be known.
+    fn edges(&self, out: &mut Vec<Self>) -> bool {
+        match self {
+            Self::Env(env) => {
+                let Ok(mut data) = env.try_borrow_mut() else {
+                    return false;
+                };
+                data.replace_values(unshare);
+                if let Some(parent) = data.parent() {
+                    out.push(Self::Env(parent.clone()));
+                }
+                for (_, value) in data.bindings() {
+                    held_edges(value, out);
+                }
+            }
+            Self::Atom(atom) => {
+                let Ok(mut value) = atom.try_write() else {
+                    return false;
+                };
+                if let Some(copy) = unshare(&value) {
+                    *value = copy;
+                }
+                held_edges(&value, out);
+            }
+            Self::Lazy(seq) => {
+                let (mut values, mut envs) = (Vec::new(), Vec::new());
+                if !seq.held(&mut values, &mut envs) {
+                    return false;
+                }
+                out.extend(envs.into_iter().map(Self::Env));
+                for value in &values {
+                    edges(value, out);
+                }
+            }
+            Self::List(values) => {
+                for value in values.iter() {
+                    edges(value, out);
+                }
+            }
+        }
+        true
+    }
+
+    /// Empty the object, breaking the cycles it is part of. What it held is moved into `garbage`, to be
+    /// dropped once every cycle is broken. Returns whether anything was freed.
+    fn clear(&self, garbage: &mut Garbage) -> bool {
+        match self {
+            Self::Env(env) => {
+                let Ok(mut data) = env.try_borrow_mut() else {
+                    return false;
+                };
+                let (parent, values) = data.clear();
+                garbage.envs.extend(parent);
+                garbage.values.extend(values);
+            }
+            Self::Atom(atom) => {
+                let Ok(mut value) = atom.try_write() else {
+                    return false;
+                };
+                garbage.values.push(mem::take(&mut *value));
+            }
+            Self::Lazy(seq) => seq.clear(&mut garbage.values, &mut garbage.envs),
+            Self::List(_) => return false,
+        }
+        true
+    }
+}
+
+/// Add the nodes a value references directly
+fn edges(value: &Value, out: &mut Vec<Node>) {
+    match value {
+        Value::Lambda { captures, .. } => out.push(Node::Env(captures.clone())),
+        Value::Atom(atom) => out.push(Node::Atom(atom.clone())),
+        Value::Lazy(seq) => out.push(Node::Lazy(seq.clone())),
+        Value::List(list) => {
+            if let Some(values) = list.flat() {
+                out.push(Node::List(values.clone()));
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Add the nodes a value references, including those in its tables and sets. Only for values whose tables
+/// and sets nothing else shares, like those [`unshare`] makes.
+fn held_edges(value: &Value, out: &mut Vec<Node>) {
+    match value {
+        Value::Table(table) => {
+            for (k, v) in table {
+                held_edges(k, out);
+                held_edges(v, out);
+            }
+        }
+        Value::Set(set) => set.iter().for_each(|v| held_edges(v, out)),
+        _ => edges(value, out),
+    }
+}
+
+/// Whether a value references a node, directly or through its tables and sets
+fn references(value: &Value) -> bool {
+    match value {
+        Value::Lambda { .. } | Value::Atom(_) | Value::Lazy(_) => true,
+        Value::List(list) => list.flat().is_some(),
+        Value::Table(table) => table.iter().any(|(k, v)| references(k) || references(v)),
+        Value::Set(set) => set.iter().any(references),
+        _ => false,
+    }
+}
+
+/// Copy a table or set that references a node, along with the tables and sets inside it, so that nothing
+/// shares their structure. Returns `None` if the value doesn't need copying.
+fn unshare(value: &Value) -> Option<Value> {
+    let copy = |value: &Value| unshare(value).unwrap_or_else(|| value.clone());
+    match value {
+        Value::Table(table) if references(value) => Some(Value::Table(
+            table.iter().map(|(k, v)| (copy(k), copy(v))).collect(),
+        )),
+        Value::Set(set) if references(value) => Some(Value::Set(set.iter().map(copy).collect())),
+        _ => None,
+    }
+}
+
+#[derive(Default)]
+struct Garbage {
+    values: Vec<Value>,
+    envs: Vec<Env>,
+}
+
+/// The objects reachable from the tracked ones, and the references between them
+#[derive(Default)]
+struct Graph {
+    nodes: Vec<Node>,
+    index: HashMap<*const (), usize>,
+    edges: Vec<Vec<usize>>,
+    /// How many references to each node were found in other nodes
+    found: Vec<usize>,
+    /// Whether a node is held from outside the graph, or couldn't be looked into
+    rooted: Vec<bool>,
+}
+
+impl Graph {
+    fn add(&mut self, node: Node) -> usize {
+        let key = node.key();
+        if let Some(&i) = self.index.get(&key) {
+            return i;
+        }
+        self.index.insert(key, self.nodes.len());
+        self.nodes.push(node);
+        self.edges.push(Vec::new());
+        self.found.push(0);
+        self.rooted.push(false);
+        self.nodes.len() - 1
+    }
+
+    /// Find every node reachable from the ones already added
+    fn explore(&mut self) {
+        let mut i = 0;
+        while i < self.nodes.len() {
+            let mut out = Vec::new();
+            if !self.nodes[i].edges(&mut out) {
+                self.rooted[i] = true;
+            }
+            for node in out {
+                let j = self.add(node);
+                self.edges[i].push(j);
+                self.found[j] += 1;
+            }
+            i += 1;
+        }
+    }
+
+    /// Find the nodes reachable from outside the graph
+    fn mark(&self) -> Vec<bool> {
+        let mut live = vec![false; self.nodes.len()];
+        let mut stack = Vec::new();
+        for (i, node) in self.nodes.iter().enumerate() {
+            // the graph holds one reference to each node itself
+            if self.rooted[i] || node.strong_count() - 1 > self.found[i] {
+                stack.push(i);
+            }
+        }
+        while let Some(i) = stack.pop() {
+            if !mem::replace(&mut live[i], true) {
+                stack.extend(self.edges[i].iter().copied().filter(|&j| !live[j]));
+            }
+        }
+        live
+    }
+}
+
+/// Free the objects that can only be reached through cycles, returning how many were freed.
+///
+/// This runs on its own as objects are made, so it rarely needs to be called.
+pub fn collect() -> usize {
+    let tracked = HEAP.with_borrow_mut(|heap| mem::take(&mut heap.tracked));
+    let mut graph = Graph::default();
+    let roots: Vec<usize> = tracked
+        .iter()
+        .filter_map(Tracked::upgrade)
+        .map(|node| graph.add(node))
+        .collect();
+    drop(tracked);
+    graph.explore();
+    let live = graph.mark();
+    let mut garbage = Garbage::default();
+    let mut freed = 0;
+    for (node, _) in graph.nodes.iter().zip(&live).filter(|(_, live)| !**live) {
+        if node.clear(&mut garbage) {
+            freed += 1;
+        }
+    }
+    let mut survivors: Vec<Tracked> = Vec::new();
+    let mut seen = vec![false; graph.nodes.len()];
+    for i in roots {
+        if live[i] && !mem::replace(&mut seen[i], true) {
+            survivors.extend(graph.nodes[i].tracked());
+        }
+    }
+    // let go of the graph before the garbage, so the garbage is the last thing holding what it frees
+    drop(graph);
+    drop(garbage);
+    HEAP.with_borrow_mut(|heap| {
+        heap.threshold = MIN_THRESHOLD.max(2 * survivors.len());
+        // objects may have been tracked while the garbage was dropped
+        survivors.append(&mut heap.tracked);
+        heap.tracked = survivors;
+        heap.collections += 1;
+        heap.collected += freed;
+    });
+    freed
+}
+
+/// Count the objects alive and collected on this thread
+#[must_use]
+pub fn stats() -> Stats {
+    HEAP.with_borrow(|heap| {
+        let atoms = heap
+            .tracked
+            .iter()
+            .filter(|tracked| matches!(tracked, Tracked::Atom(atom) if atom.strong_count() > 0))
+            .count();
+        // an environment is tracked again each time a lambda captures it
+        let tracked = heap
+            .tracked
+            .iter()
+            .filter_map(|tracked| Some(tracked.upgrade()?.key()))
+            .collect::<HashSet<_>>()
+            .len();
+        Stats {
+            envs: LIVE_ENVS.get(),
+            atoms,
+            lazy_seqs: LIVE_LAZY_SEQS.get(),
+            tracked,
+            collections: heap.collections,
+            collected: heap.collected,
+        }
+    })
+}
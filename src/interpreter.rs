@@ -0,0 +1,159 @@
+use std::{fs, path::Path, rc::Rc};
+
+use crate::{
+    env::{self, Env},
+    eval::{self, builtins},
+    parser,
+    types::{
+        convert::{FromValue, IntoArgs, IntoValue},
+        Symbol, Value,
+    },
+};
+
+/// An interpreter with its own global environment, for embedding lisping in a Rust program.
+///
+/// Interpreters don't share definitions, documentation, or imported modules, so several can be used at once.
+///
+/// ```
+/// use lisping::Interpreter;
+///
+/// let lisp = Interpreter::new();
+/// lisp.eval_str("(defun! double (x) (* x 2))").unwrap();
+/// let doubled: i64 = lisp.call("double", (21,)).unwrap();
+/// assert_eq!(doubled, 42);
+/// ```
+pub struct Interpreter {
+    env: Env,
+}
+
+impl Default for Interpreter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Interpreter {
+    /// Create an interpreter with the builtins and stdlib defined
+    #[must_use]
+    pub fn new() -> Self {
+        Self::with_args(Vec::<String>::new())
+    }
+
+    /// Create an interpreter whose `*ARGS*` are the given strings
+    #[must_use]
+    pub fn with_args(args: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        let args = args
+            .into_iter()
+            .map(|arg| Value::String(arg.into()))
+            .collect();
+        Self {
+            env: env::default_env(args),
+        }
+    }
+
+    /// The interpreter's global environment
+    #[must_use]
+    pub const fn env(&self) -> &Env {
+        &self.env
+    }
+
+    /// Parse and evaluate source code, returning the value of the last expression.
+    /// `name` is the file name used in error locations.
+    /// # Errors
+    /// `(ParseError name message)` if the code doesn't parse, or the error raised while evaluating it
+    pub fn eval_named(&self, src: &str, name: &str) -> Result<Value, Value> {
+        let code = parser::parse(src, name).map_err(|err| {
+            Value::error(
+                "ParseError",
+                vec![
+                    Value::String(name.to_string()),
+                    Value::String(err.to_string()),
+                ],
+            )
+        })?;
+        eval::eval(code, self.env.clone())
+    }
+
+    /// Parse and evaluate source code, returning the value of the last expression
+    /// # Errors
+    /// `(ParseError "<eval>" message)` if the code doesn't parse, or the error raised while evaluating it
+    pub fn eval_str(&self, src: &str) -> Result<Value, Value> {
+        self.eval_named(src, "<eval>")
+    }
+
+    /// Evaluate a file, returning the value of its last expression. Imports in it are relative to it.
+    /// # Errors
+    /// `(IoError Kind "path")` if the file can't be read, `(ParseError "path" message)` if it doesn't parse,
+    /// or the error raised while evaluating it
+    pub fn eval_file(&self, path: impl AsRef<Path>) -> Result<Value, Value> {
+        let path = path.as_ref();
+        let src = fs::read_to_string(path).map_err(|err| builtins::fs::io_error(path, &err))?;
+        self.eval_named(&src, &path.to_string_lossy())
+    }
+
+    /// Get the value of a global definition
+    #[must_use]
+    pub fn get(&self, name: &str) -> Option<Value> {
+        self.env.borrow().get(Symbol::intern(name))
+    }
+
+    /// Define a global value
+    pub fn set(&self, name: &str, value: impl IntoValue) {
+        self.env
+            .borrow_mut()
+            .set(Symbol::intern(name), value.into_value());
+    }
+
+    /// Call the function defined with a name, converting the arguments and result
+    /// # Errors
+    /// `(UnresolvedIdentifier name)` if nothing is defined with the name, the error raised by the function,
+    /// or the error converting its result
+    pub fn call<R: FromValue>(&self, name: &str, args: impl IntoArgs) -> Result<R, Value> {
+        let func = self
+            .get(name)
+            .ok_or_else(|| Value::error("UnresolvedIdentifier", vec![Value::symbol(name)]))?;
+        self.call_value(func, args)
+    }
+
+    /// Call a function value, converting the arguments and result
+    /// # Errors
+    /// The error raised by the function, or the error converting its result
+    pub fn call_value<R: FromValue>(&self, func: Value, args: impl IntoArgs) -> Result<R, Value> {
+        let args = Value::List(args.into_args().into());
+        R::from_value(builtins::apply(vec![func, args], self.env.clone())?)
+    }
+
+    /// Define a builtin function implemented in Rust, with documentation for `help`
+    ///
+    /// ```
+    /// use lisping::{FromValue, IntoValue, Interpreter};
+    ///
+    /// let lisp = Interpreter::new();
+    /// lisp.register("shout", "Convert a string to uppercase", |mut args, _env| {
+    ///     let s = String::from_value(args.remove(0))?;
+    ///     Ok(s.to_uppercase().into_value())
+    /// });
+    /// assert_eq!(lisp.call::<String>("shout", ("hi",)).unwrap(), "HI");
+    /// assert!(Interpreter::new().doc("shout").is_none());
+    /// ```
+    pub fn register(
+        &self,
+        name: &str,
+        doc: &str,
+        func: impl Fn(Vec<Value>, Env) -> Result<Value, Value> + 'static,
+    ) {
+        self.set(name, Value::function(Rc::new(func)));
+        self.env
+            .borrow()
+            .runtime()
+            .docs
+            .borrow_mut()
+            .insert(name.to_string(), doc.to_string());
+    }
+
+    /// Get the documentation for a name
+    #[must_use]
+    pub fn doc(&self, name: &str) -> Option<String> {
+        self.env.borrow().runtime().docs.borrow().get(name).cloned()
+    }
+}
@@ -0,0 +1,19 @@
+#![warn(clippy::pedantic, clippy::nursery)]
+#![allow(clippy::mutable_key_type)]
+
+pub mod env;
+pub mod eval;
+pub mod gc;
+pub mod interpreter;
+mod line_count;
+pub mod module;
+pub mod parser;
+pub mod span;
+pub mod trace;
+pub mod types;
+
+pub use interpreter::Interpreter;
+pub use types::{
+    convert::{FromValue, IntoArgs, IntoValue},
+    Value,
+};
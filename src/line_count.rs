@@ -1,3 +1,4 @@
+#[derive(Clone)]
 struct LineCount<I: Iterator<Item = char>> {
     iter: I,
     row: usize,
@@ -30,11 +31,11 @@ impl<I: Iterator<Item = char>> Iterator for LineCount<I> {
 }
 
 pub trait LineCountable {
-    fn line_count(self) -> impl Iterator<Item = (usize, usize, char)>;
+    fn line_count(self) -> impl Iterator<Item = (usize, usize, char)> + Clone;
 }
 
-impl<I: Iterator<Item = char>> LineCountable for I {
-    fn line_count(self) -> impl Iterator<Item = (usize, usize, char)> {
+impl<I: Iterator<Item = char> + Clone> LineCountable for I {
+    fn line_count(self) -> impl Iterator<Item = (usize, usize, char)> + Clone {
         LineCount::new(self)
     }
 }
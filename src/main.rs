@@ -1,81 +1,87 @@
 #![warn(clippy::pedantic, clippy::nursery)]
 #![allow(clippy::mutable_key_type)]
-use std::{
-    cell::RefCell,
-    fs,
-    io::{stdin, stdout, Write},
-    path::PathBuf,
-    rc::Rc,
-};
+use std::{fs, path::PathBuf, process, rc::Rc};
 
-use clap::Parser;
+use clap::{Parser, Subcommand};
+use lisping::{env, eval, parser, trace::format_error, types::Value};
 
-pub mod env;
-pub mod eval;
-pub mod line_count;
-pub mod parser;
-pub mod types;
-
-use types::Value;
+mod repl;
+mod testing;
 
+/// Run a source file with `lisping <SRC> [ARGS]...`, passing it the arguments after it, or enter REPL mode
+/// with no source file
 #[derive(Parser)]
+#[command(
+    allow_external_subcommands = true,
+    override_usage = "lisping [OPTIONS] [SRC] [ARGS]...\n       lisping [OPTIONS] test [PATHS]..."
+)]
 struct Args {
-    /// The source file to run. Omit to enter REPL mode
-    src: Option<PathBuf>,
-    /// The arguments to pass to the program
-    args: Vec<String>,
+    #[command(subcommand)]
+    command: Option<Command>,
+    /// How many evaluations can be nested inside each other before a `StackOverflow` error is raised
+    #[arg(long, global = true, default_value_t = eval::DEFAULT_MAX_DEPTH)]
+    max_depth: usize,
+    /// How to evaluate code: `tree` walks the syntax tree, and `vm` compiles it to bytecode
+    #[arg(long, global = true, default_value = "tree")]
+    engine: eval::Engine,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Run the tests defined with `deftest`
+    Test {
+        /// Test files, or directories to search for `*_test.lisp` files. Defaults to the current directory
+        paths: Vec<PathBuf>,
+    },
+    /// A source file to run, followed by the arguments to pass to it. Options go before the source file.
+    #[command(external_subcommand)]
+    Run(Vec<String>),
 }
 
 fn main() {
     let args = Args::parse();
-    if let Some(src) = args.src {
-        let src = fs::read_to_string(&src).unwrap();
-        let code = parser::parse(&src).unwrap();
-        // println!("{code:?}");
-        let args = args.args.into_iter().map(Value::String).collect();
-        let result = eval::eval(code, env::default_env(args));
-        println!("{result:?}");
-    } else {
-        let env = env::default_env(Rc::new([]));
-        let mut code = Value::nil();
-        let quit_flag = Rc::new(RefCell::new(false));
-        let qf = quit_flag.clone();
-        env.borrow_mut().set(
-            "quit",
-            Value::Function {
-                fn_ref: Rc::new(move |_, _| {
-                    qf.replace(true);
-                    Ok(Value::nil())
-                }),
-                is_macro: false,
-            },
-        );
-        while !*quit_flag.borrow() {
-            print!("> ");
-            stdout().flush().unwrap();
-            let mut s = String::new();
-            stdin().read_line(&mut s).unwrap();
-            if !s.is_empty() {
-                match parser::parse(&s) {
-                    Ok(c) => {
-                        code = c;
-                    }
-                    Err(e) => {
-                        println!("Parse error: {e}");
-                        continue;
-                    }
-                }
-            };
-            let result = eval::eval(code.clone(), env.clone());
-            match result {
-                Ok(result) => {
-                    println!("{result:?}");
-                    env.borrow_mut().set("_", result);
-                }
-                Err(result) => {
-                    println!("err: {result:?}");
-                }
+    eval::set_max_depth(args.max_depth);
+    eval::set_engine(args.engine);
+    match args.command {
+        Some(Command::Test { mut paths }) => {
+            if paths.is_empty() {
+                paths.push(PathBuf::from("."));
             }
+            if !testing::run(&paths) {
+                process::exit(1);
+            }
+        }
+        Some(Command::Run(mut args)) => {
+            let path = args.remove(0);
+            run(&path, args);
+        }
+        None => repl::run(&env::default_env(Rc::new([]))),
+    }
+}
+
+/// Run a source file, passing it arguments, and print what it evaluates to
+fn run(path: &str, args: Vec<String>) {
+    let src = match fs::read_to_string(path) {
+        Ok(src) => src,
+        Err(e) => {
+            eprintln!("Can't read {path}: {e}");
+            process::exit(1);
+        }
+    };
+    let code = match parser::parse(&src, path) {
+        Ok(code) => code,
+        Err(e) => {
+            eprintln!("Parse error: {e}");
+            process::exit(1);
+        }
+    };
+    // println!("{code:?}");
+    let args = args.into_iter().map(Value::String).collect();
+    match eval::eval(code, env::default_env(args)) {
+        Ok(result) => println!("Ok({result:?})"),
+        Err(err) => {
+            eprintln!("{}", format_error(&err));
+            process::exit(1);
         }
     }
 }
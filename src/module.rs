@@ -0,0 +1,122 @@
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    env::{self, Env},
+    eval::eval,
+    parser::parse,
+    span::Span,
+    types::Value,
+};
+
+/// A module that has been imported
+enum Module {
+    /// The module is still being evaluated; importing it again is a cycle
+    Loading,
+    /// The module's definitions, as a table from symbols to values
+    Loaded(Value),
+}
+
+/// The modules an interpreter has imported
+#[derive(Default)]
+pub(crate) struct Modules {
+    /// Every module that has been imported, by canonical path
+    loaded: RefCell<HashMap<PathBuf, Module>>,
+    /// The modules currently being evaluated, outermost first
+    importing: RefCell<Vec<PathBuf>>,
+}
+
+fn io_error(path: &Path, err: &std::io::Error) -> Value {
+    Value::error(
+        "ImportError",
+        vec![
+            Value::String(path.to_string_lossy().to_string()),
+            Value::String(err.to_string()),
+        ],
+    )
+}
+
+/// Resolve an import path relative to the file the import appears in
+fn resolve(path: &str, from: Option<&Span>) -> Result<PathBuf, Value> {
+    let base = from
+        .map(|span| Path::new(&*span.file))
+        // REPL input and other non-file sources are relative to the working directory
+        .filter(|file| !file.to_string_lossy().starts_with('<'))
+        .and_then(Path::parent)
+        .unwrap_or_else(|| Path::new(""));
+    let path = base.join(path);
+    path.canonicalize().map_err(|err| io_error(&path, &err))
+}
+
+/// The name a module's definitions are qualified with: its file name without the extension
+#[must_use]
+pub fn namespace(path: &str) -> String {
+    Path::new(path).file_stem().map_or_else(
+        || path.to_string(),
+        |stem| stem.to_string_lossy().to_string(),
+    )
+}
+
+/// Load a module, evaluating it into its own environment the first time it is imported.
+/// Returns a table of the module's definitions.
+/// # Errors
+/// If the file can't be read or parsed, if evaluating it fails, or if it is already being imported
+pub fn import(path: &str, from: Option<&Span>, env: &Env) -> Result<Value, Value> {
+    let path = resolve(path, from)?;
+    let runtime = env.borrow().runtime();
+    let modules = &runtime.modules;
+    match modules.loaded.borrow().get(&path) {
+        Some(Module::Loaded(table)) => return Ok(table.clone()),
+        Some(Module::Loading) => {
+            let cycle = modules
+                .importing
+                .borrow()
+                .iter()
+                .skip_while(|p| **p != path)
+                .chain([&path])
+                .map(|p| Value::String(p.to_string_lossy().to_string()))
+                .collect();
+            return Err(Value::error("ImportCycle", cycle));
+        }
+        None => {}
+    }
+    let src = fs::read_to_string(&path).map_err(|err| io_error(&path, &err))?;
+    let code = parse(&src, &path.to_string_lossy()).map_err(|err| {
+        Value::error(
+            "ParseError",
+            vec![
+                Value::String(path.to_string_lossy().to_string()),
+                Value::String(err.to_string()),
+            ],
+        )
+    })?;
+
+    modules
+        .loaded
+        .borrow_mut()
+        .insert(path.clone(), Module::Loading);
+    modules.importing.borrow_mut().push(path.clone());
+    let module_env = env::new_global(env::root(env));
+    let result = eval(code, module_env.clone());
+    modules.importing.borrow_mut().pop();
+    if let Err(err) = result {
+        modules.loaded.borrow_mut().remove(&path);
+        return Err(err);
+    }
+
+    let table: im_rc::HashMap<_, _> = module_env
+        .borrow()
+        .bindings()
+        .map(|(k, v)| (Value::Symbol(k), v.clone()))
+        .collect();
+    let table = Value::Table(table);
+    modules
+        .loaded
+        .borrow_mut()
+        .insert(path, Module::Loaded(table.clone()));
+    Ok(table)
+}
@@ -1,6 +1,16 @@
-use std::{collections::HashMap, iter::Peekable, rc::Rc};
+use std::{fmt::Display, iter::Peekable, rc::Rc};
 
-use crate::{line_count::LineCountable, types::Value};
+use im_rc::HashMap;
+
+use num_bigint::BigInt;
+use num_rational::BigRational;
+use num_traits::Signed;
+
+use crate::{
+    line_count::LineCountable,
+    span::{self, Span},
+    types::{number::Number, List, Symbol, Value},
+};
 
 #[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 enum ParserState {
@@ -9,14 +19,42 @@ enum ParserState {
     Quote,
     QuasiQuote,
     Unquote,
+    SpliceUnquote,
     Table,
+    Set,
+}
+
+/// An error encountered while parsing
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ParseError {
+    /// The source ended in the middle of a form, so more input could complete it
+    Incomplete(String),
+    /// The source is malformed
+    Invalid(String),
+}
+
+impl ParseError {
+    #[must_use]
+    pub const fn is_incomplete(&self) -> bool {
+        matches!(self, Self::Incomplete(_))
+    }
 }
 
+impl Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Incomplete(msg) | Self::Invalid(msg) => write!(f, "{msg}"),
+        }
+    }
+}
+
+/// Parse source code into a `do` block. Lists are recorded with their location in `file`
 /// # Errors
-pub fn parse(src: &str) -> Result<Value, String> {
+pub fn parse(src: &str, file: &str) -> Result<Value, ParseError> {
+    let file: Rc<str> = Rc::from(file);
     let mut chars = src.chars().line_count().peekable();
-    // the stack of arrays at higher depths
-    let mut parse_stack: Vec<Vec<Value>> = Vec::new();
+    // the stack of arrays at higher depths, with the location each of their children was opened at
+    let mut parse_stack: Vec<(Vec<Value>, usize, usize)> = Vec::new();
     let mut states: Vec<ParserState> = Vec::new();
     // the array of atoms at the current depth
     let mut current_array: Vec<Value> = Vec::new();
@@ -26,6 +64,7 @@ pub fn parse(src: &str) -> Result<Value, String> {
             &mut current_array,
             &mut states,
             &mut parse_stack,
+            &file,
         )?
         else {
             break 'main;
@@ -44,19 +83,31 @@ pub fn parse(src: &str) -> Result<Value, String> {
                     states.pop();
                     next_thing = Value::List(vec![Value::symbol("unquote"), next_thing].into());
                 }
+                Some(ParserState::SpliceUnquote) => {
+                    states.pop();
+                    next_thing =
+                        Value::List(vec![Value::symbol("splice-unquote"), next_thing].into());
+                }
                 Some(ParserState::Table) => {
                     states.pop();
                     let Value::List(l) = next_thing else {
-                        return Err("Invalid table syntax".to_string());
+                        return Err(ParseError::Invalid("Invalid table syntax".to_string()));
                     };
                     if l.len() % 2 != 0 {
-                        return Err("Invalid table syntax".to_string());
+                        return Err(ParseError::Invalid("Invalid table syntax".to_string()));
                     }
                     let mut hm = HashMap::new();
-                    for i in 0..(l.len() / 2) {
-                        hm.insert(l[2 * i].clone(), l[2 * i + 1].clone());
+                    for pair in l.values().chunks(2) {
+                        hm.insert(pair[0].clone(), pair[1].clone());
                     }
-                    next_thing = Value::Table(Rc::new(hm));
+                    next_thing = Value::Table(hm);
+                }
+                Some(ParserState::Set) => {
+                    states.pop();
+                    let Value::List(l) = next_thing else {
+                        return Err(ParseError::Invalid("Invalid set syntax".to_string()));
+                    };
+                    next_thing = Value::Set(l.iter().cloned().collect());
                 }
                 Some(ParserState::Array | ParserState::List) | None => break,
             }
@@ -65,23 +116,58 @@ pub fn parse(src: &str) -> Result<Value, String> {
     }
     if parse_stack.is_empty() {
         current_array.insert(0, Value::symbol("do"));
-        Ok(Value::List(current_array.into()))
+        let code = List::from(current_array);
+        span::record(
+            &code,
+            Span {
+                file,
+                row: 0,
+                col: 1,
+            },
+        );
+        Ok(Value::List(code))
     } else {
-        Err("Unmatched opening parenthesis".to_string())
+        Err(ParseError::Incomplete(
+            "Unmatched opening parenthesis".to_string(),
+        ))
     }
 }
 
 #[allow(clippy::too_many_lines)]
 fn read_value(
-    chars: &mut Peekable<impl Iterator<Item = (usize, usize, char)>>,
+    chars: &mut Peekable<impl Iterator<Item = (usize, usize, char)> + Clone>,
     current_array: &mut Vec<Value>,
     states: &mut Vec<ParserState>,
-    parse_stack: &mut Vec<Vec<Value>>,
-) -> Result<Option<Value>, String> {
+    parse_stack: &mut Vec<(Vec<Value>, usize, usize)>,
+    file: &Rc<str>,
+) -> Result<Option<Value>, ParseError> {
+    // close the current array, recording where it was opened
+    let close = |current_array: &mut Vec<Value>, (previous_level, row, col)| {
+        let arr = List::from(core::mem::replace(current_array, previous_level));
+        span::record(
+            &arr,
+            Span {
+                file: file.clone(),
+                row,
+                col,
+            },
+        );
+        Value::List(arr)
+    };
+    let at = |row, col| Span {
+        file: file.clone(),
+        row,
+        col,
+    };
     'by_char: while let Some((row, col, c)) = chars.next() {
         // println!("{parse_stack:#?}\n{current_array:#?}\n{row}:{col} = {c:?}");
         // begin a comment
-        if c == '#' {
+        if c == '#' && chars.next_if(|(_, _, x)| *x == '{').is_some() {
+            // begin a new set
+            parse_stack.push((core::mem::take(current_array), row, col));
+            states.push(ParserState::Set);
+            states.push(ParserState::Array);
+        } else if c == '#' {
             if chars.peek().is_some_and(|(_, _, x)| *x == '#') {
                 chars.next();
                 let mut doc_buf = String::new();
@@ -92,8 +178,8 @@ fn read_value(
                     }
                     doc_buf.push(c);
                 }
-                current_array.push(Value::List(Rc::new([
-                    Value::Symbol("doc".to_string()),
+                current_array.push(Value::List(List::from([
+                    Value::Symbol(Symbol::DOC),
                     Value::String(doc_buf),
                 ])));
                 continue 'by_char;
@@ -106,7 +192,7 @@ fn read_value(
             }
         } else if c == '{' {
             // begin a new table
-            parse_stack.push(core::mem::take(current_array));
+            parse_stack.push((core::mem::take(current_array), row, col));
             states.push(ParserState::Table);
             states.push(ParserState::Array);
         } else if c == '}' {
@@ -114,26 +200,30 @@ fn read_value(
             if let (Some(previous_level), Some(ParserState::Array)) =
                 (parse_stack.pop(), states.pop())
             {
-                let arr = Value::List(core::mem::replace(current_array, previous_level).into());
-                return Ok(Some(arr));
+                return Ok(Some(close(current_array, previous_level)));
             }
-            return Err(format!("Unmatched closing curly bracket at {row}:{col}"));
+            return Err(ParseError::Invalid(format!(
+                "Unmatched closing curly bracket at {}",
+                at(row, col)
+            )));
         } else if c == '(' {
             // begin a new array
-            parse_stack.push(core::mem::take(current_array));
+            parse_stack.push((core::mem::take(current_array), row, col));
             states.push(ParserState::Array);
         } else if c == ')' {
             // end the current array
             if let (Some(previous_level), Some(ParserState::Array)) =
                 (parse_stack.pop(), states.pop())
             {
-                let arr = Value::List(core::mem::replace(current_array, previous_level).into());
-                return Ok(Some(arr));
+                return Ok(Some(close(current_array, previous_level)));
             }
-            return Err(format!("Unmatched closing parenthesis at {row}:{col}"));
+            return Err(ParseError::Invalid(format!(
+                "Unmatched closing parenthesis at {}",
+                at(row, col)
+            )));
         } else if c == '[' {
             // begin a new list
-            parse_stack.push(core::mem::take(current_array));
+            parse_stack.push((core::mem::take(current_array), row, col));
             states.push(ParserState::List);
         } else if c == ']' {
             // end the current array
@@ -141,79 +231,195 @@ fn read_value(
                 (parse_stack.pop(), states.pop())
             {
                 current_array.insert(0, Value::symbol("list"));
-                let arr = Value::List(core::mem::replace(current_array, previous_level).into());
-                return Ok(Some(arr));
+                return Ok(Some(close(current_array, previous_level)));
             }
-            return Err(format!("Unmatched closing square bracket at {row}:{col}"));
+            return Err(ParseError::Invalid(format!(
+                "Unmatched closing square bracket at {}",
+                at(row, col)
+            )));
         } else if c == '\'' {
             states.push(ParserState::Quote);
         } else if c == '`' {
             states.push(ParserState::QuasiQuote);
+        } else if c == '~' && chars.next_if(|(_, _, x)| *x == '@').is_some() {
+            states.push(ParserState::SpliceUnquote);
         } else if c == '~' {
             states.push(ParserState::Unquote);
         } else if c.is_whitespace() {
-        } else if c.is_numeric() {
-            let mut int_value = c as u32 - '0' as u32;
-            while let Some((row, col, c)) = chars.peek() {
-                if c.is_whitespace()
-                    || *c == '('
-                    || *c == ')'
-                    || *c == '['
-                    || *c == ']'
-                    || *c == '#'
-                    || *c == '\''
-                    || *c == '{'
-                    || *c == '}'
-                {
+        } else if c.is_ascii_digit()
+            || (matches!(c, '-' | '+') && chars.peek().is_some_and(|(_, _, x)| x.is_ascii_digit()))
+        {
+            let mut num_buffer = String::from(c);
+            while let Some((_, _, c)) = chars.peek() {
+                if is_delimiter(*c) {
                     break;
                 }
-                if !c.is_numeric() {
-                    return Err(format!(
-                        "Invalid character in int literal: `{c:?}`; {row}:{col}"
-                    ));
-                }
-                int_value *= 10;
-                int_value += *c as u32 - '0' as u32;
+                num_buffer.push(*c);
                 chars.next();
             }
-            #[allow(clippy::cast_possible_wrap)]
-            return Ok(Some(Value::Int(int_value as i128)));
+            return parse_number(&num_buffer).map(Some).ok_or_else(|| {
+                ParseError::Invalid(format!(
+                    "Invalid number literal: `{num_buffer}` at {}",
+                    at(row, col)
+                ))
+            });
         } else if c == '"' {
+            let mut string_buf = String::new();
+            while let Some((esc_row, esc_col, c)) = chars.next() {
+                match c {
+                    '"' => return Ok(Some(Value::String(string_buf))),
+                    '\\' => string_buf.push(read_escape(chars, &at(esc_row, esc_col))?),
+                    c => string_buf.push(c),
+                }
+            }
+            return Err(ParseError::Incomplete(format!(
+                "Unmatched quote at {}",
+                at(row, col)
+            )));
+        } else if let Some(hashes) = (c == 'r').then(|| raw_string_start(chars)).flatten() {
+            // a raw string like r#"..."#, which ends at a quote followed by the same number of #s
+            for _ in 0..=hashes {
+                chars.next();
+            }
             let mut string_buf = String::new();
             while let Some((_, _, c)) = chars.next() {
                 if c == '"' {
-                    return Ok(Some(Value::String(
-                        string_buf.replace("\\n", "\n").replace("\\\\", "\\"),
-                    )));
+                    let mut closing = chars.clone();
+                    if (0..hashes).all(|_| closing.next().is_some_and(|(_, _, c)| c == '#')) {
+                        *chars = closing;
+                        return Ok(Some(Value::String(string_buf)));
+                    }
                 }
                 string_buf.push(c);
-                if c == '\\' {
-                    let Some((_, _, c)) = chars.next() else {
-                        return Err("Unexpected EOF".to_string());
-                    };
-                    string_buf.push(c);
-                }
             }
-            return Err(format!("Unmatched quote; {row}:{col}"));
+            return Err(ParseError::Incomplete(format!(
+                "Unmatched raw string quote at {}",
+                at(row, col)
+            )));
         } else {
             let mut id_buffer = String::from(c);
-            while let Some((_, _, c)) = chars.peek() {
-                if c.is_whitespace()
-                    || *c == '('
-                    || *c == ')'
-                    || *c == '['
-                    || *c == ']'
-                    || *c == '#'
-                    || *c == '{'
-                    || *c == '}'
-                {
+            while let Some(&(_, _, c)) = chars.peek() {
+                if c == '#' && ends_symbol(chars) {
+                    // an auto-gensym name like `tmp#`
+                    id_buffer.push('#');
+                    chars.next();
                     break;
                 }
-                id_buffer.push(*c);
+                if is_delimiter(c) {
+                    break;
+                }
+                id_buffer.push(c);
                 chars.next();
             }
-            return Ok(Some(Value::symbol(&id_buffer)));
+            return Ok(Some(match id_buffer.strip_prefix(':') {
+                Some(name) if !name.is_empty() => Value::keyword(name),
+                _ => Value::symbol(&id_buffer),
+            }));
         }
     }
     Ok(None)
 }
+
+/// Read the escape sequence after a backslash in a string. `at` is the backslash's location.
+fn read_escape(
+    chars: &mut Peekable<impl Iterator<Item = (usize, usize, char)>>,
+    at: &Span,
+) -> Result<char, ParseError> {
+    let invalid = |seq: &str| {
+        ParseError::Invalid(format!(
+            "Invalid escape sequence `\\{seq}` in string at {at}"
+        ))
+    };
+    let Some((_, _, c)) = chars.next() else {
+        return Err(ParseError::Incomplete("Unexpected EOF".to_string()));
+    };
+    Ok(match c {
+        'n' => '\n',
+        't' => '\t',
+        'r' => '\r',
+        '0' => '\0',
+        '"' => '"',
+        '\'' => '\'',
+        '\\' => '\\',
+        'x' => {
+            let digits: String = (0..2)
+                .map_while(|_| chars.next_if(|(_, _, c)| c.is_ascii_hexdigit()))
+                .map(|(_, _, c)| c)
+                .collect();
+            // like Rust, only ASCII can be written this way since other bytes aren't valid UTF-8 on their own
+            u8::from_str_radix(&digits, 16)
+                .ok()
+                .filter(|b| digits.len() == 2 && b.is_ascii())
+                .map(char::from)
+                .ok_or_else(|| invalid(&format!("x{digits}")))?
+        }
+        'u' => {
+            if chars.next_if(|(_, _, c)| *c == '{').is_none() {
+                return Err(invalid("u"));
+            }
+            let mut digits = String::new();
+            loop {
+                match chars.next() {
+                    Some((_, _, '}')) => break,
+                    Some((_, _, c)) if c.is_ascii_hexdigit() && digits.len() < 6 => digits.push(c),
+                    Some((_, _, c)) => return Err(invalid(&format!("u{{{digits}{c}"))),
+                    None => return Err(ParseError::Incomplete("Unexpected EOF".to_string())),
+                }
+            }
+            u32::from_str_radix(&digits, 16)
+                .ok()
+                .and_then(char::from_u32)
+                .ok_or_else(|| invalid(&format!("u{{{digits}}}")))?
+        }
+        other => return Err(invalid(&other.to_string())),
+    })
+}
+
+/// If the characters after an `r` start a raw string (`r"` or `r#"`), get how many `#`s it uses
+fn raw_string_start(
+    chars: &Peekable<impl Iterator<Item = (usize, usize, char)> + Clone>,
+) -> Option<usize> {
+    let mut hashes = 0;
+    for (_, _, c) in chars.clone() {
+        match c {
+            '#' => hashes += 1,
+            '"' => return Some(hashes),
+            _ => return None,
+        }
+    }
+    None
+}
+
+/// Check if the `#` next in the input is the last character of the symbol before it, rather than the start of a
+/// comment or set
+fn ends_symbol(chars: &Peekable<impl Iterator<Item = (usize, usize, char)> + Clone>) -> bool {
+    let mut ahead = chars.clone();
+    ahead.next();
+    ahead
+        .peek()
+        .is_none_or(|(_, _, c)| is_delimiter(*c) && !matches!(c, '#' | '{'))
+}
+
+/// Check if a character ends a symbol or number
+#[must_use]
+pub const fn is_delimiter(c: char) -> bool {
+    c.is_whitespace() || matches!(c, '(' | ')' | '[' | ']' | '#' | '{' | '}')
+}
+
+/// Parse an int (`12`), ratio (`1/3`), or float (`1.5`, `1e-3`) literal. Ints of any length are allowed.
+fn parse_number(src: &str) -> Option<Value> {
+    if let Ok(i) = src.parse::<BigInt>() {
+        return Some(Number::BigInt(i).into_value());
+    }
+    if let Some((numer, denom)) = src.split_once('/') {
+        let (numer, denom): (BigInt, BigInt) = (numer.parse().ok()?, denom.parse().ok()?);
+        if !denom.is_positive() {
+            return None;
+        }
+        return Some(Number::Ratio(BigRational::new(numer, denom)).into_value());
+    }
+    if src.contains(['.', 'e', 'E']) {
+        return src.parse().ok().map(Value::Float);
+    }
+    None
+}
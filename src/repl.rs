@@ -0,0 +1,181 @@
+use std::{cell::RefCell, path::PathBuf, rc::Rc};
+
+use rustyline::{
+    completion::{Completer, Pair},
+    error::ReadlineError,
+    highlight::Highlighter,
+    hint::Hinter,
+    history::DefaultHistory,
+    validate::{ValidationContext, ValidationResult, Validator},
+    Context, Editor, Helper,
+};
+
+use lisping::{
+    env::Env,
+    eval::{self, expand},
+    parser::{self, is_delimiter},
+    trace::format_error,
+    types::{Symbol, Value},
+};
+
+/// Completes symbols from the environment and documentation, and asks for more lines until the input parses
+struct ReplHelper {
+    env: Env,
+}
+
+impl Completer for ReplHelper {
+    type Candidate = Pair;
+
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        _ctx: &Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<Pair>)> {
+        let start = line[..pos]
+            .char_indices()
+            .rev()
+            .find(|(_, c)| is_delimiter(*c) || matches!(c, '\'' | '`' | '~' | '"'))
+            .map_or(0, |(i, c)| i + c.len_utf8());
+        let prefix = &line[start..pos];
+        if prefix.is_empty() {
+            return Ok((start, Vec::new()));
+        }
+        let mut names = self.env.borrow().names();
+        names.extend(self.env.borrow().runtime().docs.borrow().keys().cloned());
+        names.retain(|name| name.starts_with(prefix));
+        names.sort_unstable();
+        names.dedup();
+        Ok((
+            start,
+            names
+                .into_iter()
+                .map(|name| Pair {
+                    display: name.clone(),
+                    replacement: name,
+                })
+                .collect(),
+        ))
+    }
+}
+
+impl Hinter for ReplHelper {
+    type Hint = String;
+}
+
+impl Highlighter for ReplHelper {}
+
+impl Validator for ReplHelper {
+    fn validate(&self, ctx: &mut ValidationContext) -> rustyline::Result<ValidationResult> {
+        match parser::parse(ctx.input(), "<repl>") {
+            Err(e) if e.is_incomplete() => Ok(ValidationResult::Incomplete),
+            _ => Ok(ValidationResult::Valid(None)),
+        }
+    }
+}
+
+impl Helper for ReplHelper {}
+
+/// The file REPL history is kept in, in the user's home directory
+fn history_path() -> Option<PathBuf> {
+    std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".lisping_history"))
+}
+
+/// The form after an `:expand` command, or `None` if the input isn't one. Keywords like `:expanded` aren't
+/// commands.
+fn expand_command(input: &str) -> Option<&str> {
+    input
+        .trim_start()
+        .strip_prefix(":expand")
+        .filter(|form| form.is_empty() || form.starts_with(char::is_whitespace))
+}
+
+/// Print each step of expanding the macros in a form, with the macro that made it, and then the form
+/// with every macro expanded
+fn show_expansion(input: &str, env: &Env) {
+    let form = match parser::parse(input, "<repl>") {
+        // the forms read are wrapped in a `do`
+        Ok(Value::List(forms)) if forms.len() == 2 => forms.values()[1].clone(),
+        Ok(form) => form,
+        Err(e) => {
+            println!("Parse error: {e}");
+            return;
+        }
+    };
+    let mut steps = 0;
+    let expanded = expand::expand_all(form, env, &mut |name, expansion| {
+        steps += 1;
+        println!("{steps}. {name} => {expansion:?}");
+    });
+    match expanded {
+        Ok(expanded) if steps == 0 => println!("no macros to expand in {expanded:?}"),
+        Ok(expanded) => println!("{expanded:?}"),
+        Err(result) => println!("err: {}", format_error(&result)),
+    }
+}
+
+/// Read, evaluate, and print until the input ends or `(quit)` is called.
+///
+/// `:expand form` shows how the macros in a form expand, step by step, without evaluating it.
+/// Ctrl-C discards the current input, and Ctrl-D exits.
+/// # Panics
+/// If the terminal can't be set up for line editing
+pub fn run(env: &Env) {
+    let quit_flag = Rc::new(RefCell::new(false));
+    let qf = quit_flag.clone();
+    env.borrow_mut().set(
+        Symbol::intern("quit"),
+        Value::Function {
+            fn_ref: Rc::new(move |_, _| {
+                qf.replace(true);
+                Ok(Value::nil())
+            }),
+            is_macro: false,
+        },
+    );
+    let mut editor: Editor<ReplHelper, DefaultHistory> = Editor::new().unwrap();
+    editor.set_helper(Some(ReplHelper { env: env.clone() }));
+    let history = history_path();
+    if let Some(history) = &history {
+        // there is no history the first time the REPL is run
+        let _ = editor.load_history(history);
+    }
+    while !*quit_flag.borrow() {
+        let input = match editor.readline("> ") {
+            Ok(input) => input,
+            Err(ReadlineError::Interrupted) => continue,
+            Err(ReadlineError::Eof) => break,
+            Err(e) => {
+                println!("Input error: {e}");
+                break;
+            }
+        };
+        if input.trim().is_empty() {
+            continue;
+        }
+        let _ = editor.add_history_entry(input.as_str());
+        if let Some(history) = &history {
+            let _ = editor.save_history(history);
+        }
+        if let Some(form) = expand_command(&input) {
+            show_expansion(form, env);
+            continue;
+        }
+        let code = match parser::parse(&input, "<repl>") {
+            Ok(code) => code,
+            Err(e) => {
+                println!("Parse error: {e}");
+                continue;
+            }
+        };
+        match eval::eval(code, env.clone()) {
+            Ok(result) => {
+                println!("{result:?}");
+                env.borrow_mut().set(Symbol::intern("_"), result);
+            }
+            Err(result) => {
+                println!("err: {}", format_error(&result));
+            }
+        }
+    }
+}
@@ -0,0 +1,92 @@
+use std::{cell::RefCell, collections::HashMap, fmt::Display, rc::Rc};
+
+use crate::types::{
+    list::{List, WeakList},
+    Value,
+};
+
+/// A location in a source file
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Span {
+    pub file: Rc<str>,
+    /// zero-indexed line
+    pub row: usize,
+    /// one-indexed column
+    pub col: usize,
+}
+
+impl Display for Span {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}:{}", self.file, self.row + 1, self.col)
+    }
+}
+
+/// A side table from list allocations to some data about them.
+///
+/// Lists are identified by the address of their storage. Each entry holds a weak reference so the address
+/// can't be reused by another list while the entry exists; entries for dropped lists are pruned as the table grows.
+pub(crate) struct ListTable<T> {
+    entries: HashMap<*const (), (WeakList, T)>,
+    prune_at: usize,
+}
+
+impl<T> ListTable<T> {
+    pub(crate) fn new() -> Self {
+        Self {
+            entries: HashMap::new(),
+            prune_at: 1024,
+        }
+    }
+
+    pub(crate) fn insert(&mut self, list: &List, value: T) {
+        if self.entries.len() >= self.prune_at {
+            self.entries.retain(|_, (weak, _)| weak.is_alive());
+            self.prune_at = (self.entries.len() * 2).max(1024);
+        }
+        self.entries
+            .insert(list.as_ptr(), (list.downgrade(), value));
+    }
+
+    pub(crate) fn get(&self, list: &List) -> Option<&T> {
+        self.entries.get(&list.as_ptr()).map(|(_, v)| v)
+    }
+}
+
+thread_local! {
+    /// Where each parsed list came from
+    static SPANS: RefCell<ListTable<Span>> = RefCell::new(ListTable::new());
+    /// Where each error was raised
+    static ERROR_SPANS: RefCell<ListTable<Span>> = RefCell::new(ListTable::new());
+}
+
+/// Record where a list was parsed from
+pub fn record(list: &List, span: Span) {
+    SPANS.with_borrow_mut(|spans| spans.insert(list, span));
+}
+
+/// Get where a list was parsed from, if it came from the parser
+#[must_use]
+pub fn of(list: &List) -> Option<Span> {
+    SPANS.with_borrow(|spans| spans.get(list).cloned())
+}
+
+/// Attach a location to an error, unless it already has one
+pub fn locate_error(err: &Value, span: Span) {
+    let Value::List(err) = err else {
+        return;
+    };
+    ERROR_SPANS.with_borrow_mut(|spans| {
+        if spans.get(err).is_none() {
+            spans.insert(err, span);
+        }
+    });
+}
+
+/// Get the location an error was raised at
+#[must_use]
+pub fn of_error(err: &Value) -> Option<Span> {
+    let Value::List(err) = err else {
+        return None;
+    };
+    ERROR_SPANS.with_borrow(|spans| spans.get(err).cloned())
+}
@@ -0,0 +1,167 @@
+use core::fmt::Write as _;
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    rc::Rc,
+};
+
+use lisping::{
+    env::{self, Env},
+    eval::{self, builtins},
+    parser,
+    trace::format_error,
+    types::{List, Symbol, Value},
+};
+
+/// Find the test files under a path. A file is always a test file; directories are searched for `*_test.lisp`.
+fn discover(path: &Path, files: &mut Vec<PathBuf>) -> io::Result<()> {
+    if !path.is_dir() {
+        files.push(path.to_path_buf());
+        return Ok(());
+    }
+    let mut entries = fs::read_dir(path)?
+        .map(|entry| Ok(entry?.path()))
+        .collect::<io::Result<Vec<_>>>()?;
+    entries.sort_unstable();
+    for entry in entries {
+        let name = entry.file_name().unwrap_or_default().to_string_lossy();
+        if name.starts_with('.') {
+            continue;
+        }
+        if entry.is_dir() {
+            discover(&entry, files)?;
+        } else if name.ends_with("_test.lisp") {
+            files.push(entry);
+        }
+    }
+    Ok(())
+}
+
+/// Evaluate a test file in a fresh environment, returning the tests it defines in the order they were defined
+fn load(code: &Value) -> Result<(Env, Vec<(Value, Value)>), Value> {
+    let env = env::default_env(Rc::new([]));
+    eval::eval(code.clone(), env.clone())?;
+    let Some(Value::Atom(tests)) = env.borrow().get(Symbol::intern("*TESTS*")) else {
+        return Err(Value::error(
+            "UnresolvedIdentifier",
+            vec![Value::symbol("*TESTS*")],
+        ));
+    };
+    let tests = tests.read().unwrap().clone();
+    let tests = tests
+        .as_list()
+        .map(List::to_vec)
+        .unwrap_or_default()
+        .iter()
+        .rev()
+        .filter_map(|test| match &test.as_list()?.values()[..] {
+            [name, func] => Some((name.clone(), func.clone())),
+            _ => None,
+        })
+        .collect();
+    Ok((env, tests))
+}
+
+/// Describe a failed test, showing where the expected and actual values of a failed assertion differ
+fn describe_failure(err: &Value) -> String {
+    let mut out = format_error(err);
+    if let Some([name, expected, actual]) = err.as_list().map(List::values).as_deref() {
+        if name.is_symbol(Symbol::intern("AssertFailed")) {
+            let expected = format!("{expected:?}");
+            let actual = format!("{actual:?}");
+            let diff_at = expected
+                .chars()
+                .zip(actual.chars())
+                .take_while(|(e, a)| e == a)
+                .count();
+            write!(
+                out,
+                "\n    expected: {expected}\n      actual: {actual}\n              {}^",
+                " ".repeat(diff_at)
+            )
+            .unwrap();
+        }
+    }
+    out
+}
+
+/// Indent every line after the first
+fn indent(text: &str) -> String {
+    text.replace('\n', "\n    ")
+}
+
+/// Run every test in a file, each in a fresh environment. Returns the number of tests that passed and failed.
+fn run_file(path: &Path) -> (usize, usize) {
+    println!("{}", path.display());
+    let code = fs::read_to_string(path)
+        .map_err(|err| err.to_string())
+        .and_then(|src| {
+            parser::parse(&src, &path.to_string_lossy())
+                .map_err(|err| format!("Parse error: {err}"))
+        });
+    let code = match code {
+        Ok(code) => code,
+        Err(err) => {
+            println!("  FAIL  {err}");
+            return (0, 1);
+        }
+    };
+    let names: Vec<Value> = match load(&code) {
+        Ok((_, tests)) => tests.into_iter().map(|(name, _)| name).collect(),
+        Err(err) => {
+            println!("  FAIL  {}", indent(&format_error(&err)));
+            return (0, 1);
+        }
+    };
+    let (mut passed, mut failed) = (0, 0);
+    for (i, name) in names.iter().enumerate() {
+        // re-evaluate the file so that no test can see what another test did
+        let result = load(&code).and_then(|(env, mut tests)| {
+            if i >= tests.len() {
+                return Err(Value::error("TestsChanged", vec![name.clone()]));
+            }
+            let (_, mut func) = tests.swap_remove(i);
+            // name the test's function after the test so it shows up in stack traces
+            if let (Value::Lambda { name, .. }, Value::Symbol(test)) = (&mut func, name) {
+                *name = Some(*test);
+            }
+            builtins::apply(vec![func, Value::List(List::new())], env)
+        });
+        match result {
+            Ok(_) => {
+                passed += 1;
+                println!("  ok    {name}");
+            }
+            Err(err) => {
+                failed += 1;
+                println!("  FAIL  {name}\n    {}", indent(&describe_failure(&err)));
+            }
+        }
+    }
+    (passed, failed)
+}
+
+/// Run the tests in the given files and directories, printing a report.
+/// Returns whether every test passed.
+#[must_use]
+pub fn run(paths: &[PathBuf]) -> bool {
+    let mut files = Vec::new();
+    for path in paths {
+        if let Err(err) = discover(path, &mut files) {
+            println!("Couldn't search {}: {err}", path.display());
+            return false;
+        }
+    }
+    let (mut passed, mut failed) = (0, 0);
+    for file in &files {
+        let (p, f) = run_file(file);
+        passed += p;
+        failed += f;
+    }
+    println!(
+        "\n{}. {passed} passed; {failed} failed; {} files",
+        if failed == 0 { "ok" } else { "FAILED" },
+        files.len()
+    );
+    failed == 0
+}
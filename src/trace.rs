@@ -0,0 +1,152 @@
+use core::fmt::Write as _;
+use std::{cell::RefCell, fmt::Display, rc::Rc};
+
+use crate::{
+    span::{self, ListTable, Span},
+    types::{List, Symbol, Value},
+};
+
+/// A lambda call that is in progress
+#[derive(Clone)]
+pub struct Frame {
+    /// The name the lambda was defined with, if any
+    pub name: Option<Symbol>,
+    /// The form that made the call
+    pub form: Value,
+    /// Where the call was made
+    pub location: Option<Span>,
+}
+
+impl Frame {
+    /// Represent the frame as a lisp value: `(name "file.lisp:12:7" (call form))`
+    #[must_use]
+    pub fn to_value(&self) -> Value {
+        Value::List(List::from([
+            self.name.map_or_else(Value::nil, Value::Symbol),
+            self.location
+                .as_ref()
+                .map_or_else(Value::nil, |loc| Value::String(loc.to_string())),
+            self.form.clone(),
+        ]))
+    }
+}
+
+impl Display for Frame {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.name {
+            Some(name) => write!(f, "in {name}")?,
+            None => write!(f, "in <lambda>")?,
+        }
+        if let Some(location) = &self.location {
+            write!(f, " at {location}")?;
+        }
+        let form = format!("{:?}", self.form);
+        if form.chars().count() > 60 {
+            write!(f, ": {}...", form.chars().take(57).collect::<String>())
+        } else {
+            write!(f, ": {form}")
+        }
+    }
+}
+
+thread_local! {
+    /// The lambda calls currently being evaluated, outermost first
+    static CALL_STACK: RefCell<Vec<Frame>> = const { RefCell::new(Vec::new()) };
+    /// The call stack at the point each error was raised, innermost first
+    static ERROR_TRACES: RefCell<ListTable<Rc<[Frame]>>> = RefCell::new(ListTable::new());
+}
+
+/// Keeps a frame on the call stack until it is dropped
+pub struct FrameGuard(());
+
+impl FrameGuard {
+    #[must_use]
+    pub fn push(frame: Frame) -> Self {
+        CALL_STACK.with_borrow_mut(|stack| stack.push(frame));
+        Self(())
+    }
+
+    /// Replace this frame, as happens in a tail call
+    pub fn replace(&mut self, frame: Frame) {
+        CALL_STACK.with_borrow_mut(|stack| {
+            if let Some(top) = stack.last_mut() {
+                *top = frame;
+            }
+        });
+    }
+}
+
+impl Drop for FrameGuard {
+    fn drop(&mut self) {
+        CALL_STACK.with_borrow_mut(Vec::pop);
+    }
+}
+
+/// Attach the current call stack to an error, unless it already has a trace
+pub fn capture(err: &Value) {
+    let Value::List(err) = err else {
+        return;
+    };
+    ERROR_TRACES.with_borrow_mut(|traces| {
+        if traces.get(err).is_none() {
+            let frames: Rc<[Frame]> =
+                CALL_STACK.with_borrow(|stack| stack.iter().rev().cloned().collect());
+            traces.insert(err, frames);
+        }
+    });
+}
+
+/// Get the call stack from when an error was raised, innermost first
+#[must_use]
+pub fn of_error(err: &Value) -> Option<Rc<[Frame]>> {
+    let Value::List(err) = err else {
+        return None;
+    };
+    ERROR_TRACES.with_borrow(|traces| traces.get(err).cloned())
+}
+
+/// Get an error's trace as a lisp list of frames
+#[must_use]
+pub fn to_value(err: &Value) -> Value {
+    of_error(err).map_or_else(
+        || Value::List(List::new()),
+        |frames| Value::List(frames.iter().map(Frame::to_value).collect()),
+    )
+}
+
+/// How many of the innermost frames of a deep stack trace to show
+const SHOWN_FRAMES: usize = 20;
+
+/// Format an error as `file.lisp:12:7: ErrorName args...`, followed by its stack trace
+#[must_use]
+pub fn format_error(err: &Value) -> String {
+    let message = err.as_list().map_or_else(
+        || format!("{err:?}"),
+        |parts| {
+            parts
+                .iter()
+                .map(|part| format!("{part:?}"))
+                .collect::<Vec<_>>()
+                .join(" ")
+        },
+    );
+    let mut out = match span::of_error(err) {
+        Some(location) => format!("{location}: {message}"),
+        None => message,
+    };
+    let frames = of_error(err).unwrap_or_else(|| Rc::new([]));
+    for (i, frame) in frames.iter().enumerate() {
+        if frames.len() > SHOWN_FRAMES + 5 && i == SHOWN_FRAMES {
+            write!(
+                out,
+                "\n    ... {} more frames",
+                frames.len() - SHOWN_FRAMES - 5
+            )
+            .unwrap();
+        }
+        if frames.len() <= SHOWN_FRAMES + 5 || i < SHOWN_FRAMES || i >= frames.len() - 5 {
+            write!(out, "\n    {frame}").unwrap();
+        }
+    }
+    out
+}
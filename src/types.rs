@@ -1,13 +1,27 @@
 use std::{
-    collections::HashMap,
     fmt::{Debug, Display},
-    hash::Hash,
+    hash::{DefaultHasher, Hash, Hasher},
     rc::Rc,
     sync::RwLock,
 };
 
+use im_rc::{HashMap, HashSet};
+use num_bigint::BigInt;
+use num_rational::BigRational;
+use regex::Regex;
+
 use crate::{env::Env, eval::eval};
 
+pub mod convert;
+pub mod lazy;
+pub mod list;
+pub mod number;
+pub mod symbol;
+
+pub use list::List;
+pub use symbol::Symbol;
+use symbol::SymbolMap;
+
 pub type DynFn = dyn Fn(Vec<Value>, Env) -> Result<Value, Value>;
 
 #[derive(Clone)]
@@ -17,6 +31,18 @@ pub enum Value {
     ///
     /// Evaluates to itself
     Int(i128),
+    /// An int too large to fit in an `Int`. Arithmetic on ints promotes to this on overflow.
+    ///
+    /// Evaluates to itself
+    BigInt(Rc<BigInt>),
+    /// A float literal or value
+    ///
+    /// Evaluates to itself
+    Float(f64),
+    /// An exact fraction, always in lowest terms with a denominator greater than one
+    ///
+    /// Evaluates to itself
+    Ratio(Rc<BigRational>),
     /// A string literal or value
     ///
     /// Evaluates to itself
@@ -24,15 +50,23 @@ pub enum Value {
     /// A symbol or identifier
     ///
     /// Keywords evaluate to constants or themself.
-    Symbol(String),
+    Symbol(Symbol),
+    /// A keyword like `:name`, stored without its colon. Used as a function, gets its value from a table.
+    ///
+    /// Evaluates to itself
+    Keyword(Symbol),
     /// A list of values
     ///
     /// Attempts to evaluate as a function invocation. Special forms may apply
-    List(Rc<[Value]>),
-    /// A key-value store
+    List(List),
+    /// A persistent key-value store
     ///
     /// Evaluates each key and value
-    Table(Rc<HashMap<Value, Value>>),
+    Table(HashMap<Self, Self>),
+    /// A persistent set of distinct values
+    ///
+    /// Evaluates each value
+    Set(HashSet<Self>),
     /// A builtin function
     ///
     /// Evaluates to itself
@@ -41,24 +75,31 @@ pub enum Value {
     ///
     /// Evaluates to itself
     Lambda {
-        args: Box<Value>,
-        body: Box<Value>,
+        args: Box<Self>,
+        body: Box<Self>,
         captures: Env,
         is_macro: bool,
+        /// The name it was first defined with, used in stack traces
+        name: Option<Symbol>,
     },
     /// A shared mutable reference to a value. When used as a function with no arguments, returns the inner value.
     ///
     /// Evaluates to itself
-    Atom(Rc<RwLock<Value>>),
+    Atom(Rc<RwLock<Self>>),
+    /// A sequence whose values are computed when they are first needed, and may never end
+    ///
+    /// Evaluates to itself
+    Lazy(Rc<lazy::LazySeq>),
+    /// A compiled regular expression. Flags are part of its pattern.
+    ///
+    /// Evaluates to itself
+    Regex(Rc<Regex>),
 }
 
 impl Value {
     #[must_use]
-    pub fn is_symbol(&self, id: &str) -> bool {
-        let Self::Symbol(my_id) = self else {
-            return false;
-        };
-        my_id == id
+    pub fn is_symbol(&self, id: Symbol) -> bool {
+        matches!(self, Self::Symbol(my_id) if *my_id == id)
     }
 
     #[must_use]
@@ -68,46 +109,103 @@ impl Value {
         Self::List(args.into())
     }
 
+    /// Fill in a quasiquoted template, evaluating the parts of it that are unquoted in `env`.
+    ///
+    /// `~@form` evaluates a form to a list and splices its values into the list around it. A quasiquote in
+    /// the template starts a template of its own, whose unquoted parts are left for it to fill in, unless
+    /// they are unquoted once more for each quasiquote they are nested in.
+    ///
+    /// Symbols in the template ending in `#`, like `tmp#`, are replaced with symbols made by
+    /// [`Symbol::gensym`], the same one for each use of a name, so code a macro makes can bind names that
+    /// won't capture the names in the code it is given.
     #[allow(clippy::missing_errors_doc)]
-    pub fn quasiquote(&self, env: Env) -> Result<Self, Self> {
+    pub fn quasiquote(&self, env: &Env) -> Result<Self, Self> {
+        if self.is_form(Symbol::SPLICE_UNQUOTE) {
+            return Err(Self::error(
+                "InvalidArgs@splice-unquote",
+                vec![self.clone()],
+            ));
+        }
+        self.fill(env, 0, &mut SymbolMap::default())
+    }
+
+    /// Whether this is a list starting with a symbol, like `(unquote x)`
+    fn is_form(&self, head: Symbol) -> bool {
+        matches!(self, Self::List(list) if list.first().is_some_and(|first| first.is_symbol(head)))
+    }
+
+    /// The value in a `(quasiquote x)`, `(unquote x)` or `(splice-unquote x)`, checking that there is exactly one
+    fn quoted(&self, head: Symbol) -> Result<&Self, Self> {
         match self {
-            other @ (Self::Int(_)
-            | Self::String(_)
-            | Self::Symbol(_)
-            | Self::Function { .. }
-            | Self::Table(_)
-            | Self::Lambda { .. }
-            | Self::Atom(..)) => Ok(other.clone()),
-            Self::List(vec) => {
-                if vec.first().is_some_and(|val| val.is_symbol("unquote")) {
-                    eval(vec[1].clone(), env)
+            Self::List(list) if list.len() == 2 => list.get(1),
+            _ => None,
+        }
+        .ok_or_else(|| Self::error(&format!("InvalidArgs@{head}"), vec![self.clone()]))
+    }
+
+    /// Fill in a template nested `depth` quasiquotes deep in the one being filled in
+    fn fill(&self, env: &Env, depth: usize, gensyms: &mut SymbolMap<Symbol>) -> Result<Self, Self> {
+        match self {
+            Self::Symbol(name) if depth == 0 => match name.name().strip_suffix('#') {
+                Some(prefix) if !prefix.is_empty() => Ok(Self::Symbol(
+                    *gensyms
+                        .entry(*name)
+                        .or_insert_with(|| Symbol::gensym(prefix)),
+                )),
+                _ => Ok(self.clone()),
+            },
+            Self::List(_) if depth == 0 && self.is_form(Symbol::UNQUOTE) => {
+                eval(self.quoted(Symbol::UNQUOTE)?.clone(), env.clone())
+            }
+            Self::List(list) => {
+                // what is inside a nested quasiquote or unquote is a level deeper or shallower
+                let (head, depth) = if self.is_form(Symbol::QUASIQUOTE) {
+                    (1, depth + 1)
+                } else if self.is_form(Symbol::UNQUOTE) || self.is_form(Symbol::SPLICE_UNQUOTE) {
+                    (1, depth - 1)
                 } else {
-                    Ok(Self::List(
-                        vec.iter()
-                            .map(|v| Self::quasiquote(v, env.clone()))
-                            .collect::<Result<_, _>>()?,
-                    ))
+                    (0, depth)
+                };
+                let mut filled: Vec<Self> = list.iter().take(head).cloned().collect();
+                for value in list.iter().skip(head) {
+                    if depth > 0 || !value.is_form(Symbol::SPLICE_UNQUOTE) {
+                        filled.push(value.fill(env, depth, gensyms)?);
+                        continue;
+                    }
+                    match eval(value.quoted(Symbol::SPLICE_UNQUOTE)?.clone(), env.clone())? {
+                        Self::List(values) => filled.extend(values.iter().cloned()),
+                        other => {
+                            return Err(Self::error("InvalidArgs@splice-unquote", vec![other]))
+                        }
+                    }
                 }
+                Ok(Self::List(filled.into()))
             }
+            other => Ok(other.clone()),
         }
     }
 
     #[must_use]
     pub fn is_truthy(&self) -> bool {
         match self {
-            Self::Symbol(i) => i != "false" && i != "nil",
+            Self::Symbol(i) => *i != Symbol::FALSE && *i != Symbol::NIL,
             _ => true,
         }
     }
 
     #[must_use]
-    pub fn nil() -> Self {
-        Self::symbol("nil")
+    pub const fn nil() -> Self {
+        Self::Symbol(Symbol::NIL)
     }
 
     #[must_use]
     pub fn symbol(sym: &str) -> Self {
-        Self::Symbol(sym.to_string())
+        Self::Symbol(Symbol::intern(sym))
+    }
+
+    #[must_use]
+    pub fn keyword(name: &str) -> Self {
+        Self::Keyword(Symbol::intern(name))
     }
 
     pub fn function(func: Rc<DynFn>) -> Self {
@@ -118,7 +216,7 @@ impl Value {
     }
 
     #[must_use]
-    pub fn as_list(&self) -> Option<&[Self]> {
+    pub const fn as_list(&self) -> Option<&List> {
         match self {
             Self::List(l) => Some(l),
             _ => None,
@@ -135,7 +233,12 @@ impl Display for Value {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Self::Int(arg0) => write!(f, "{arg0}"),
-            Self::String(arg0) | Self::Symbol(arg0) => write!(f, "{arg0}"),
+            Self::BigInt(arg0) => write!(f, "{arg0}"),
+            Self::Float(arg0) => write!(f, "{arg0:?}"),
+            Self::Ratio(arg0) => write!(f, "{arg0}"),
+            Self::String(arg0) => write!(f, "{arg0}"),
+            Self::Symbol(arg0) => write!(f, "{arg0}"),
+            Self::Keyword(arg0) => write!(f, ":{arg0}"),
             Self::List(arg0) => {
                 write!(f, "(")?;
                 for (i, v) in arg0.iter().enumerate() {
@@ -158,8 +261,21 @@ impl Display for Value {
                 }
                 write!(f, "}}")
             }
+            Self::Set(s) => {
+                write!(f, "#{{")?;
+                for (i, v) in s.iter().enumerate() {
+                    if i == 0 {
+                        write!(f, "{v:?}")?;
+                    } else {
+                        write!(f, " {v:?}")?;
+                    }
+                }
+                write!(f, "}}")
+            }
             Self::Function { .. } | Self::Lambda { .. } => write!(f, "#<function>"),
             Self::Atom(a) => write!(f, "#<atom:{:?}>", a.read().unwrap()),
+            Self::Lazy(_) => write!(f, "#<lazy-seq>"),
+            Self::Regex(re) => write!(f, "#<regex:{:?}>", re.as_str()),
         }
     }
 }
@@ -168,8 +284,13 @@ impl Debug for Value {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Self::Int(arg0) => write!(f, "{arg0}"),
+            Self::BigInt(arg0) => write!(f, "{arg0}"),
+            Self::Float(arg0) => write!(f, "{arg0:?}"),
+            Self::Ratio(arg0) => write!(f, "{arg0}"),
+            // the parser understands every escape Rust uses, so this reads back as the same string
             Self::String(arg0) => write!(f, "{arg0:?}"),
             Self::Symbol(arg0) => write!(f, "{arg0}"),
+            Self::Keyword(arg0) => write!(f, ":{arg0}"),
             Self::List(arg0) => {
                 write!(f, "(")?;
                 for (i, v) in arg0.iter().enumerate() {
@@ -192,8 +313,21 @@ impl Debug for Value {
                 }
                 write!(f, "}}")
             }
+            Self::Set(s) => {
+                write!(f, "#{{")?;
+                for (i, v) in s.iter().enumerate() {
+                    if i == 0 {
+                        write!(f, "{v:?}")?;
+                    } else {
+                        write!(f, ", {v:?}")?;
+                    }
+                }
+                write!(f, "}}")
+            }
             Self::Function { .. } | Self::Lambda { .. } => write!(f, "#<function>"),
             Self::Atom(a) => write!(f, "#<atom:{:?}>", a.read().unwrap()),
+            Self::Lazy(_) => write!(f, "#<lazy-seq>"),
+            Self::Regex(re) => write!(f, "#<regex:{:?}>", re.as_str()),
         }
     }
 }
@@ -202,7 +336,11 @@ impl PartialEq for Value {
     fn eq(&self, other: &Self) -> bool {
         match (self, other) {
             (Self::Int(a), Self::Int(b)) => *a == *b,
-            (Self::String(a), Self::String(b)) | (Self::Symbol(a), Self::Symbol(b)) => a == b,
+            (Self::BigInt(a), Self::BigInt(b)) => a == b,
+            (Self::Float(a), Self::Float(b)) => a.to_bits() == b.to_bits(),
+            (Self::Ratio(a), Self::Ratio(b)) => a == b,
+            (Self::String(a), Self::String(b)) => a == b,
+            (Self::Symbol(a), Self::Symbol(b)) | (Self::Keyword(a), Self::Keyword(b)) => a == b,
             (Self::List(a), Self::List(b)) => a == b,
             (
                 Self::Function {
@@ -220,15 +358,21 @@ impl PartialEq for Value {
                     body: a_b,
                     captures: _,
                     is_macro: a_c,
+                    name: _,
                 },
                 Self::Lambda {
                     args: b_a,
                     body: b_b,
                     captures: _,
                     is_macro: b_c,
+                    name: _,
                 },
             ) => **a_a == **b_a && **a_b == **b_b && *a_c == *b_c,
             (Self::Table(a), Self::Table(b)) => a == b,
+            (Self::Set(a), Self::Set(b)) => a == b,
+            (Self::Regex(a), Self::Regex(b)) => a.as_str() == b.as_str(),
+            // lazy sequences may never end, so they are only equal to themselves
+            (Self::Lazy(a), Self::Lazy(b)) => Rc::ptr_eq(a, b),
             _ => false,
         }
     }
@@ -236,6 +380,18 @@ impl PartialEq for Value {
 
 impl Eq for Value {}
 
+/// Sum the hashes of a collection's items, so that equal collections hash the same whatever order they
+/// iterate in
+fn unordered_hash(items: impl Iterator<Item = impl Hash>) -> u64 {
+    items
+        .map(|item| {
+            let mut hasher = DefaultHasher::new();
+            item.hash(&mut hasher);
+            hasher.finish()
+        })
+        .fold(0, u64::wrapping_add)
+}
+
 impl Hash for Value {
     fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
         core::mem::discriminant(self).hash(state);
@@ -247,11 +403,21 @@ impl Hash for Value {
             Self::Int(i) => {
                 i.hash(state);
             }
+            Self::BigInt(i) => {
+                i.hash(state);
+            }
+            Self::Float(f) => {
+                f.to_bits().hash(state);
+            }
+            Self::Ratio(r) => {
+                r.hash(state);
+            }
             Self::Lambda {
                 args,
                 body,
                 captures: _,
                 is_macro,
+                name: _,
             } => {
                 args.hash(state);
                 body.hash(state);
@@ -260,19 +426,30 @@ impl Hash for Value {
             Self::List(l) => {
                 l.hash(state);
             }
-            Self::String(s) | Self::Symbol(s) => {
+            Self::String(s) => {
+                s.hash(state);
+            }
+            Self::Symbol(s) | Self::Keyword(s) => {
                 s.hash(state);
             }
             Self::Table(t) => {
-                for (k, v) in &**t {
-                    k.hash(state);
-                    v.hash(state);
-                }
+                t.len().hash(state);
+                unordered_hash(t.iter()).hash(state);
+            }
+            Self::Set(s) => {
+                s.len().hash(state);
+                unordered_hash(s.iter()).hash(state);
             }
             Self::Atom(a) => {
                 // hash an Atom on its memory address
                 core::ptr::from_ref(&**a).hash(state);
             }
+            Self::Regex(re) => {
+                re.as_str().hash(state);
+            }
+            Self::Lazy(seq) => {
+                Rc::as_ptr(seq).hash(state);
+            }
         }
     }
 }
@@ -0,0 +1,256 @@
+use std::{
+    collections::{HashMap, HashSet},
+    hash::{BuildHasher, Hash},
+    rc::Rc,
+};
+
+use num_bigint::BigInt;
+
+use super::{number::Number, Symbol, Value};
+
+/// Convert a Rust value into a lisp value
+pub trait IntoValue {
+    fn into_value(self) -> Value;
+}
+
+/// Convert a lisp value into a Rust value
+pub trait FromValue: Sized {
+    /// # Errors
+    /// `(TypeError expected value)` if the value is the wrong type, or `(OutOfRange expected value)`
+    /// if it is a number that doesn't fit
+    fn from_value(value: Value) -> Result<Self, Value>;
+}
+
+fn type_error(expected: &str, value: Value) -> Value {
+    Value::error("TypeError", vec![Value::symbol(expected), value])
+}
+
+impl IntoValue for Value {
+    fn into_value(self) -> Value {
+        self
+    }
+}
+
+impl FromValue for Value {
+    fn from_value(value: Value) -> Result<Self, Value> {
+        Ok(value)
+    }
+}
+
+macro_rules! int_conversions {
+    ($($int:ty),*) => {
+        $(
+            impl IntoValue for $int {
+                fn into_value(self) -> Value {
+                    i128::try_from(self)
+                        .map_or_else(|_| Value::BigInt(Rc::new(BigInt::from(self))), Value::Int)
+                }
+            }
+
+            impl FromValue for $int {
+                fn from_value(value: Value) -> Result<Self, Value> {
+                    let converted = match &value {
+                        Value::Int(i) => Self::try_from(*i).ok(),
+                        Value::BigInt(i) => Self::try_from(&**i).ok(),
+                        _ => return Err(type_error("int", value)),
+                    };
+                    converted.ok_or_else(|| {
+                        Value::error("OutOfRange", vec![Value::symbol(stringify!($int)), value])
+                    })
+                }
+            }
+        )*
+    };
+}
+
+int_conversions!(i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize);
+
+impl IntoValue for BigInt {
+    fn into_value(self) -> Value {
+        Number::BigInt(self).into_value()
+    }
+}
+
+impl FromValue for BigInt {
+    fn from_value(value: Value) -> Result<Self, Value> {
+        match value {
+            Value::Int(i) => Ok(Self::from(i)),
+            Value::BigInt(i) => Ok((*i).clone()),
+            other => Err(type_error("int", other)),
+        }
+    }
+}
+
+impl IntoValue for f64 {
+    fn into_value(self) -> Value {
+        Value::Float(self)
+    }
+}
+
+/// Any number converts to a float
+impl FromValue for f64 {
+    fn from_value(value: Value) -> Result<Self, Value> {
+        Number::from_value(&value)
+            .map(|n| n.to_f64())
+            .ok_or_else(|| type_error("float", value))
+    }
+}
+
+impl IntoValue for bool {
+    fn into_value(self) -> Value {
+        Value::symbol(if self { "true" } else { "false" })
+    }
+}
+
+/// Uses the lisp's truthiness, so every value converts to a bool
+impl FromValue for bool {
+    fn from_value(value: Value) -> Result<Self, Value> {
+        Ok(value.is_truthy())
+    }
+}
+
+impl IntoValue for String {
+    fn into_value(self) -> Value {
+        Value::String(self)
+    }
+}
+
+impl IntoValue for &str {
+    fn into_value(self) -> Value {
+        Value::String(self.to_string())
+    }
+}
+
+impl FromValue for String {
+    fn from_value(value: Value) -> Result<Self, Value> {
+        match value {
+            Value::String(s) => Ok(s),
+            other => Err(type_error("string", other)),
+        }
+    }
+}
+
+impl IntoValue for () {
+    fn into_value(self) -> Value {
+        Value::nil()
+    }
+}
+
+/// Accepts any value, so that the result of a function called for its side effects can be ignored
+impl FromValue for () {
+    fn from_value(_value: Value) -> Result<Self, Value> {
+        Ok(())
+    }
+}
+
+/// `None` is nil
+impl<T: IntoValue> IntoValue for Option<T> {
+    fn into_value(self) -> Value {
+        self.map_or_else(Value::nil, IntoValue::into_value)
+    }
+}
+
+/// nil is `None`
+impl<T: FromValue> FromValue for Option<T> {
+    fn from_value(value: Value) -> Result<Self, Value> {
+        if value.is_symbol(Symbol::NIL) {
+            Ok(None)
+        } else {
+            T::from_value(value).map(Some)
+        }
+    }
+}
+
+impl<T: IntoValue> IntoValue for Vec<T> {
+    fn into_value(self) -> Value {
+        Value::List(self.into_iter().map(IntoValue::into_value).collect())
+    }
+}
+
+impl<T: FromValue> FromValue for Vec<T> {
+    fn from_value(value: Value) -> Result<Self, Value> {
+        match value {
+            Value::List(l) => l.iter().cloned().map(T::from_value).collect(),
+            other => Err(type_error("list", other)),
+        }
+    }
+}
+
+impl<K: IntoValue, V: IntoValue, S> IntoValue for HashMap<K, V, S> {
+    fn into_value(self) -> Value {
+        Value::Table(
+            self.into_iter()
+                .map(|(k, v)| (k.into_value(), v.into_value()))
+                .collect(),
+        )
+    }
+}
+
+impl<K: FromValue + Eq + Hash, V: FromValue, S: BuildHasher + Default> FromValue
+    for HashMap<K, V, S>
+{
+    fn from_value(value: Value) -> Result<Self, Value> {
+        match value {
+            Value::Table(t) => t
+                .iter()
+                .map(|(k, v)| Ok((K::from_value(k.clone())?, V::from_value(v.clone())?)))
+                .collect(),
+            other => Err(type_error("table", other)),
+        }
+    }
+}
+
+impl<T: IntoValue, S> IntoValue for HashSet<T, S> {
+    fn into_value(self) -> Value {
+        Value::Set(self.into_iter().map(IntoValue::into_value).collect())
+    }
+}
+
+impl<T: FromValue + Eq + Hash, S: BuildHasher + Default> FromValue for HashSet<T, S> {
+    fn from_value(value: Value) -> Result<Self, Value> {
+        match value {
+            Value::Set(s) => s.iter().cloned().map(T::from_value).collect(),
+            other => Err(type_error("set", other)),
+        }
+    }
+}
+
+/// Arguments to a lisp function, converted from Rust values
+pub trait IntoArgs {
+    fn into_args(self) -> Vec<Value>;
+}
+
+impl<T: IntoValue> IntoArgs for Vec<T> {
+    fn into_args(self) -> Vec<Value> {
+        self.into_iter().map(IntoValue::into_value).collect()
+    }
+}
+
+macro_rules! tuple_args {
+    ($(($($arg:ident),*)),*) => {
+        $(
+            impl<$($arg: IntoValue),*> IntoArgs for ($($arg,)*) {
+                #[allow(non_snake_case)]
+                fn into_args(self) -> Vec<Value> {
+                    let ($($arg,)*) = self;
+                    vec![$($arg.into_value()),*]
+                }
+            }
+        )*
+    };
+}
+
+impl IntoArgs for () {
+    fn into_args(self) -> Vec<Value> {
+        Vec::new()
+    }
+}
+
+tuple_args!(
+    (A),
+    (A, B),
+    (A, B, C),
+    (A, B, C, D),
+    (A, B, C, D, E),
+    (A, B, C, D, E, F)
+);
@@ -0,0 +1,305 @@
+use core::cell::RefCell;
+use std::{collections::HashSet, rc::Rc};
+
+use crate::{
+    env::Env,
+    eval::{builtins, eval},
+    gc,
+};
+
+use super::{number::Number, List, Symbol, Value};
+
+/// A sequence whose values are computed as they are needed. Each step is computed at most once; later uses
+/// of the sequence see the cached step.
+pub struct LazySeq {
+    state: RefCell<State>,
+}
+
+enum State {
+    Unrealized(Thunk),
+    /// A step is being computed, so asking for it again means the sequence depends on itself
+    Realizing,
+    /// The first value and the rest of the sequence, or `None` if the sequence is empty
+    Realized(Option<(Value, Value)>),
+}
+
+/// How to compute the next step of a lazy sequence
+#[derive(Clone)]
+pub enum Thunk {
+    /// Evaluate a body to a sequence, as `lazy-seq` does
+    Body { body: Value, env: Env },
+    /// The results of applying a function over and over to a value, after the value itself
+    Iterate { func: Value, prev: Value, env: Env },
+    /// The values of a list from an index, then the list again from the start, forever
+    Cycle { items: List, index: usize },
+    /// Numbers from a start, counting by a step, without end
+    Range { start: Number, step: Number },
+    /// A function applied to each value of another sequence
+    Map {
+        func: Value,
+        source: Value,
+        env: Env,
+    },
+    /// The values of another sequence that a function returns a truthy value for
+    Filter {
+        pred: Value,
+        source: Value,
+        env: Env,
+    },
+}
+
+impl LazySeq {
+    #[must_use]
+    pub fn from_thunk(thunk: Thunk) -> Value {
+        // only a sequence that evaluates code can come to refer to itself
+        let evaluates = !matches!(thunk, Thunk::Cycle { .. } | Thunk::Range { .. });
+        let seq = Rc::new(Self::new(State::Unrealized(thunk)));
+        if evaluates {
+            gc::track_lazy(&seq);
+        }
+        Value::Lazy(seq)
+    }
+
+    fn new(state: State) -> Self {
+        gc::allocated(&gc::Kind::Lazy);
+        Self {
+            state: RefCell::new(state),
+        }
+    }
+
+    /// A sequence whose first step is already known
+    #[must_use]
+    pub fn cons(first: Value, rest: Value) -> Value {
+        Value::Lazy(Rc::new(Self::new(State::Realized(Some((first, rest))))))
+    }
+
+    /// Get the first value and the rest of the sequence, computing them if they haven't been yet
+    /// # Errors
+    /// If computing the step raises an error, or the step depends on itself
+    pub fn step(&self) -> Result<Option<(Value, Value)>, Value> {
+        let thunk = match &*self.state.borrow() {
+            State::Realized(step) => return Ok(step.clone()),
+            State::Realizing => return Err(Value::error("CircularLazySeq", Vec::new())),
+            State::Unrealized(thunk) => thunk.clone(),
+        };
+        self.state.replace(State::Realizing);
+        match thunk.clone().realize() {
+            Ok(step) => {
+                self.state.replace(State::Realized(step.clone()));
+                Ok(step)
+            }
+            Err(err) => {
+                // leave the thunk to be tried again
+                self.state.replace(State::Unrealized(thunk));
+                Err(err)
+            }
+        }
+    }
+
+    /// Whether the first step has been computed
+    #[must_use]
+    pub fn is_realized(&self) -> bool {
+        matches!(&*self.state.borrow(), State::Realized(_))
+    }
+
+    /// Add the values and environments the sequence holds on to. Returns false if its step is being
+    /// computed, when what it holds can't be known.
+    pub(crate) fn held(&self, values: &mut Vec<Value>, envs: &mut Vec<Env>) -> bool {
+        let Ok(state) = self.state.try_borrow() else {
+            return false;
+        };
+        match &*state {
+            State::Realizing => return false,
+            State::Unrealized(thunk) => thunk.clone().into_parts(values, envs),
+            State::Realized(Some((first, rest))) => values.extend([first.clone(), rest.clone()]),
+            State::Realized(None) => {}
+        }
+        true
+    }
+
+    /// Forget the sequence's state, to break a cycle it is part of. What it held is moved into `values` and
+    /// `envs`, so it can be dropped once every cycle is broken.
+    pub(crate) fn clear(&self, values: &mut Vec<Value>, envs: &mut Vec<Env>) {
+        let Ok(mut state) = self.state.try_borrow_mut() else {
+            return;
+        };
+        match core::mem::replace(&mut *state, State::Realized(None)) {
+            State::Unrealized(thunk) => thunk.into_parts(values, envs),
+            State::Realized(Some(step)) => values.extend(<[Value; 2]>::from(step)),
+            State::Realized(None) | State::Realizing => {}
+        }
+    }
+}
+
+impl Thunk {
+    fn is_infinite(&self) -> bool {
+        match self {
+            Self::Range { .. } | Self::Iterate { .. } => true,
+            Self::Cycle { items, .. } => !items.is_empty(),
+            Self::Map { source, .. } | Self::Filter { source, .. } => is_infinite(source),
+            Self::Body { .. } => false,
+        }
+    }
+
+    fn into_parts(self, values: &mut Vec<Value>, envs: &mut Vec<Env>) {
+        match self {
+            Self::Body { body, env } => {
+                values.push(body);
+                envs.push(env);
+            }
+            Self::Iterate { func, prev, env } => {
+                values.extend([func, prev]);
+                envs.push(env);
+            }
+            Self::Cycle { items, .. } => values.push(Value::List(items)),
+            Self::Range { .. } => {}
+            Self::Map { func, source, env } => {
+                values.extend([func, source]);
+                envs.push(env);
+            }
+            Self::Filter { pred, source, env } => {
+                values.extend([pred, source]);
+                envs.push(env);
+            }
+        }
+    }
+
+    fn realize(self) -> Result<Option<(Value, Value)>, Value> {
+        match self {
+            Self::Body { body, env } => step(&eval(body, env)?),
+            Self::Iterate { func, prev, env } => {
+                let next = builtins::apply(
+                    vec![func.clone(), Value::List(List::from([prev]))],
+                    env.clone(),
+                )?;
+                let rest = LazySeq::from_thunk(Self::Iterate {
+                    func,
+                    prev: next.clone(),
+                    env,
+                });
+                Ok(Some((next, rest)))
+            }
+            Self::Cycle { items, index } => {
+                let Some(first) = items.get(index).cloned() else {
+                    return Ok(None);
+                };
+                let index = (index + 1) % items.len();
+                Ok(Some((
+                    first,
+                    LazySeq::from_thunk(Self::Cycle { items, index }),
+                )))
+            }
+            Self::Range { start, step } => {
+                let next = start.clone() + step.clone();
+                Ok(Some((
+                    start.into_value(),
+                    LazySeq::from_thunk(Self::Range { start: next, step }),
+                )))
+            }
+            Self::Map { func, source, env } => {
+                let Some((first, rest)) = step(&source)? else {
+                    return Ok(None);
+                };
+                let first = builtins::apply(
+                    vec![func.clone(), Value::List(List::from([first]))],
+                    env.clone(),
+                )?;
+                let rest = LazySeq::from_thunk(Self::Map {
+                    func,
+                    source: rest,
+                    env,
+                });
+                Ok(Some((first, rest)))
+            }
+            Self::Filter { pred, source, env } => {
+                let mut source = source;
+                while let Some((first, rest)) = step(&source)? {
+                    let keep = builtins::apply(
+                        vec![pred.clone(), Value::List(List::from([first.clone()]))],
+                        env.clone(),
+                    )?;
+                    if keep.is_truthy() {
+                        let rest = LazySeq::from_thunk(Self::Filter {
+                            pred,
+                            source: rest,
+                            env,
+                        });
+                        return Ok(Some((first, rest)));
+                    }
+                    source = rest;
+                }
+                Ok(None)
+            }
+        }
+    }
+}
+
+/// Split a sequence into its first value and the rest of it, or `None` if it is empty. Lists, strings, lazy
+/// sequences and nil are sequences.
+/// # Errors
+/// `(NotASequence value)` if the value isn't a sequence, or the error raised computing a lazy step
+pub fn step(seq: &Value) -> Result<Option<(Value, Value)>, Value> {
+    match seq {
+        Value::List(l) => Ok(l
+            .first()
+            .map(|first| (first.clone(), Value::List(l.rest())))),
+        Value::String(s) => {
+            let mut chars = s.chars();
+            Ok(chars.next().map(|c| {
+                (
+                    Value::String(c.to_string()),
+                    Value::String(chars.as_str().to_string()),
+                )
+            }))
+        }
+        Value::Lazy(lazy) => lazy.step(),
+        nil if nil.is_symbol(Symbol::NIL) => Ok(None),
+        other => Err(Value::error("NotASequence", vec![other.clone()])),
+    }
+}
+
+/// Whether a sequence is known to never end.
+///
+/// It counts, cycles or iterates forever, maps or filters a sequence that does, or its realized steps lead
+/// back to themselves. A sequence that evaluates code to compute its steps may never end without this
+/// knowing.
+#[must_use]
+pub fn is_infinite(seq: &Value) -> bool {
+    let mut seen = HashSet::new();
+    let mut seq = seq.clone();
+    loop {
+        let Value::Lazy(lazy) = &seq else {
+            return false;
+        };
+        if !seen.insert(Rc::as_ptr(lazy)) {
+            return true;
+        }
+        let rest = match &*lazy.state.borrow() {
+            State::Realized(Some((_, rest))) => rest.clone(),
+            State::Realized(None) | State::Realizing => return false,
+            State::Unrealized(thunk) => return thunk.is_infinite(),
+        };
+        seq = rest;
+    }
+}
+
+/// Take the rest of a realized step out of a state, so it can be dropped without recursing
+fn take_rest(state: &mut State) -> Option<Value> {
+    match state {
+        State::Realized(Some((_, rest))) => Some(core::mem::take(rest)),
+        _ => None,
+    }
+}
+
+/// Drop a long realized chain one link at a time, rather than recursing once per value
+impl Drop for LazySeq {
+    fn drop(&mut self) {
+        gc::freed(&gc::Kind::Lazy);
+        let mut next = take_rest(self.state.get_mut());
+        while let Some(Value::Lazy(seq)) = next {
+            next = Rc::try_unwrap(seq)
+                .ok()
+                .and_then(|mut seq| take_rest(seq.state.get_mut()));
+        }
+    }
+}
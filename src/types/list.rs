@@ -0,0 +1,309 @@
+use core::{
+    hash::{Hash, Hasher},
+    iter::FusedIterator,
+};
+use std::rc::{Rc, Weak};
+
+use im_rc::Vector;
+
+use super::Value;
+
+/// A persistent list of values.
+///
+/// Lists read by the parser or collected from an iterator are stored flat. Adding to or taking from a list
+/// gives a vector that shares structure with the original, so `cons`, `conj` and `rest` take O(log n) time
+/// instead of copying the list. Reading a list with [`List::get`] and [`List::iter`] keeps those costs;
+/// [`List::values`] copies a shared list into a slice each time it is called.
+#[derive(Clone)]
+pub struct List(Repr);
+
+#[derive(Clone)]
+enum Repr {
+    Flat(Rc<[Value]>),
+    Shared(Rc<Vector<Value>>),
+}
+
+impl List {
+    #[must_use]
+    pub fn new() -> Self {
+        Self(Repr::Flat(Rc::new([])))
+    }
+
+    fn shared(vector: Vector<Value>) -> Self {
+        Self(Repr::Shared(Rc::new(vector)))
+    }
+
+    /// The list as a vector, which is only a copy if the list is flat
+    fn vector(&self) -> Vector<Value> {
+        match &self.0 {
+            Repr::Flat(values) => values.iter().cloned().collect(),
+            Repr::Shared(vector) => (**vector).clone(),
+        }
+    }
+
+    #[must_use]
+    pub fn len(&self) -> usize {
+        match &self.0 {
+            Repr::Flat(values) => values.len(),
+            Repr::Shared(vector) => vector.len(),
+        }
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    #[must_use]
+    pub fn get(&self, i: usize) -> Option<&Value> {
+        match &self.0 {
+            Repr::Flat(values) => values.get(i),
+            Repr::Shared(vector) => vector.get(i),
+        }
+    }
+
+    #[must_use]
+    pub fn first(&self) -> Option<&Value> {
+        self.get(0)
+    }
+
+    #[must_use]
+    pub fn last(&self) -> Option<&Value> {
+        self.get(self.len().checked_sub(1)?)
+    }
+
+    #[must_use]
+    pub fn iter(&self) -> Iter<'_> {
+        match &self.0 {
+            Repr::Flat(values) => Iter::Flat(values.iter()),
+            Repr::Shared(vector) => Iter::Shared(vector.iter()),
+        }
+    }
+
+    /// The values as a slice, which is the storage of a flat list and a copy of a shared one
+    #[must_use]
+    pub fn values(&self) -> Rc<[Value]> {
+        match &self.0 {
+            Repr::Flat(values) => Rc::clone(values),
+            Repr::Shared(vector) => vector.iter().cloned().collect(),
+        }
+    }
+
+    /// The values copied into a vector
+    #[must_use]
+    pub fn to_vec(&self) -> Vec<Value> {
+        self.iter().cloned().collect()
+    }
+
+    /// Whether the list has a value equal to the given one
+    #[must_use]
+    pub fn contains(&self, value: &Value) -> bool {
+        self.iter().any(|v| v == value)
+    }
+
+    /// A list with a value added to the front
+    #[must_use]
+    pub fn cons(&self, value: Value) -> Self {
+        let mut vector = self.vector();
+        vector.push_front(value);
+        Self::shared(vector)
+    }
+
+    /// A list with a value added to the end
+    #[must_use]
+    pub fn conj(&self, value: Value) -> Self {
+        let mut vector = self.vector();
+        vector.push_back(value);
+        Self::shared(vector)
+    }
+
+    /// The list without its first `n` values
+    #[must_use]
+    pub fn skip(&self, n: usize) -> Self {
+        if n >= self.len() {
+            return Self::new();
+        }
+        Self::shared(self.vector().skip(n))
+    }
+
+    /// The first `n` values of the list
+    #[must_use]
+    pub fn take(&self, n: usize) -> Self {
+        if n >= self.len() {
+            return self.clone();
+        }
+        Self::shared(self.vector().take(n))
+    }
+
+    /// The list without its first value
+    #[must_use]
+    pub fn rest(&self) -> Self {
+        self.skip(1)
+    }
+
+    /// This list followed by another
+    #[must_use]
+    pub fn concat(&self, other: &Self) -> Self {
+        if other.is_empty() {
+            return self.clone();
+        }
+        if self.is_empty() {
+            return other.clone();
+        }
+        // add the shorter list to the longer one, one value at a time
+        if self.len() < other.len() {
+            let mut vector = other.vector();
+            for value in self.iter().rev() {
+                vector.push_front(value.clone());
+            }
+            Self::shared(vector)
+        } else {
+            let mut vector = self.vector();
+            vector.extend(other.iter().cloned());
+            Self::shared(vector)
+        }
+    }
+
+    /// The storage of a flat list. A flat list's values aren't shared with any other list.
+    pub(crate) const fn flat(&self) -> Option<&Rc<[Value]>> {
+        match &self.0 {
+            Repr::Flat(values) => Some(values),
+            Repr::Shared(_) => None,
+        }
+    }
+
+    /// The address of the list's storage, which identifies it as long as it is alive
+    pub(crate) fn as_ptr(&self) -> *const () {
+        match &self.0 {
+            Repr::Flat(values) => Rc::as_ptr(values).cast(),
+            Repr::Shared(shared) => Rc::as_ptr(shared).cast(),
+        }
+    }
+
+    /// A reference to the list's storage that doesn't keep it alive
+    pub(crate) fn downgrade(&self) -> WeakList {
+        WeakList(match &self.0 {
+            Repr::Flat(values) => WeakRepr::Flat(Rc::downgrade(values)),
+            Repr::Shared(shared) => WeakRepr::Shared(Rc::downgrade(shared)),
+        })
+    }
+}
+
+/// A weak reference to a list's storage
+pub(crate) struct WeakList(WeakRepr);
+
+enum WeakRepr {
+    Flat(Weak<[Value]>),
+    Shared(Weak<Vector<Value>>),
+}
+
+impl WeakList {
+    /// Whether the list still exists
+    pub(crate) fn is_alive(&self) -> bool {
+        match &self.0 {
+            WeakRepr::Flat(values) => values.strong_count() > 0,
+            WeakRepr::Shared(shared) => shared.strong_count() > 0,
+        }
+    }
+}
+
+impl Default for List {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PartialEq for List {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_ptr() == other.as_ptr()
+            || (self.len() == other.len() && self.iter().eq(other.iter()))
+    }
+}
+
+impl Eq for List {}
+
+/// Hashes the values in order, so that a flat list and a shared list with the same values hash the same
+impl Hash for List {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.len().hash(state);
+        for value in self {
+            value.hash(state);
+        }
+    }
+}
+
+impl From<Vec<Value>> for List {
+    fn from(values: Vec<Value>) -> Self {
+        Self(Repr::Flat(values.into()))
+    }
+}
+
+impl From<Rc<[Value]>> for List {
+    fn from(values: Rc<[Value]>) -> Self {
+        Self(Repr::Flat(values))
+    }
+}
+
+impl From<&[Value]> for List {
+    fn from(values: &[Value]) -> Self {
+        Self(Repr::Flat(values.into()))
+    }
+}
+
+impl<const N: usize> From<[Value; N]> for List {
+    fn from(values: [Value; N]) -> Self {
+        Self(Repr::Flat(Rc::new(values)))
+    }
+}
+
+impl FromIterator<Value> for List {
+    fn from_iter<I: IntoIterator<Item = Value>>(iter: I) -> Self {
+        Self(Repr::Flat(iter.into_iter().collect()))
+    }
+}
+
+impl<'a> IntoIterator for &'a List {
+    type Item = &'a Value;
+    type IntoIter = Iter<'a>;
+
+    fn into_iter(self) -> Iter<'a> {
+        self.iter()
+    }
+}
+
+/// An iterator over the values in a list
+pub enum Iter<'a> {
+    Flat(core::slice::Iter<'a, Value>),
+    Shared(im_rc::vector::Iter<'a, Value>),
+}
+
+impl<'a> Iterator for Iter<'a> {
+    type Item = &'a Value;
+
+    fn next(&mut self) -> Option<&'a Value> {
+        match self {
+            Self::Flat(iter) => iter.next(),
+            Self::Shared(iter) => iter.next(),
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match self {
+            Self::Flat(iter) => iter.size_hint(),
+            Self::Shared(iter) => iter.size_hint(),
+        }
+    }
+}
+
+impl DoubleEndedIterator for Iter<'_> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        match self {
+            Self::Flat(iter) => iter.next_back(),
+            Self::Shared(iter) => iter.next_back(),
+        }
+    }
+}
+
+impl ExactSizeIterator for Iter<'_> {}
+
+impl FusedIterator for Iter<'_> {}
@@ -0,0 +1,292 @@
+use core::{
+    cmp::Ordering,
+    ops::{Add, Mul, Neg, Sub},
+};
+use std::rc::Rc;
+
+use num_bigint::BigInt;
+use num_rational::BigRational;
+use num_traits::{FromPrimitive, Signed, ToPrimitive, Zero};
+
+use super::Value;
+
+/// A numeric value, used to implement promotion along the numeric tower.
+///
+/// Operations on two numbers of different kinds promote the lower one: `Int` < `BigInt` < `Ratio` < `Float`.
+/// Int operations that overflow are redone as `BigInt`s, and results are demoted back to `Int` when they fit.
+#[derive(Clone, Debug)]
+pub enum Number {
+    Int(i128),
+    BigInt(BigInt),
+    Ratio(BigRational),
+    Float(f64),
+}
+
+impl Number {
+    #[must_use]
+    pub fn from_value(value: &Value) -> Option<Self> {
+        match value {
+            Value::Int(i) => Some(Self::Int(*i)),
+            Value::BigInt(i) => Some(Self::BigInt((**i).clone())),
+            Value::Ratio(r) => Some(Self::Ratio((**r).clone())),
+            Value::Float(f) => Some(Self::Float(*f)),
+            _ => None,
+        }
+    }
+
+    /// Convert back into a value. Ratios with a denominator of one become ints, and `BigInt`s that fit become `Int`s.
+    #[must_use]
+    pub fn into_value(self) -> Value {
+        match self {
+            Self::Int(i) => Value::Int(i),
+            Self::BigInt(i) => i
+                .to_i128()
+                .map_or_else(|| Value::BigInt(Rc::new(i)), Value::Int),
+            Self::Ratio(r) if r.is_integer() => Self::BigInt(r.to_integer()).into_value(),
+            Self::Ratio(r) => Value::Ratio(Rc::new(r)),
+            Self::Float(f) => Value::Float(f),
+        }
+    }
+
+    #[must_use]
+    #[allow(clippy::cast_precision_loss)]
+    pub fn to_f64(&self) -> f64 {
+        match self {
+            Self::Int(i) => *i as f64,
+            Self::BigInt(i) => i.to_f64().unwrap_or(f64::NAN),
+            Self::Ratio(r) => r.to_f64().unwrap_or(f64::NAN),
+            Self::Float(f) => *f,
+        }
+    }
+
+    fn to_bigint(&self) -> BigInt {
+        match self {
+            Self::Int(i) => BigInt::from(*i),
+            Self::BigInt(i) => i.clone(),
+            Self::Ratio(_) | Self::Float(_) => unreachable!(),
+        }
+    }
+
+    fn to_ratio(&self) -> BigRational {
+        match self {
+            Self::Int(_) | Self::BigInt(_) => BigRational::from_integer(self.to_bigint()),
+            Self::Ratio(r) => r.clone(),
+            Self::Float(_) => unreachable!(),
+        }
+    }
+
+    /// Convert both numbers to the same kind
+    fn promote(self, other: Self) -> (Self, Self) {
+        match (&self, &other) {
+            (Self::Float(_), _) | (_, Self::Float(_)) => {
+                (Self::Float(self.to_f64()), Self::Float(other.to_f64()))
+            }
+            (Self::Ratio(_), _) | (_, Self::Ratio(_)) => {
+                (Self::Ratio(self.to_ratio()), Self::Ratio(other.to_ratio()))
+            }
+            (Self::BigInt(_), _) | (_, Self::BigInt(_)) => (
+                Self::BigInt(self.to_bigint()),
+                Self::BigInt(other.to_bigint()),
+            ),
+            _ => (self, other),
+        }
+    }
+
+    /// Apply an operation after promoting both numbers, redoing it with `BigInt`s if `Int`s overflow
+    fn arith(
+        self,
+        other: Self,
+        int: impl Fn(i128, i128) -> Option<i128>,
+        big: impl Fn(BigInt, BigInt) -> BigInt,
+        ratio: impl Fn(BigRational, BigRational) -> BigRational,
+        float: impl Fn(f64, f64) -> f64,
+    ) -> Self {
+        match self.promote(other) {
+            (Self::Int(a), Self::Int(b)) => int(a, b).map_or_else(
+                || Self::BigInt(big(BigInt::from(a), BigInt::from(b))),
+                Self::Int,
+            ),
+            (Self::BigInt(a), Self::BigInt(b)) => Self::BigInt(big(a, b)),
+            (Self::Ratio(a), Self::Ratio(b)) => Self::Ratio(ratio(a, b)),
+            (a, b) => Self::Float(float(a.to_f64(), b.to_f64())),
+        }
+    }
+
+    /// Exact division. Dividing two ints produces a ratio unless the result is whole.
+    /// # Errors
+    /// If dividing by an exact zero
+    pub fn try_div(self, other: Self) -> Result<Self, Value> {
+        if other.is_exact_zero() {
+            return Err(Value::error("DivideByZero", vec![]));
+        }
+        Ok(match self.promote(other) {
+            (Self::Float(a), Self::Float(b)) => Self::Float(a / b),
+            (a, b) => Self::Ratio(a.to_ratio() / b.to_ratio()),
+        })
+    }
+
+    /// Division rounding toward zero
+    /// # Errors
+    /// If dividing by an exact zero
+    pub fn quot(self, other: Self) -> Result<Self, Value> {
+        if other.is_exact_zero() {
+            return Err(Value::error("DivideByZero", vec![]));
+        }
+        match self.promote(other) {
+            (a @ (Self::Int(_) | Self::BigInt(_)), b) => Ok(a.arith(
+                b,
+                i128::checked_div,
+                |a, b| a / b,
+                |a, b| a / b,
+                |a, b| a / b,
+            )),
+            (a, b) => a.try_div(b)?.trunc(),
+        }
+    }
+
+    /// The remainder of division rounding toward zero, with the sign of the dividend
+    /// # Errors
+    /// If dividing by an exact zero
+    pub fn try_rem(self, other: Self) -> Result<Self, Value> {
+        if other.is_exact_zero() {
+            return Err(Value::error("DivideByZero", vec![]));
+        }
+        Ok(self.arith(
+            other,
+            i128::checked_rem,
+            |a, b| a % b,
+            |a, b| a % b,
+            |a, b| a % b,
+        ))
+    }
+
+    fn is_exact_zero(&self) -> bool {
+        match self {
+            Self::Int(i) => *i == 0,
+            Self::BigInt(i) => i.is_zero(),
+            Self::Ratio(r) => r.is_zero(),
+            Self::Float(_) => false,
+        }
+    }
+
+    /// Check if the number is less than zero
+    #[must_use]
+    pub fn is_negative(&self) -> bool {
+        match self {
+            Self::Int(i) => *i < 0,
+            Self::BigInt(i) => i.is_negative(),
+            Self::Ratio(r) => r.is_negative(),
+            Self::Float(f) => *f < 0.0,
+        }
+    }
+
+    /// Compare two numbers. Returns `None` if either is NaN
+    #[must_use]
+    pub fn partial_cmp(self, other: Self) -> Option<Ordering> {
+        match self.promote(other) {
+            (Self::Int(a), Self::Int(b)) => Some(a.cmp(&b)),
+            (Self::BigInt(a), Self::BigInt(b)) => Some(a.cmp(&b)),
+            (Self::Ratio(a), Self::Ratio(b)) => Some(a.cmp(&b)),
+            (a, b) => a.to_f64().partial_cmp(&b.to_f64()),
+        }
+    }
+
+    /// Round toward negative infinity, producing an int
+    /// # Errors
+    /// If the value is a float that is infinite or NaN
+    pub fn floor(self) -> Result<Self, Value> {
+        self.round_with(BigRational::floor, f64::floor)
+    }
+
+    /// Round toward positive infinity, producing an int
+    /// # Errors
+    /// If the value is a float that is infinite or NaN
+    pub fn ceil(self) -> Result<Self, Value> {
+        self.round_with(BigRational::ceil, f64::ceil)
+    }
+
+    /// Round to the nearest int, with halfway cases away from zero
+    /// # Errors
+    /// If the value is a float that is infinite or NaN
+    pub fn round(self) -> Result<Self, Value> {
+        self.round_with(BigRational::round, f64::round)
+    }
+
+    /// Round toward zero, producing an int
+    /// # Errors
+    /// If the value is a float that is infinite or NaN
+    pub fn trunc(self) -> Result<Self, Value> {
+        self.round_with(BigRational::trunc, f64::trunc)
+    }
+
+    fn round_with(
+        self,
+        ratio: impl Fn(&BigRational) -> BigRational,
+        float: impl Fn(f64) -> f64,
+    ) -> Result<Self, Value> {
+        match self {
+            Self::Int(_) | Self::BigInt(_) => Ok(self),
+            Self::Ratio(r) => Ok(Self::BigInt(ratio(&r).to_integer())),
+            Self::Float(f) => BigInt::from_f64(float(f))
+                .map(Self::BigInt)
+                .ok_or_else(|| Value::error("NotFinite", vec![Value::Float(f)])),
+        }
+    }
+}
+
+impl Add for Number {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self {
+        self.arith(
+            rhs,
+            i128::checked_add,
+            |a, b| a + b,
+            |a, b| a + b,
+            |a, b| a + b,
+        )
+    }
+}
+
+impl Sub for Number {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self {
+        self.arith(
+            rhs,
+            i128::checked_sub,
+            |a, b| a - b,
+            |a, b| a - b,
+            |a, b| a - b,
+        )
+    }
+}
+
+impl Mul for Number {
+    type Output = Self;
+
+    fn mul(self, rhs: Self) -> Self {
+        self.arith(
+            rhs,
+            i128::checked_mul,
+            |a, b| a * b,
+            |a, b| a * b,
+            |a, b| a * b,
+        )
+    }
+}
+
+impl Neg for Number {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        match self {
+            Self::Int(i) => i
+                .checked_neg()
+                .map_or_else(|| Self::BigInt(-BigInt::from(i)), Self::Int),
+            Self::BigInt(i) => Self::BigInt(-i),
+            Self::Ratio(r) => Self::Ratio(-r),
+            Self::Float(f) => Self::Float(-f),
+        }
+    }
+}
@@ -0,0 +1,235 @@
+use core::{
+    cmp::Ordering,
+    fmt,
+    hash::{BuildHasherDefault, Hasher},
+};
+use std::{borrow::Cow, cell::RefCell, collections::HashMap};
+
+/// An interned name, used by symbols and keywords. Every symbol with the same name has the same id, so
+/// comparing, hashing and cloning one never touches its text.
+///
+/// Interned text lives until the program ends, so a name that ends in a number is kept as the text before
+/// the number and the number. Names made from a counter, like `item1` and `item2`, share their text.
+/// Symbols made by [`Symbol::gensym`] only keep the text of their prefix.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Symbol {
+    /// The interned text of the name, or of the part before its number
+    id: u32,
+    /// 0 for a name that is all text, the number the name ends in plus 1, or the number of a gensym with
+    /// [`GENSYM`] set
+    suffix: u32,
+}
+
+/// Set in the suffix of a symbol made by [`Symbol::gensym`]
+const GENSYM: u32 = 1 << 31;
+
+/// Names the interpreter checks for, interned ahead of time in this order so they can be matched on
+macro_rules! known {
+    ($($name:ident $text:literal;)*) => {
+        #[allow(non_camel_case_types, clippy::upper_case_acronyms)]
+        #[repr(u32)]
+        enum Known {
+            $($name,)*
+        }
+
+        impl Symbol {
+            $(pub const $name: Self = Self { id: Known::$name as u32, suffix: 0 };)*
+        }
+
+        const KNOWN: &[&str] = &[$($text,)*];
+    };
+}
+
+known! {
+    TRUE "true";
+    FALSE "false";
+    NIL "nil";
+    LAMBDA "\\";
+    IF "if";
+    QUOTE "quote";
+    QUASIQUOTE "quasiquote";
+    UNQUOTE "unquote";
+    SPLICE_UNQUOTE "splice-unquote";
+    ERR "err";
+    CONS "cons";
+    LAZY_SEQ "lazy-seq";
+    DOSEQ "doseq";
+    LET "let*";
+    LOOP "loop";
+    RECUR "recur";
+    DEF "def!";
+    DO "do";
+    TRY "try*";
+    CATCH "catch*";
+    DOC "doc";
+    IMPORT "import";
+    REQUIRE "require";
+    HELP "help";
+    TRACE "trace";
+    EVAL "eval";
+    WILDCARD "_";
+    ELLIPSIS "...";
+}
+
+/// Hashes symbols by spreading out their ids, which are already unique, instead of running a general
+/// purpose hash function over them
+#[derive(Default)]
+pub struct SymbolHasher(u64);
+
+impl Hasher for SymbolHasher {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.write_u64(u64::from(byte));
+        }
+    }
+
+    fn write_u32(&mut self, id: u32) {
+        self.write_u64(u64::from(id));
+    }
+
+    fn write_u64(&mut self, n: u64) {
+        self.0 = (self.0.rotate_left(5) ^ n).wrapping_mul(0x9e37_79b9_7f4a_7c15);
+    }
+}
+
+/// A map keyed by symbols, such as the names defined in an environment
+pub type SymbolMap<V> = HashMap<Symbol, V, BuildHasherDefault<SymbolHasher>>;
+
+struct Interner {
+    ids: HashMap<&'static str, u32>,
+    names: Vec<&'static str>,
+    /// How many symbols `gensym` has made
+    gensyms: u32,
+}
+
+thread_local! {
+    static INTERNER: RefCell<Interner> = RefCell::new(Interner {
+        ids: KNOWN
+            .iter()
+            .zip(0..)
+            .map(|(&name, id)| (name, id))
+            .collect(),
+        names: KNOWN.to_vec(),
+        gensyms: 0,
+    });
+}
+
+impl Symbol {
+    /// Get the symbol with a name, interning the name if it hasn't been seen before.
+    ///
+    /// Interned text lives until the program ends.
+    ///
+    /// # Panics
+    /// If more than 2^32 distinct texts are interned
+    #[must_use]
+    pub fn intern(name: &str) -> Self {
+        let prefix = name.trim_end_matches(|c: char| c.is_ascii_digit());
+        let number = &name[prefix.len()..];
+        match number.parse::<u32>() {
+            Ok(n) if n < GENSYM - 1 && (number == "0" || !number.starts_with('0')) => Self {
+                id: Self::text(prefix),
+                suffix: n + 1,
+            },
+            _ => Self {
+                id: Self::text(name),
+                suffix: 0,
+            },
+        }
+    }
+
+    /// The id of some interned text, interning it if it hasn't been seen before
+    fn text(text: &str) -> u32 {
+        INTERNER.with_borrow_mut(|interner| {
+            if let Some(&id) = interner.ids.get(text) {
+                return id;
+            }
+            let text: &'static str = Box::leak(text.into());
+            let id = u32::try_from(interner.names.len()).expect("fewer than 2^32 distinct texts");
+            interner.names.push(text);
+            interner.ids.insert(text, id);
+            id
+        })
+    }
+
+    /// Make a symbol that isn't equal to any other, named after a prefix, like `prefix__12`. Only the
+    /// prefix is interned, so making gensyms doesn't use up memory.
+    ///
+    /// # Panics
+    /// If more than 2^31 gensyms are made
+    #[must_use]
+    pub fn gensym(prefix: &str) -> Self {
+        let n = INTERNER.with_borrow_mut(|interner| {
+            interner.gensyms += 1;
+            interner.gensyms
+        });
+        assert!(n < GENSYM, "fewer than 2^31 gensyms");
+        Self {
+            id: Self::text(prefix),
+            suffix: GENSYM | n,
+        }
+    }
+
+    /// The text of the name
+    #[must_use]
+    pub fn name(self) -> Cow<'static, str> {
+        let text = INTERNER.with_borrow(|interner| interner.names[self.id as usize]);
+        match self.suffix {
+            0 => Cow::Borrowed(text),
+            suffix if suffix & GENSYM != 0 => Cow::Owned(format!("{text}__{}", suffix & !GENSYM)),
+            suffix => Cow::Owned(format!("{text}{}", suffix - 1)),
+        }
+    }
+}
+
+impl From<&str> for Symbol {
+    fn from(name: &str) -> Self {
+        Self::intern(name)
+    }
+}
+
+impl PartialEq<str> for Symbol {
+    fn eq(&self, other: &str) -> bool {
+        self.name() == other
+    }
+}
+
+impl PartialEq<&str> for Symbol {
+    fn eq(&self, other: &&str) -> bool {
+        self.name() == *other
+    }
+}
+
+/// Symbols sort by name, not by when they were interned; a gensym sorts apart from an interned symbol with the same name
+impl PartialOrd for Symbol {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Symbol {
+    fn cmp(&self, other: &Self) -> Ordering {
+        if self == other {
+            Ordering::Equal
+        } else {
+            self.name()
+                .cmp(&other.name())
+                .then_with(|| (self.id, self.suffix).cmp(&(other.id, other.suffix)))
+        }
+    }
+}
+
+impl fmt::Display for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.name())
+    }
+}
+
+impl fmt::Debug for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.name())
+    }
+}
@@ -6,6 +6,12 @@
 ## usage: (defun! my-function (my function parameters) (evaluate the return value))
 (defmacro! defun! (name args body) `(def! ~name (\ ~args ~body)))
 
+## Define a macro by the shapes of code it accepts. Each rule is a pattern, whose head is ignored, and a quasiquoted template that
+## the symbols the pattern binds are filled into. A pattern or template followed by ... repeats. Use name# to bind names safely.
+## usage: (defsyntax! swap! () ((_ a b) (let* (tmp# (a)) (do (set! a (b)) (set! b tmp#)))))
+## usage: (defsyntax! my-or () ((_) false) ((_ x) x) ((_ x rest ...) (let* (v# x) (if v# v# (my-or rest ...)))))
+(defmacro! defsyntax! spec `(def! ~(first spec) (apply syntax-rules '~(rest spec))))
+
 ## Concatenate all of the arguments into a list
 (defun! list x x)
 
@@ -18,26 +24,17 @@
 ## Convert the provided value to a boolean
 (defun! bool (x) (if x true false))
 
-## Create a range of numbers from the start to the end
-(defun! range (start end) (if (< start end) (cons start (range (+ start 1) end)) []))
-
-## Create a list with the given element repeated the given number of times
-(defun! repeat (x l) (if (<= l 0) [] (cons x (repeat x (- l 1)))))
-
-## Apply a function to each element of a list
-(defun! map (l func) (cons (func (first l)) (map (rest l) func)))
-
 ## Check if a table, list, or string is empty
 (defun! empty? (x) (= (count x) 0))
 
 ## Return true if the list is empty or if every of the parameters is truthy
-(defun! all? ls (if (empty? ls) true (if (first ls) (all? (rest ls)) false)))
+(defun! all? ls (if (empty? ls) true (if (first ls) (apply all? (rest ls)) false)))
 
 ## Return true if any of the parameters is truthy
-(defun! any? ls (if (empty? ls) false (if (first ls) true (any? (rest ls)))))
+(defun! any? ls (if (empty? ls) false (if (first ls) true (apply any? (rest ls)))))
 
-## Return a copy of the provided list with a given number of values removed from the start
-(defun! trim (ls i) (if (any? (= i 0) (empty? ls)) ls (trim (rest ls) (- i 1))))
+## Check if the value is an int, ratio, or float
+(defun! number? (x) (any? (int? x) (ratio? x) (float? x)))
 
 ## Calculate a factorial
 (defun! fact (n) (apply * (cons 1 (range 1 (+ 1 n)))))
@@ -48,23 +45,34 @@
 ## Calculate the product of all elements in a list
 (defun! prod (n) (apply * n))
 
-## Calculate the first number raised to the power of the second number
-(defun! ^ (x y) (apply * (cons 1 (repeat x y))))
-
-# ## Calculate the first number raised to the power of the second number
-#(defun! ^ (x y) (let* (
-#    pow_helper (\ (a x y) (if (= y 0) a (pow_helper (if (odd? y) (* a x) a) (* x x) (/ y 2))))
-#) (pow_helper 1 x y)))
-
 ## Check if a number is even
 (defun! even? (x) (= (% x 2) 0))
 
 ## Check if a number is odd
-(defun! odd? (x) (= (% x 2) 1))
+(defun! odd? (x) (not (even? x)))
+
+## Calculate the first number raised to the power of the second number. The result is exact for ints and ratios.
+(defun! ^ (x y) (let* (
+    pow_helper (\ (a x y) (if (= y 0) a (pow_helper (if (odd? y) (* a x) a) (* x x) (quot y 2))))
+) (if (< y 0) (/ 1 (pow_helper 1 x (- y))) (pow_helper 1 x y))))
 
 ## Take the combinations between two numbers
-(defun! choose (a b) (
+(defun! choose (a b) (let* (
+    k (if (< (* 2 b) a) b (- a b))
+) (
     /
-    (apply * (range (+ b 1) (+ a 1)))
-    (fact (- a b))
-))
+    (apply * (cons 1 (range (+ (- a k) 1) (+ a 1))))
+    (fact k)
+)))
+
+## The tests defined with deftest, most recently defined first, as (name function) pairs
+(def! *TESTS* (atom []))
+
+## Define a test to be run by `lisping test`. The body can hold any number of expressions.
+## usage: (deftest addition (assert= 4 (+ 2 2)) (assert= 0 (+ 2 -2)))
+(defmacro! deftest spec
+    `(inspect! *TESTS* (\ (tests#) (cons (list '~(first spec) (\ () (do ~@(rest spec)))) tests#))))
+
+## Check that evaluating an expression raises an error. Give error names after the expression to only accept those errors.
+## usage: (assert-err (/ 1 0) DivideByZero)
+(defmacro! assert-err spec `(assert-raises (\ () ~(first spec)) '~(first spec) '~(rest spec)))
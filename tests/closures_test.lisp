@@ -0,0 +1,51 @@
+## Tests for what lambdas capture from the scopes they are made in
+
+(defun! adder (n) (\ (x) (+ x n)))
+(defun! compose (f g) (\ (x) (f (g x))))
+(defun! later () (defined-later 1))
+
+(deftest capturing-locals
+    (assert= 7 ((adder 3) 4))
+    (assert= 9 ((compose (adder 1) (adder 5)) 3))
+    (assert= 6 (let* (a 1 b 2) ((\ () (let* (c 3) ((\ () (+ a b c))))))))
+    (assert= (list 2 3 4) (map (list 1 2 3) (adder 1))))
+
+(deftest capturing-each-iteration
+    (let* (fns (atom []))
+        (do
+            (doseq (i (list 1 2 3)) (inspect! fns (\ (l) (cons (\ () i) l))))
+            (assert= (list 3 2 1) (map (fns) (\ (f) (f)))))))
+
+(deftest recursion-through-a-scope
+    (assert= 1024 (^ 2 10))
+    (assert= 120 (let* (f (\ (n) (if (= n 0) 1 (* n (f (- n 1)))))) (f 5))))
+
+(def! defined-later (\ (x) (+ x 100)))
+
+(deftest globals-are-looked-up-when-called
+    (assert= 101 (later))
+    (assert= 5 (let* (x 2) ((\ () (+ x (count (list 1 2 3))))))))
+
+(deftest defining-in-a-scope
+    (let* (x 1)
+        (do
+            (def! y 10)
+            (assert= 11 ((\ () (+ x y))))
+            (def! y 20)
+            (assert= 21 ((\ () (+ x y)))))))
+
+(deftest defining-after-a-closure-is-made
+    (assert= 21 (let* (x 1) (let* (y 2) (do (def! g (\ () (+ x y))) (def! y 20) (g)))))
+    (assert= 2 (let* (a 1) (do (def! f (\ () a)) (def! a 2) (f))))
+    (assert= 5 ((\ (a) (do (def! f (\ () a)) (defun! a () 5) ((f)))) 1)))
+
+(deftest macros-and-quasiquote-in-closures
+    (let* (n 3 i (atom 0))
+        (do
+            ((\ () (while (< (i) n) (set! i (+ (i) 1)))))
+            (assert= 3 (i))
+            (assert= (list 'n 3) ((\ () `(n ~n)))))))
+
+(deftest capturing-in-catch-blocks
+    (let* (f (try* (err Oops 5) (catch* e (\ () (rest e)))))
+        (assert= (list 5) (f))))
@@ -0,0 +1,45 @@
+## Tests for persistent lists and tables
+
+## Build the list (1 ... n) by adding to the end of acc
+(defun! build (n acc) (if (== (count acc) n) acc (build n (conj acc (+ (count acc) 1)))))
+
+(deftest conj-appends
+    (assert= (list 1 2 3) (conj [1] 2 3))
+    (assert= [] (conj []))
+    (assert-err (conj {} 1) InvalidArgs@conj))
+
+(deftest shared-lists-equal-flat-lists
+    (assert= (list 1 2) (rest [0 1 2]))
+    (assert= (list 0 1 2) (cons 0 (conj [1] 2)))
+    (assert= true (contains? (assoc {} (rest [0 1 2]) 'found) [1 2]))
+    (assert= 'found (get (assoc {} [1 2] 'found) (conj [1] 2))))
+
+(deftest updates-leave-the-original
+    (let* (l [1 2 3] longer (conj l 4) t {1 2} bigger (assoc t 3 4))
+        (do
+            (assert= 3 (count l))
+            (assert= 4 (count longer))
+            (assert= (list 2 3) (rest l))
+            (assert= 1 (count t))
+            (assert= nil (get t 3))
+            (assert= (list 3) (keys (dissoc bigger 1))))))
+
+(deftest table-equality-ignores-order
+    (assert= (assoc {} 1 2 3 4) (assoc {} 3 4 1 2))
+    (assert= true (contains? (assoc {} (assoc {} 1 2 3 4) 'x) (assoc {} 3 4 1 2))))
+
+(deftest long-lists
+    (let* (big (build 5000 []))
+        (do
+            (assert= 5000 (count big))
+            (assert= 5000 (last big))
+            (assert= 2500 (nth big 2499))
+            (assert= 4999 (count (rest big)))
+            (assert= 10000 (last (map big (\ (x) (* x 2))))))))
+
+(deftest walking-long-lists
+    (assert= 199990000
+        (loop (l (range 0 20000) total 0)
+            (if (empty? l)
+                total
+                (let* ((x) l) (recur (rest l) (+ total x)))))))
@@ -0,0 +1,71 @@
+use std::process::Command;
+
+/// Run the lisp test suite in `tests` with an engine, returning whether every test passed and the line
+/// reporting each test's outcome
+fn run_suite(engine: &str) -> (bool, Vec<String>) {
+    let output = Command::new(env!("CARGO_BIN_EXE_lisping"))
+        .args(["test", "--engine", engine, "tests"])
+        .current_dir(env!("CARGO_MANIFEST_DIR"))
+        .output()
+        .expect("the interpreter runs");
+    let report = String::from_utf8_lossy(&output.stdout);
+    let outcomes = report
+        .lines()
+        .filter(|line| line.starts_with("  ok") || line.starts_with("  FAIL"))
+        .map(str::to_string)
+        .collect();
+    (output.status.success(), outcomes)
+}
+
+/// Run a program with an engine, returning everything it printed
+fn run_program(engine: &str, path: &str) -> String {
+    let output = Command::new(env!("CARGO_BIN_EXE_lisping"))
+        .args(["--engine", engine, path])
+        .current_dir(env!("CARGO_MANIFEST_DIR"))
+        .output()
+        .expect("the interpreter runs");
+    format!(
+        "{}{}",
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr)
+    )
+}
+
+/// The engines run one after the other, since some tests write files
+#[test]
+fn engines_agree_on_the_lisp_suite() {
+    let (tree_passed, tree) = run_suite("tree");
+    let (vm_passed, vm) = run_suite("vm");
+    assert!(!tree.is_empty());
+    assert_eq!(tree, vm);
+    assert!(
+        tree_passed,
+        "the suite fails with the tree engine: {tree:#?}"
+    );
+    assert!(vm_passed, "the suite fails with the vm engine: {vm:#?}");
+}
+
+#[test]
+fn engines_agree_on_programs() {
+    for program in ["class_example.lisp", "something.lisp"] {
+        assert_eq!(
+            run_program("tree", program),
+            run_program("vm", program),
+            "{program}"
+        );
+    }
+}
+
+#[test]
+fn options_go_before_the_subcommand_or_source() {
+    let output = Command::new(env!("CARGO_BIN_EXE_lisping"))
+        .args(["--engine", "vm", "test", "tests/lazy_test.lisp"])
+        .current_dir(env!("CARGO_MANIFEST_DIR"))
+        .output()
+        .expect("the interpreter runs");
+    assert!(output.status.success());
+    assert_eq!(
+        run_program("vm", "tests/does_not_exist.lisp"),
+        "Can't read tests/does_not_exist.lisp: No such file or directory (os error 2)\n"
+    );
+}
@@ -0,0 +1,29 @@
+## Tests for the file system builtins. They work in a scratch directory under the working directory.
+
+(def! scratch "fs_test_scratch")
+
+(deftest write-and-read
+    (mkdir scratch)
+    (def! file (path-join scratch "file.txt"))
+    (spit file "one\ntwo")
+    (append-file file "\nthree")
+    (assert= "one\ntwo\nthree" (slurp file))
+    (assert= (list "one" "two" "three") (read-lines file))
+    (assert= true (file-exists? file))
+    (assert= (list "file.txt") (list-dir scratch))
+    (delete-file file)
+    (delete-file scratch)
+    (assert= false (file-exists? scratch)))
+
+(deftest io-errors
+    (assert= (list 'IoError 'NotFound "no/such/file") (try* (slurp "no/such/file") (catch* e e)))
+    (assert-err (list-dir "no/such/dir") IoError))
+
+(deftest paths
+    (assert= "a/b/c.txt" (path-join "a" "b" "c.txt"))
+    (assert= "a/b" (path-dir "a/b/c.txt"))
+    (assert= nil (path-dir "c.txt"))
+    (assert= "c.txt" (path-name "a/b/c.txt"))
+    (assert= "c" (path-stem "a/b/c.txt"))
+    (assert= "txt" (path-ext "a/b/c.txt"))
+    (assert= nil (path-ext "a/b")))
@@ -0,0 +1,68 @@
+## Tests for collecting cycles
+
+(defun! recursive-closure (n) (let* (f (\ (x) (if (= x 0) n (f (- x 1))))) f))
+(defun! self-holding-atom () (let* (a (atom nil)) (do (set! a a) nil)))
+(defun! times (n f) (if (= n 0) nil (do (f) (times (- n 1) f))))
+(defun! self-holding-table () (let* (self (atom nil)) (set! self {get (\ () 1) me (\ () (self))})))
+
+(deftest freeing-closures-in-their-own-scope
+    (do
+        (gc)
+        (let* (before (gc-stats))
+            (do
+                (times 100 (\ () ((recursive-closure 1) 5)))
+                (gc)
+                (assert= true (>= (:collected (gc-stats)) (+ (:collected before) 100)))
+                (assert= (:envs before) (:envs (gc-stats)))))))
+
+(deftest freeing-atoms-that-hold-themselves
+    (do
+        (gc)
+        (let* (before (gc-stats))
+            (do
+                (times 50 self-holding-atom)
+                (gc)
+                (assert= (+ (:collected before) 50) (:collected (gc-stats)))
+                (assert= (:atoms before) (:atoms (gc-stats)))))))
+
+(deftest freeing-cycles-through-tables
+    (do
+        (gc)
+        (let* (before (gc-stats))
+            (do
+                (times 50 self-holding-table)
+                (gc)
+                (assert= true (>= (:collected (gc-stats)) (+ (:collected before) 50)))
+                (assert= (:envs before) (:envs (gc-stats)))
+                (assert= (:atoms before) (:atoms (gc-stats)))))))
+
+(deftest keeping-what-tables-reach
+    (let* (a (atom nil) t {f (recursive-closure 4) s #{a}})
+        (do
+            (set! a t)
+            (gc)
+            (assert= 4 ((get (a) 'f) 2))
+            (assert= 4 ((get t 'f) 1)))))
+
+(deftest keeping-what-is-reachable
+    (let* (f (recursive-closure 7) a (atom nil))
+        (do
+            (set! a (list a f))
+            (gc)
+            (assert= 7 (f 3))
+            (assert= 7 ((nth (a) 1) 2)))))
+
+(def! ones (lazy-seq (cons 1 ones)))
+
+(deftest keeping-lazy-seqs-that-refer-to-themselves
+    (do
+        (gc)
+        (assert= (list 1 1 1) (take ones 3))))
+
+(deftest gc-stats
+    (let* (stats (gc-stats))
+        (do
+            (assert= (list :atoms :collected :collections :envs :lazy-seqs :tracked) (sort (keys stats)))
+            (assert= true (> (:envs stats) 0))
+            (assert-err (gc 1) InvalidArgs@gc)
+            (assert-err (gc-stats 1) InvalidArgs@gc-stats))))
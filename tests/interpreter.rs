@@ -0,0 +1,138 @@
+use std::collections::{HashMap, HashSet};
+
+use lisping::{types::Symbol, FromValue, Interpreter, IntoValue, Value};
+use num_bigint::BigInt;
+
+/// Convert a value to lisp and back
+fn round_trip<T: IntoValue + FromValue + Clone>(value: &T) -> T {
+    T::from_value(value.clone().into_value()).unwrap()
+}
+
+/// The name of an error raised by the interpreter
+fn error_name(err: &Value) -> String {
+    format!("{:?}", err.as_list().unwrap().first().unwrap())
+}
+
+#[test]
+fn ints_round_trip() {
+    assert_eq!(round_trip(&-5i8), -5);
+    assert_eq!(round_trip(&u64::MAX), u64::MAX);
+    assert_eq!(round_trip(&i128::MIN), i128::MIN);
+    assert_eq!(round_trip(&i128::MAX), i128::MAX);
+    assert_eq!(round_trip(&u128::MAX), u128::MAX);
+    assert_eq!(round_trip(&usize::MAX), usize::MAX);
+}
+
+#[test]
+fn ints_above_i128_are_bignums() {
+    let value = u128::MAX.into_value();
+    assert!(matches!(value, Value::BigInt(_)));
+    assert_eq!(BigInt::from_value(value).unwrap(), BigInt::from(u128::MAX));
+}
+
+#[test]
+fn ints_out_of_range() {
+    assert_eq!(
+        error_name(&u8::from_value(300.into_value()).unwrap_err()),
+        "OutOfRange"
+    );
+    assert_eq!(
+        error_name(&u32::from_value((-1).into_value()).unwrap_err()),
+        "OutOfRange"
+    );
+    assert_eq!(
+        error_name(&i128::from_value(u128::MAX.into_value()).unwrap_err()),
+        "OutOfRange"
+    );
+    assert_eq!(
+        error_name(&i64::from_value("1".into_value()).unwrap_err()),
+        "TypeError"
+    );
+}
+
+#[test]
+fn other_values_round_trip() {
+    assert_eq!(round_trip(&BigInt::from(7)), BigInt::from(7));
+    assert!((round_trip(&0.25f64) - 0.25).abs() < f64::EPSILON);
+    assert!(round_trip(&true));
+    assert!(!round_trip(&false));
+    assert_eq!(round_trip(&"héllo".to_string()), "héllo");
+    assert_eq!(round_trip(&Some(3u8)), Some(3));
+    assert_eq!(round_trip(&None::<u8>), None);
+    assert_eq!(round_trip(&vec![1i32, 2, 3]), vec![1, 2, 3]);
+    let table = HashMap::from([("a".to_string(), 1i64), ("b".to_string(), 2)]);
+    assert_eq!(round_trip(&table), table);
+    let set = HashSet::from([1u16, 2, 3]);
+    assert_eq!(round_trip(&set), set);
+}
+
+#[test]
+fn lisp_values_convert() {
+    let lisp = Interpreter::new();
+    let ratio = lisp.eval_str("1/4").unwrap();
+    assert!((f64::from_value(ratio).unwrap() - 0.25).abs() < f64::EPSILON);
+    let list: Vec<String> = Vec::from_value(lisp.eval_str("(list \"a\" \"b\")").unwrap()).unwrap();
+    assert_eq!(list, ["a", "b"]);
+    let nothing: Option<i64> = Option::from_value(lisp.eval_str("nil").unwrap()).unwrap();
+    assert_eq!(nothing, None);
+}
+
+#[test]
+fn calls_convert_arguments_and_results() {
+    let lisp = Interpreter::new();
+    lisp.eval_str("(defun! add (a b) (+ a b))").unwrap();
+    assert_eq!(
+        lisp.call::<u128>("add", (u128::MAX - 1, 1)).unwrap(),
+        u128::MAX
+    );
+    assert_eq!(
+        lisp.call::<String>("str", ("a", 1, true)).unwrap(),
+        "a1true"
+    );
+    let err = lisp.call::<i64>("missing", ()).unwrap_err();
+    assert_eq!(error_name(&err), "UnresolvedIdentifier");
+}
+
+#[test]
+fn interpreters_are_separate() {
+    let first = Interpreter::new();
+    let second = Interpreter::new();
+    first.set("x", 1);
+    assert_eq!(first.get("x"), Some(Value::Int(1)));
+    assert_eq!(second.get("x"), None);
+    first.register("one", "Always 1", |_, _| Ok(1.into_value()));
+    assert_eq!(first.doc("one").as_deref(), Some("Always 1"));
+    assert_eq!(second.doc("one"), None);
+}
+
+#[test]
+fn errors_are_values() {
+    let lisp = Interpreter::new();
+    assert_eq!(error_name(&lisp.eval_str("(").unwrap_err()), "ParseError");
+    assert_eq!(
+        lisp.eval_named("(+ 1\n  2))", "sum.lisp").unwrap_err(),
+        Value::error(
+            "ParseError",
+            vec![
+                Value::String("sum.lisp".to_string()),
+                Value::String("Unmatched closing parenthesis at sum.lisp:2:5".to_string()),
+            ]
+        )
+    );
+    assert_eq!(
+        error_name(&lisp.eval_str("(err Oops 1)").unwrap_err()),
+        "Oops"
+    );
+    let err = lisp.eval_file("tests/does_not_exist.lisp").unwrap_err();
+    assert_eq!(error_name(&err), "IoError");
+}
+
+#[test]
+fn symbols_order_like_they_compare() {
+    let gensym = Symbol::gensym("tmp");
+    let interned = Symbol::intern(&gensym.name());
+    assert_ne!(gensym, interned);
+    assert_ne!(gensym.cmp(&interned), std::cmp::Ordering::Equal);
+    assert_eq!(gensym.cmp(&gensym), std::cmp::Ordering::Equal);
+    assert!(Symbol::intern("a") < Symbol::intern("b"));
+}
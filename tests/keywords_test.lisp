@@ -0,0 +1,30 @@
+## Tests for keywords
+
+(deftest keywords-evaluate-to-themselves
+    (assert= :name :name)
+    (assert= true (keyword? :name))
+    (assert= false (keyword? 'name))
+    (assert= false (= :name 'name))
+    (assert= 'keyword (type :a))
+    (assert= ":name" (str :name))
+    (assert= 'symbol (type (first '(: x)))))
+
+(deftest converting
+    (assert= :name (keyword "name"))
+    (assert= :name (keyword 'name))
+    (assert= :name (keyword :name))
+    (assert-err (keyword "") InvalidArgs@keyword))
+
+(deftest keywords-as-accessors
+    (let* (dog (assoc {} :name "Tucky" :age 3))
+        (do
+            (assert= "Tucky" (:name dog))
+            (assert= nil (:owner dog))
+            (assert= "nobody" (:owner dog "nobody"))
+            (assert= 3 (apply :age (list dog)))
+            (assert= (list "Tucky") (map (list dog) :name))
+            (assert-err (:name [1 2]) InvalidArgs@:name))))
+
+(deftest keywords-in-literals
+    (assert= 1 (:a {:a 1}))
+    (assert= true (contains? #{:a :b} :b)))
@@ -0,0 +1,91 @@
+## Tests for lazy sequences
+
+(deftest infinite-ranges
+    (assert= (list 0 1 2 3 4) (take (range) 5))
+    (assert= (list 10 11 12) (take (drop (range) 10) 3))
+    (assert= 7 (nth (range) 7))
+    (assert-err (nth (range) -1) InvalidIndex)
+    (assert= (list 3 4 5) (take (drop (range) 3) 3)))
+
+(deftest finite-ranges
+    (assert= (list 0 1 2) (range 3))
+    (assert= 10 (count (range 10)))
+    (assert= [] (range 0))
+    (assert= (list 1 2 3) (range 1 4))
+    (assert= (list 0 2 4) (range 0 6 2))
+    (assert= (list 5 4 3) (range 5 2 -1))
+    (assert= [] (range 3 1))
+    (assert-err (range 0 5 0) InvalidArgs@range))
+
+(deftest iterate-and-cycle
+    (assert= (list 1 2 4 8 16) (take (iterate (\ (x) (* x 2)) 1) 5))
+    (assert= (list 'a 'b 'a 'b 'a) (take (cycle '(a b)) 5))
+    (assert= (list 'x 'x 'x) (take (repeat 'x) 3))
+    (assert= (list 'x 'x) (repeat 'x 2))
+    (assert= (list) (repeat 'x 0))
+    (assert-err (repeat 'x "3") InvalidArgs@repeat)
+    (assert-err (repeat 'x 1.5) InvalidArgs@repeat)
+    (assert-err (repeat 'x -1) InvalidArgs@repeat))
+
+(deftest lazy-map-and-filter
+    (let* (evens (filter (range) (\ (x) (= 0 (% x 2)))))
+        (do
+            (assert= true (lazy-seq? evens))
+            (assert= (list 0 2 4 6) (take evens 4))
+            (assert= (list 1 9 25) (take (map (filter (range) (\ (x) (= 1 (% x 2)))) (\ (x) (* x x))) 3)))))
+
+(deftest zipping-lazy-seqs
+    (assert= (list (list :a 0) (list :b 1) (list :c 2)) (zip [:a :b :c] (range)))
+    (assert= (list (list 1 'x 0) (list 2 'y 0)) (zip (iterate (\ (x) (+ x 1)) 1) (cycle '(x y)) [0 0]))
+    (assert= (list (list 0 1)) (zip (range) (lazy-seq [1])))
+    (assert-err (zip (range) (repeat 1)) InfiniteSequence))
+
+(deftest whole-infinite-seqs
+    (assert-err (reduce (range) +) InfiniteSequence)
+    (assert-err (sort (map (range) (\ (x) (- x)))) InfiniteSequence)
+    (assert-err (reverse (cycle [1 2])) InfiniteSequence)
+    (assert-err (count (filter (range) (\ (x) true))) InfiniteSequence)
+    (assert-err (last (range)) InfiniteSequence)
+    (def! twos (lazy-seq (cons 2 twos)))
+    (assert= 2 (first twos))
+    (assert-err (count twos) InfiniteSequence)
+    (assert= 6 (reduce (lazy-seq [1 2 3]) +)))
+
+(deftest realization-is-cached
+    (let* (calls (atom 0)
+           seq (lazy-seq (do (inspect! calls (\ (n) (+ n 1))) [1 2 3])))
+        (do
+            (assert= false (realized? seq))
+            (assert= 0 (calls))
+            (assert= 1 (first seq))
+            (assert= true (realized? seq))
+            (assert= (list 2 3) (take (rest seq) 2))
+            (assert= 3 (count seq))
+            (assert= 1 (calls)))))
+
+(deftest finite-lazy-seqs
+    (let* (seq (cons 0 (lazy-seq [1 2])))
+        (do
+            (assert= 0 (first seq))
+            (assert= 2 (nth seq 2))
+            (assert= 3 (count seq))
+            (assert= 2 (last seq))
+            (assert= nil (first (drop seq 5))))))
+
+(deftest doseq-walks-sequences
+    (let* (total (atom 0))
+        (do
+            (assert= nil (doseq (x (take (drop (range) 1) 4)) (inspect! total (\ (n) (+ n x)))))
+            (assert= 10 (total))
+            (doseq ((a b) [[1 2] [3 4]]) (inspect! total (\ (n) (+ n (* a b)))))
+            (assert= 24 (total))
+            (doseq (x (cons 100 (lazy-seq nil))) (inspect! total (\ (n) (+ n x))))
+            (assert= 124 (total)))))
+
+(deftest self-dependent-seqs
+    (def! loopy (lazy-seq loopy))
+    (assert-err (first loopy) CircularLazySeq))
+
+(deftest long-chains
+    (let* (seq (range))
+        (assert= 100000 (nth seq 100000))))
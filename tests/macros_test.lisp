@@ -0,0 +1,118 @@
+## Tests for quasiquoting and writing macros
+
+(defmacro! with-doubled (name value body) `(let* (v# ~value ~name (* 2 v#)) ~body))
+(defsyntax! swap! () ((_ a b) (let* (tmp# (a)) (do (set! a (b)) (set! b tmp#)))))
+(defsyntax! my-or ()
+    ((_) false)
+    ((_ x) x)
+    ((_ x more ...) (let* (v# x) (if v# v# (my-or more ...)))))
+(defsyntax! bind (=>) ((_ ((name value) ...) => body) ((\ (name ...) body) value ...)))
+(defsyntax! count-forms () ((_ forms ...) ~(count '(forms ...))))
+
+(deftest gensym
+    (assert= true (symbol? (gensym)))
+    (assert= false (= (gensym) (gensym)))
+    (assert= "tmp__" (substr (str (gensym "tmp")) 0 5))
+    (assert= "x__" (substr (str (gensym 'x)) 0 3))
+    (assert-err (gensym 1) InvalidArgs@gensym)
+    (let* (g (gensym 'v))
+        (assert= false (= g (symbol (str g))))))
+
+(deftest symbols-ending-in-numbers
+    (assert= 'item12 (symbol (str "item" 12)))
+    (assert= false (= 'item12 'item012))
+    (assert= "x007" (str (symbol "x007")))
+    (assert= "12" (str (symbol "12")))
+    (assert= (list 'a10 'a2 'b1) (sort (list 'b1 'a2 'a10))))
+
+(deftest auto-gensym
+    (let* ((a b c) `(x# y# x#))
+        (do
+            (assert= a c)
+            (assert= false (= a b))
+            (assert= false (= a 'x#))))
+    (assert= false (= (first `(x#)) (first `(x#))))
+    (assert= 'x# (first '(x#))))
+
+(deftest macros-that-bind-names
+    (let* (v 3)
+        (assert= 9 (with-doubled d v (+ v d)))))
+
+(deftest syntax-rules
+    (let* (tmp (atom 1) other (atom 2))
+        (do
+            (swap! tmp other)
+            (assert= (list 2 1) (list (tmp) (other)))))
+    (assert= false (my-or))
+    (assert= 3 (my-or false 3 (err Never)))
+    (let* (v 5)
+        (assert= 5 (my-or false v)))
+    (assert= 3 (bind ((a 1) (b 2)) => (+ a b)))
+    (assert= 0 (count-forms))
+    (assert= 3 (count-forms a (b c) d)))
+
+(deftest syntax-rules-errors
+    (assert-err (bind ((a 1)) 3) NoMatchingRule)
+    (assert-err (syntax-rules 1) InvalidArgs@syntax-rules)
+    (assert-err (syntax-rules '() '(a b)) InvalidArgs@syntax-rules)
+    (assert-err ((syntax-rules '() '((_ x ...) x)) 1 2) InvalidTemplate))
+
+(defmacro! my-do forms `((\ () (do ~@forms))))
+
+(deftest splicing
+    (let* (xs [1 2 3])
+        (do
+            (assert= '(a 1 2 3 b) `(a ~@xs b))
+            (assert= '(a (b 1 2 3) c) `(a (b ~@xs) ~@[] c))
+            (assert= '(1 2 3 1 2 3) `(~@xs ~@xs))
+            (assert= '(splice-unquote xs) '~@xs)))
+    (assert= 3 (my-do 1 2 3)))
+
+(deftest nested-quasiquote
+    (let* (n 2 xs [1 2])
+        (do
+            (assert= '(a (quasiquote (b (unquote c)))) `(a `(b ~c)))
+            (assert= '(a (quasiquote (b (unquote 2)))) `(a `(b ~~n)))
+            (assert= '(quasiquote ((splice-unquote xs) (unquote 1 2))) ``(~@xs ~~@xs))
+            (assert= '(b 2) (eval ``(b ~~n))))))
+
+(deftest splicing-errors
+    (assert-err `(a ~@5) InvalidArgs@splice-unquote)
+    (assert-err `(a ~@nil) InvalidArgs@splice-unquote)
+    (assert-err `~@[1] InvalidArgs@splice-unquote)
+    (assert-err `(a (unquote)) InvalidArgs@unquote))
+
+(defmacro! twice (form) `(do ~form ~form))
+(defmacro! unless (condition body) `(if ~condition nil ~body))
+
+(deftest macroexpand-1
+    (assert= '(do (twice 1) (twice 1)) (macroexpand-1 '(twice (twice 1))))
+    (assert= '(+ 1 2) (macroexpand-1 '(+ 1 2)))
+    (assert= '(if a 1 (twice 2)) (macroexpand-1 '(if a 1 (twice 2))))
+    (assert= 5 (macroexpand-1 5))
+    (assert= false (eval (macroexpand-1 '(my-or))))
+    (assert-err (macroexpand-1) InvalidArgs@macroexpand-1))
+
+(deftest macroexpand
+    (assert= '(if a nil (twice b)) (macroexpand '(unless a (twice b))))
+    (assert= '(if (twice x) nil 1) (macroexpand '(unless (twice x) 1)))
+    (assert= 'x (macroexpand 'x))
+    (assert-err (macroexpand '(bind 1)) NoMatchingRule)
+    (assert-err (macroexpand) InvalidArgs@macroexpand))
+
+(deftest macroexpand-all
+    (assert= '(if a nil (do b b)) (macroexpand-all '(unless a (twice b))))
+    (assert= '(let* (x (do 1 1)) (\ (twice) (quote (twice 2))))
+        (macroexpand-all '(let* (x (twice 1)) (\ (twice) '(twice 2)))))
+    (assert= '(def! y (f (do 1 1) `(twice ~z))) (macroexpand-all '(def! y (f (twice 1) `(twice ~z)))))
+    (let* (expanded (macroexpand-all '(with-doubled d 2 (twice d))))
+        (assert= 'do (first (nth expanded 2))))
+    (assert= 4 (eval (macroexpand-all '(let* (n 2) (unless false (+ n n))))))
+    (assert= '(list `(twice ~(do x x) ~@(do y y) `(~(twice z))))
+        (macroexpand-all '(list `(twice ~(twice x) ~@(twice y) `(~(twice z))))))
+    (assert= '(\ (twice) (twice 1)) (macroexpand-all '(\ (twice) (twice 1))))
+    (assert= '(let* (a (do 1 1) twice a) (twice (if b nil 2)))
+        (macroexpand-all '(let* (a (twice 1) twice a) (twice (unless b 2)))))
+    (assert= '(doseq ((twice) (do l l)) (twice 1)) (macroexpand-all '(doseq ((twice) (twice l)) (twice 1))))
+    (assert= '(try* (do 1 1) (catch* twice (twice 2))) (macroexpand-all '(try* (twice 1) (catch* twice (twice 2)))))
+    (assert-err (macroexpand-all 1 2) InvalidArgs@macroexpand-all))
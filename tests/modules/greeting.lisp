@@ -0,0 +1,5 @@
+## A module imported by modules_test.lisp
+
+(def! greeting "hello")
+
+(defun! greet (name) (str greeting ", " name))
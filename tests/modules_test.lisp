@@ -0,0 +1,19 @@
+## Tests for import and require. Paths are relative to this file.
+
+(deftest import-namespace
+    (import "modules/greeting.lisp")
+    (assert= "hello" greeting/greeting)
+    (assert= "hello, world" (greeting/greet "world")))
+
+(deftest import-names
+    (import "modules/greeting.lisp" (greet))
+    (assert= "hello, you" (greet "you"))
+    (assert-err greeting UnresolvedIdentifier))
+
+(deftest require-everything
+    (require "modules/greeting.lisp")
+    (assert= "hello" greeting))
+
+(deftest import-errors
+    (assert-err (import "modules/greeting.lisp" (missing)) UndefinedImport)
+    (assert-err (import "modules/missing.lisp") ImportError))
@@ -0,0 +1,62 @@
+## Tests for the numeric tower: ints, bignums, ratios, and floats
+
+(deftest int-arithmetic
+    (assert= 5 (+ 2 3))
+    (assert= -1 (- 2 3))
+    (assert= -4 (- 4))
+    (assert= 24 (* 2 3 4))
+    (assert= 3 (quot 7 2))
+    (assert= 1 (% 7 2)))
+
+(deftest ints-promote-to-bignums
+    (assert= 340282366920938463463374607431768211456 (* 18446744073709551616 18446744073709551616))
+    (assert= 'int (type (* 18446744073709551616 18446744073709551616)))
+    (assert= 1 (- 170141183460469231731687303715884105728 170141183460469231731687303715884105727))
+    (assert= 'int (type (- 170141183460469231731687303715884105728 1))))
+
+(deftest division-is-exact
+    (assert= 2 (/ 4 2))
+    (assert= 1/3 (/ 1 3))
+    (assert= 'ratio (type (/ 1 3)))
+    (assert= 1/2 (/ 2))
+    (assert= 1 (+ 1/3 2/3))
+    (assert= 1 (numerator 1/3))
+    (assert= 3 (denominator 1/3)))
+
+(deftest floats-are-contagious
+    (assert= 2.5 (/ 5.0 2))
+    (assert= 1.5 (+ 1 1/2 0.0))
+    (assert= 'float (type (* 1.0 2)))
+    (assert= 0.5 (float 1/2)))
+
+(deftest comparisons-cross-types
+    (assert= true (== 1 1.0))
+    (assert= false (= 1 1.0))
+    (assert= true (< 1/3 0.5))
+    (assert= true (>= 2 2))
+    (assert= false (> 1/3 1/2)))
+
+(deftest rounding
+    (assert= 3 (round 5/2))
+    (assert= 2 (floor 2.7))
+    (assert= 3 (ceil 2.1))
+    (assert= -2 (int -2.9)))
+
+(deftest division-by-zero
+    (assert-err (/ 1 0) DivideByZero)
+    (assert-err (quot 1 0) DivideByZero)
+    (assert-err (% 1 0) DivideByZero))
+
+(deftest powers
+    (assert= 1024 (^ 2 10))
+    (assert= 1/8 (^ 2 -3))
+    (assert= 1267650600228229401496703205376 (^ 2 100))
+    (assert= 10 (choose 5 2)))
+
+(deftest number-predicate
+    (assert= true (number? 2))
+    (assert= true (number? 2.5))
+    (assert= true (number? 18446744073709551616))
+    (assert= false (number? "a"))
+    (assert= false (number? nil))
+    (assert= false (number? (list 1 2))))
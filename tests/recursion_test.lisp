@@ -0,0 +1,39 @@
+## Tests for tail calls, loop and recur, and the recursion depth limit
+
+(defun! count-down (n) (if (= n 0) 'done (count-down (- n 1))))
+(defun! ping (n) (if (= n 0) 'ping (apply pong (list (- n 1)))))
+(defun! pong (n) (if (= n 0) 'pong (apply ping (list (- n 1)))))
+(defun! depth (n) (if (= n 0) 0 (+ 1 (depth (- n 1)))))
+
+(deftest tail-calls
+    (assert= 'done (count-down 100000))
+    (assert= 'done (apply count-down (list 100000)))
+    (assert= 'pong (ping 100001))
+    (assert= 'ping (ping 100000)))
+
+(deftest loop-and-recur
+    (assert= 1024 (loop (i 0 acc 1) (if (= i 10) acc (recur (+ i 1) (* acc 2)))))
+    (assert= 5000050000 (loop (i 0 total 0) (if (> i 100000) total (recur (+ i 1) (+ total i)))))
+    (assert= 55 (loop ((a b) [0 1] n 0) (if (= n 10) a (recur [b (+ a b)] (+ n 1)))))
+    (assert-err (loop (i 0) (recur 1 2)) InvalidArgs@recur)
+    (assert-err (loop (i) i) InvalidArgs@loop))
+
+(deftest recur-in-lambdas
+    (assert= 'done ((\ (n) (if (= n 0) 'done (recur (- n 1)))) 100000))
+    (assert= (list 1000 200 300) (map [1 2 3] (\ (n) (if (> n 100) n (recur (* n 10)))))))
+
+(deftest recur-outside-tail-position
+    (assert-err (+ 1 (recur 1)) RecurNotInTailPosition)
+    (assert-err (loop (i 0) (+ 1 (recur i))) RecurNotInTailPosition))
+
+(deftest depth-limit
+    (assert= 3000 (depth 3000))
+    (let* (limit (max-depth))
+        (do
+            (max-depth 100)
+            (let* (result (try* (depth 1000) (catch* StackOverflow e e)))
+                (do
+                    (max-depth limit)
+                    (assert= (list 'StackOverflow 100) result)))
+            (assert= limit (max-depth))
+            (assert-err (max-depth 0) InvalidArgs@max-depth))))
@@ -0,0 +1,42 @@
+## Tests for the regular expression builtins
+
+(deftest compiled-regexes
+    (assert= 'regex (type (regex "a+")))
+    (assert= true (regex? (regex "a+")))
+    (assert= (regex "a+" "i") (regex "a+" "i"))
+    (assert= false (= (regex "a+") (regex "a+" "i")))
+    (assert-err (regex "(") InvalidRegex)
+    (assert-err (regex "a" "q") InvalidRegexFlag))
+
+(deftest matching
+    (assert= "ab" (:text (re-match "a|ab" "ab")))
+    (assert= nil (re-match "b" "abc"))
+    (assert= (list "2024" "01") (:groups (re-match (regex "(\\d+)-(\\d+)") "2024-01")))
+    (assert= "HELLO" (:text (re-match (regex "hello" "i") "HELLO")))
+    (assert= "a" (:text (re-match (regex "a # letter" "x") "a")))
+    (assert= "ab" (:text (re-match (regex "a # letter\n b" "x") "ab")))
+    (assert= nil (re-match "a" "a\n")))
+
+(deftest finding
+    (def! m (re-find "(?<word>[a-z]+)(\\d)?" "12 héllo 3"))
+    (assert= "h" (:text m))
+    (assert= 3 (:start m))
+    (assert= 4 (:end m))
+    (assert= (list "h" nil) (:groups m))
+    (assert= "h" (:word (:named m)))
+    (assert= 3 (:start (re-find "l+" "héllo" 3)))
+    (assert= nil (re-find "z" "abc")))
+
+(deftest finding-all
+    (assert= (list "1" "22" "333") (map (re-find-all "\\d+" "1 22 333") (\ (m) (:text m))))
+    (assert= 4 (count (re-find-all "x*" "abc")))
+    (assert= 2 (count (re-find-all (regex "^a" "m") "a\na"))))
+
+(deftest replacing
+    (assert= "01-2024" (re-replace "(\\d+)-(\\d+)" "2024-01" "$2-$1"))
+    (assert= "b a" (re-replace "(?<x>\\w) (?<y>\\w)" "a b" "${y} ${x}"))
+    (assert= "2 4 6" (re-replace "\\d" "1 2 3" (\ (m) (* 2 (int (:text m)))))))
+
+(deftest splitting
+    (assert= (list "a" "b" "c") (re-split ",\\s*" "a, b,c"))
+    (assert= '(("1") ("2")) (findall "\\d" "1 2")))
@@ -0,0 +1,68 @@
+## Tests for the native sequence functions
+
+(deftest mapping
+    (assert= (list 2 4 6) (map [1 2 3] (\ (x) (* x 2))))
+    (assert= [] (map [] (\ (x) (* x 2))))
+    (assert= (list "A" "B") (map "ab" upper))
+    (assert= (list 1 2) (map (list {:a 1} {:a 2}) :a))
+    (assert-err (map 5 upper) InvalidArgs@map))
+
+(deftest filtering
+    (assert= (list 2 4) (filter [1 2 3 4] even?))
+    (assert= "hllo" (filter "hello" (\ (c) (not (= c "e")))))
+    (assert= [] (filter [1 3] even?)))
+
+(deftest reducing
+    (assert= 10 (reduce [1 2 3 4] +))
+    (assert= 16 (reduce [1 2 3 4] + 6))
+    (assert= 6 (reduce [] + 6))
+    (assert= "cba" (reduce "abc" (\ (acc c) (str c acc))))
+    (assert-err (reduce [] +) EmptySequence))
+
+(deftest zipping
+    (assert= (list (list 1 "a") (list 2 "b")) (zip [1 2 3] "ab"))
+    (assert= [] (zip)))
+
+(deftest taking-and-dropping
+    (assert= (list 1 2) (take [1 2 3] 2))
+    (assert= (list 1 2 3) (take [1 2 3] 10))
+    (assert= "he" (take "hello" 2))
+    (assert= (list 3) (drop [1 2 3] 2))
+    (assert= [] (drop [1 2 3] 5))
+    (assert= "llo" (drop "hello" 2))
+    (assert-err (take [1 2] -1) InvalidArgs@take))
+
+(deftest sorting
+    (assert= (list 1 3/2 2.5 3) (sort [3 2.5 1 3/2]))
+    (assert= "ehllo" (sort "hello"))
+    (assert= (list :a :b) (sort [:b :a]))
+    (assert= (list "ccc" "bb" "a") (sort ["a" "ccc" "bb"] (\ (s) (- 0 (count s)))))
+    (assert= (list (list 1 2) (list 1 3)) (sort [[1 3] [1 2]]))
+    (assert= (list (list 1 "b") (list 1 "a")) (sort [[1 "b"] [1 "a"]] first))
+    (assert-err (sort [1 "a"]) Incomparable))
+
+(deftest reversing-and-concatenating
+    (assert= (list 3 2 1) (reverse [1 2 3]))
+    (assert= "olleh" (reverse "hello"))
+    (assert= (list 1 2 3 4) (concat [1] [2 3] [] [4]))
+    (assert= "abc" (concat "a" "bc"))
+    (assert= (list 1 "a" "b") (concat [1] "ab")))
+
+(deftest flattening
+    (assert= (list 1 2 3 4) (flatten [1 [2 [3 []]] [[4]]]))
+    (assert= (list "a" "b") (flatten "ab")))
+
+(deftest grouping
+    (let* (groups (group-by [1 2 3 4 5] even?))
+        (do
+            (assert= (list 2 4) (get groups true))
+            (assert= (list 1 3 5) (get groups false)))))
+
+(deftest long-sequences
+    (let* (big (range 0 20000))
+        (do
+            (assert= 19999 (last (map big (\ (x) x))))
+            (assert= 10000 (count (filter big even?)))
+            (assert= 199990000 (reduce big +))
+            (assert= 0 (last (reverse big)))
+            (assert= 19999 (last (sort (reverse big)))))))
@@ -0,0 +1,38 @@
+## Tests for sets and the set builtins
+
+(deftest literals
+    (assert= true (set? #{1 2 3}))
+    (assert= 3 (count #{1 2 3 3}))
+    (assert= #{} (set []))
+    (assert= #{1 2} #{2 1})
+    (assert= #{2 3} (let* (x 2) #{x (+ x 1)}))
+    (assert= 'set (type #{})))
+
+(deftest membership
+    (assert= true (contains? #{1 "two" [3]} "two"))
+    (assert= true (contains? #{1 "two" [3]} [3]))
+    (assert= false (contains? #{1 2} 3))
+    (assert= true (contains? (set {a 1 b 2}) 'a)))
+
+(deftest adding-and-removing
+    (assert= #{1 2 3} (conj #{1} 2 3))
+    (assert= #{1} (disj #{1 2 3} 2 3 4))
+    (assert-err (disj [1 2] 1) InvalidArgs@disj))
+
+(deftest set-operations
+    (assert= #{1 2 3 4} (union #{1 2} #{2 3} #{4}))
+    (assert= #{} (union))
+    (assert= #{2} (intersection #{1 2} #{2 3}))
+    (assert= #{1} (difference #{1 2 3} #{2} #{3}))
+    (assert= true (subset? #{1 2} #{1 2 3}))
+    (assert= false (subset? #{1 4} #{1 2 3}))
+    (assert-err (union #{1} [2]) InvalidArgs@union)
+    (assert-err (intersection) InvalidArgs@intersection))
+
+(deftest sets-as-keys
+    (assert= 'found (get (assoc {} #{1 2} 'found) #{2 1}))
+    (assert= 1 (count (set [#{1 2} #{2 1}]))))
+
+(deftest printing
+    (assert= "#{1}" (str #{1}))
+    (assert= "#{}" (str #{})))
@@ -0,0 +1,56 @@
+## Tests for the functions and macros defined in stdlib.lisp
+
+(deftest lists
+    (assert= (list 1 2 3) (cons 1 (list 2 3)))
+    (assert= 1 (first (list 1 2 3)))
+    (assert= 3 (last (list 1 2 3)))
+    (assert= (list 2 3) (rest (list 1 2 3)))
+    (assert= 2 (nth (list 1 2 3) 1))
+    (assert= 3 (count (list 1 2 3)))
+    (assert= true (empty? [])))
+
+(deftest ranges
+    (assert= (list 0 1 2) (range 0 3))
+    (assert= [] (range 3 3))
+    (assert= (list 7 7) (repeat 7 2))
+    (assert= (list 3 4) (drop (range 1 5) 2))
+    (assert= (list 3 4) (trim (range 1 5) 2)))
+
+(deftest logic
+    (assert= false (not true))
+    (assert= true (not nil))
+    (assert= true (all? 1 2 3))
+    (assert= false (all? 1 false 3))
+    (assert= true (any? false nil 3))
+    (assert= false (any?))
+    (assert= true (bool 0)))
+
+(deftest number-predicates
+    (assert= true (even? 4))
+    (assert= true (odd? 3))
+    (assert= true (number? 1/2))
+    (assert= false (number? "1")))
+
+(deftest folds
+    (assert= 10 (sum (range 1 5)))
+    (assert= 24 (prod (range 1 5)))
+    (assert= 120 (fact 5)))
+
+(deftest tables
+    (def! t (assoc {} 'a 1 'b 2))
+    (assert= 1 (get t 'a))
+    (assert= nil (get t 'c))
+    (assert= true (contains? t 'b))
+    (assert= false (contains? (dissoc t 'b) 'b)))
+
+(deftest atoms
+    (def! a (atom 1))
+    (assert= 1 (set! a 2))
+    (assert= 2 (a))
+    (inspect! a (\ (x) (* x 10)))
+    (assert= 20 (a)))
+
+(deftest errors
+    (assert-err (err Custom 1 2) Custom)
+    (assert= (list 'Custom 1 2) (try* (err Custom 1 2) (catch* e e)))
+    (assert-err (undefined-function) UnresolvedIdentifier))
@@ -0,0 +1,59 @@
+## Tests for string literals
+
+(deftest escapes
+    (assert= 1 (count "\t"))
+    (assert= (chr 9) "\t")
+    (assert= (chr 13) "\r")
+    (assert= (chr 0) "\0")
+    (assert= (chr 34) "\"")
+    (assert= (chr 92) "\\")
+    (assert= (chr 10) "\n")
+    (assert= "A" "\x41")
+    (assert= (chr 128512) "\u{1F600}")
+    (assert= "é" "\u{e9}"))
+
+(deftest raw-strings
+    (assert= "a\\nb" r"a\nb")
+    (assert= "say \"hi\"" r#"say "hi""#)
+    (assert= "\"# inside" r##""# inside"##)
+    (assert= "two\nlines" r#"two
+lines"#))
+
+(deftest counting-characters
+    (assert= 5 (count "héllo"))
+    (assert= 1 (count "😀"))
+    (assert= (list "h" "é" "y") (chars "héy"))
+    (assert= 233 (ord "é"))
+    (assert= "é" (chr (ord "é")))
+    (assert-err (ord "ab") NotAChar))
+
+(deftest substrings
+    (assert= "ll" (substr "héllo" 2 4))
+    (assert= "llo" (substr "héllo" 2))
+    (assert= "" (substr "abc" 3))
+    (assert-err (substr "abc" 1 5) IndexOutOfRange)
+    (assert-err (substr "abc" 2 1) InvalidArgs@substr)
+    (assert= 2 (index-of "héllo" "ll"))
+    (assert= nil (index-of "hello" "z")))
+
+(deftest splitting-and-joining
+    (assert= (list "a" "b" "" "c") (split "a,b,,c" ","))
+    (assert= (list "a" "b" "c") (split "  a b\n c "))
+    (assert= "a, b, c" (join (list "a" "b" "c") ", "))
+    (assert= "123" (join (list 1 2 3)))
+    (assert= "" (join [] ",")))
+
+(deftest transforming
+    (assert= "x y" (trim "  x y \n"))
+    (assert= "ÉCOLE" (upper "école"))
+    (assert= "école" (lower "ÉCOLE"))
+    (assert= "a-b-c" (replace "a b c" " " "-"))
+    (assert= true (starts-with? "lisping" "lisp"))
+    (assert= false (ends-with? "lisping" "lisp")))
+
+(deftest formatting
+    (assert= "1 + 2 = 3" (format "{} + {} = {}" 1 2 (+ 1 2)))
+    (assert= "{x} is (1 \"a\")" (format "{{x}} is {}" (list 1 "a")))
+    (assert-err (format "{} {}" 1) FormatError)
+    (assert-err (format "{}" 1 2) FormatError)
+    (assert-err (format "{" 1) FormatError))
@@ -0,0 +1,14 @@
+## Tests for the stack traces attached to errors
+
+(defun! inner () (err Boom))
+(defun! outer () (do (inner) 'unreachable))
+(defun! local-error () (let* (x 1) (err Q x)))
+
+(deftest traces-start-at-the-innermost-frame
+    (let* (frames (try* (outer) (catch* Boom e trace)))
+        (do
+            (assert= 'inner (first (first frames)))
+            (assert= 'outer (first (nth frames 1))))))
+
+(deftest traces-include-the-raising-lambda
+    (assert= 'local-error (first (first (try* (local-error) (catch* Q e trace))))))